@@ -1,318 +1,445 @@
 use std::io::{self, Write};
-use std::sync::{Arc, Mutex};
+use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
-use std::sync::mpsc;
-use rodio::{OutputStream, Sink};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use crossterm::{
     cursor,
-    event::{poll, read, Event, KeyCode, KeyEventKind},
+    event::{read, Event, KeyCode, KeyEvent, KeyEventKind},
     execute,
     style::{Color, Print, ResetColor, SetForegroundColor},
     terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
 };
-use rand::Rng;
-use std::collections::HashMap;
+use metronome_engine::utilities::config::{Config, config_path, load_config_or_default, save_config};
+use metronome_engine::utilities::history::{history_path, record_history};
+use metronome_engine::utilities::profile::select_profile;
+use metronome_engine::utilities::mode::SettingValue;
+use metronome_engine::utilities::modes::{RandomMode, StandardMode, builtin_modes};
+use metronome_engine::utilities::timeline::TimelineEvent;
+use metronome_engine::{Metronome, MetronomeBuilder, MetronomeCommand, MetronomeEvent, MetronomeSnapshot, Sound};
 
-use crate::utilities::sound::{create_beep_sound, create_kick_sound, create_click_sound, create_cowbell_sound, create_hihat_sound, create_square_sound, create_triangle_sound, create_wood_block_sound};
+use crate::utilities::accent::AccentedDownbeats;
+use crate::utilities::args::{CliCommand, PlayOptions, parse_args};
+use crate::utilities::render::{render_all, save_wav};
+use metronome_engine::utilities::sample::{custom_sounds_dir, load_custom_sounds};
+use crate::utilities::sound::Sounds;
+use crate::utilities::stats::print_stats;
 mod utilities;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum SoundType {
-    Beep,
-    Kick,
-    Click,
-    Cowbell,
-    Hihat,
-    Square,
-    Triangle,
-    Woodblock
+// Plain beats play at this gain and the first beat of each bar above it
+const BEAT_GAIN: f32 = 0.6;
+const DEFAULT_RENDER_BARS: u32 = 8;
+
+// Everything the UI loop wakes up for, so it can block on a single channel
+enum UiEvent {
+    Key(KeyEvent),
+    // Forwarded from the engine thread
+    Engine(MetronomeEvent),
+    // Ctrl-C or a termination signal while printing beats with --no-tui
+    Interrupted,
 }
 
-impl SoundType {
-    const ALL: [SoundType; 8] = [
-        SoundType::Beep,
-        SoundType::Kick,
-        SoundType::Click,
-        SoundType::Cowbell,
-        SoundType::Hihat,
-        SoundType::Square,
-        SoundType::Triangle,
-        SoundType::Woodblock,
-    ];
-
-    fn next(&self) -> Self {
-        let current_idx = Self::ALL.iter().position(|&s| s == *self).unwrap();
-        Self::ALL[(current_idx + 1) % Self::ALL.len()]
-    }
+// This app's two modes come after the engine's built-in ones, which it does not offer
+fn app_mode(random: bool) -> usize {
+    builtin_modes().len() + usize::from(random)
+}
 
-    fn prev(&self) -> Self {
-        let current_idx = Self::ALL.iter().position(|&s| s == *self).unwrap();
-        Self::ALL[(current_idx + Self::ALL.len() - 1) % Self::ALL.len()]
+// What a run plays: the saved settings with the command line on top. While
+// playing, the engine's snapshot is the truth and this follows it.
+#[derive(Clone)]
+struct PlaySettings {
+    bpm: u32,
+    random_mode: bool,
+    random_count: u32,
+    sound: u32,
+    beats_per_bar: u32,
+    // Only shown; the click follows the beat either way
+    beat_unit: u32,
+    // Stops by itself after this many bars when set
+    bars: Option<u32>,
+}
+
+impl PlaySettings {
+    // Starts from the settings shared with the other frontends
+    fn from_config(config: &Config, sounds: &Sounds) -> Self {
+        let random_count = match config.mode_setting("Random", "count") {
+            Some(SettingValue::Number(count)) => (*count).clamp(10, 1000),
+            _ => 100,
+        };
+        Self {
+            bpm: config.bpm.clamp(30, 300),
+            random_mode: config.mode == "Random",
+            random_count,
+            sound: sounds.find(&config.sound).unwrap_or(Sound::Kick.key()),
+            beats_per_bar: config.beats_per_bar.clamp(1, 32),
+            beat_unit: 4,
+            bars: None,
+        }
     }
 
-    fn name(&self) -> &'static str {
-        match self {
-            SoundType::Beep => "Beep",
-            SoundType::Kick => "Kick",
-            SoundType::Click => "Click",
-            SoundType::Cowbell => "Cowbell",
-            SoundType::Hihat => "Hi-hat",
-            SoundType::Square => "Square",
-            SoundType::Triangle => "Triangle",
-            SoundType::Woodblock => "Woodblock",
+    // Command line flags win over the saved settings
+    fn apply_options(&mut self, options: &PlayOptions, sounds: &Sounds) -> Result<(), String> {
+        if let Some(bpm) = options.bpm {
+            self.bpm = bpm;
+        }
+        if let Some(meter) = options.meter {
+            self.beats_per_bar = meter.beats;
+            self.beat_unit = meter.unit;
+        }
+        if let Some(name) = &options.sound {
+            self.sound = sounds
+                .find(name)
+                .ok_or_else(|| format!("no sound called '{}'; run `cli-metronome list-sounds` to see them", name))?;
+        }
+        self.bars = options.bars;
+        self.random_mode |= options.random;
+        if let Some(count) = options.random_count {
+            self.random_count = count;
         }
+        Ok(())
     }
 
-    fn create_sound(&self) -> Vec<f32> {
-        match self {
-            SoundType::Beep => create_beep_sound(),
-            SoundType::Kick => create_kick_sound(),
-            SoundType::Click => create_click_sound(),
-            SoundType::Cowbell => create_cowbell_sound(),
-            SoundType::Hihat => create_hihat_sound(),
-            SoundType::Square => create_square_sound(),
-            SoundType::Triangle => create_triangle_sound(),
-            SoundType::Woodblock => create_wood_block_sound(),
+    // Picks up what the engine changed: the tempo in random mode, and what the keys sent it
+    fn follow(&mut self, snapshot: &MetronomeSnapshot) {
+        self.bpm = snapshot.bpm;
+        self.sound = snapshot.sound_type;
+        self.random_mode = snapshot.mode == app_mode(true);
+    }
+
+    // Modes this app does not have are left as they are. So is the meter: --meter
+    // only sets it for one run, since the config has no note value to go with it
+    fn capture(&self, config: &mut Config, sounds: &Sounds) {
+        config.bpm = self.bpm;
+        config.sound = sounds.name(self.sound).to_string();
+        if self.random_mode {
+            config.mode = "Random".to_string();
+        } else if config.mode == "Random" {
+            config.mode = "Standard".to_string();
         }
+        config.set_mode_setting("Random", "count", SettingValue::Number(self.random_count));
     }
-}
 
-#[derive(Clone)]
-struct MetronomeState {
-    bpm: u32,
-    is_running: bool,
-    random_mode: bool,
-    random_count: u32,
-    remaining_ticks: u32,
-    sound_type: SoundType,
-    ui_dirty: bool,
-}
+    // The engine set up with only what this app plays: its sound, meter and
+    // modes, without a kit or count-in
+    fn builder(&self, config: &Config, sounds: &Sounds) -> MetronomeBuilder {
+        let mut config = config.clone();
+        self.capture(&mut config, sounds);
+        config.beats_per_bar = self.beats_per_bar;
+        config.kit.clear();
+        config.count_in = false;
 
-impl MetronomeState {
-    fn new() -> Self {
-        Self {
-            bpm: 120,
-            is_running: false,
-            random_mode: false,
-            random_count: 100,
-            remaining_ticks: 0,
-            sound_type: SoundType::Kick,
-            ui_dirty: true,
+        let mut builder = Metronome::builder()
+            .mode(Box::new(AccentedDownbeats::new(Box::new(StandardMode), self.beats_per_bar)))
+            .mode(Box::new(AccentedDownbeats::new(Box::new(RandomMode::default()), self.beats_per_bar)));
+        for custom_sound in sounds.custom() {
+            builder = builder.custom_sound(custom_sound.samples.clone());
         }
+        builder
+            .config(&config, |name| sounds.find(name))
+            .select_mode(app_mode(self.random_mode))
+            .volume((BEAT_GAIN * 100.0) as u32)
+    }
+
+    // The last beat of the last bar, for a run with --bars
+    fn is_last_beat(&self, event: &TimelineEvent) -> bool {
+        self.bars.is_some_and(|bars| event.bar + 1 >= bars && event.beat + 1 >= self.beats_per_bar)
     }
 }
 
-enum AudioCommand {
-    PlayTick(Vec<f32>),
-    Stop,
+fn load_settings() -> Config {
+    let (config, error) = load_config_or_default();
+    if let Some(message) = error {
+        eprintln!("{}", message);
+    }
+    config
 }
 
-struct SoundCache {
-    sounds: HashMap<SoundType, Vec<f32>>,
+// Writes the config file when anything in it changed
+fn save_settings(settings: &PlaySettings, sounds: &Sounds, config: &mut Config) {
+    let mut updated = config.clone();
+    settings.capture(&mut updated, sounds);
+    if updated == *config {
+        return;
+    }
+    *config = updated;
+    if let Some(path) = config_path()
+        && let Err(message) = save_config(&path, config)
+    {
+        eprintln!("Failed to save settings: {}", message);
+    }
 }
 
-impl SoundCache {
-    fn new() -> Self {
-        let mut sounds = HashMap::new();
-        for &sound_type in &SoundType::ALL {
-            sounds.insert(sound_type, sound_type.create_sound());
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args();
+    if let Some(name) = &args.profile {
+        select_profile(name)?;
+    }
+    if let CliCommand::Stats = args.command {
+        return print_stats();
+    }
+
+    let mut custom_sounds = Vec::new();
+    if let Some(dir) = custom_sounds_dir() {
+        let (sounds, errors) = load_custom_sounds(&dir);
+        for error in errors {
+            eprintln!("Skipping custom sound {}", error);
         }
-        Self { sounds }
+        custom_sounds = sounds;
     }
+    let sounds = Sounds::new(custom_sounds);
 
-    fn get_sound(&self, sound_type: SoundType) -> &Vec<f32> {
-        &self.sounds[&sound_type]
+    match args.command {
+        CliCommand::Play { options, no_tui } => play(&options, no_tui, &sounds),
+        CliCommand::Render { options, output } => render(&options, &output, &sounds),
+        CliCommand::ListSounds => {
+            for name in sounds.names() {
+                println!("{}", name);
+            }
+            Ok(())
+        }
+        CliCommand::Stats => Ok(()),
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let state = Arc::new(Mutex::new(MetronomeState::new()));
-    let sound_cache = Arc::new(SoundCache::new());
-    
-    let (tick_tx, tick_rx) = mpsc::channel();
-    let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>();
-    
-    let (_stream, stream_handle) = OutputStream::try_default()?;
-    let sink = Sink::try_new(&stream_handle)?;
-    
-    let state_clone = Arc::clone(&state);
-    let sound_cache_clone = Arc::clone(&sound_cache);
-    let tick_tx_clone = tick_tx.clone();
-    let audio_tx_clone = audio_tx.clone();
-    
+fn play(options: &PlayOptions, no_tui: bool, sounds: &Sounds) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = load_settings();
+    let mut settings = PlaySettings::from_config(&config, sounds);
+    settings.apply_options(options, sounds)?;
+
+    let metronome = settings.builder(&config, sounds).build()?;
+    // Sessions are saved as they end, the last one when the metronome is dropped
+    if let Some(path) = history_path() {
+        record_history(&metronome, path, |message| eprintln!("{}", message));
+    }
+
+    let (ui_tx, ui_rx) = mpsc::channel();
+    let engine_tx = ui_tx.clone();
+    metronome.on_event(move |event| engine_tx.send(UiEvent::Engine(event.clone())).is_ok());
+
+    if no_tui {
+        // Stopping with Ctrl-C still saves the session and settings
+        forward_interrupts(ui_tx)?;
+        return play_plain(&metronome, &mut settings, &ui_rx, sounds, &mut config);
+    }
+
+    // Terminal input blocks in its own thread and is forwarded to the UI loop
     thread::spawn(move || {
-        metronome_loop(state_clone, sound_cache_clone, tick_tx_clone, audio_tx_clone);
+        while let Ok(event) = read() {
+            let Event::Key(key_event) = event else {
+                continue;
+            };
+            if ui_tx.send(UiEvent::Key(key_event)).is_err() {
+                break;
+            }
+        }
     });
-    
+
     enable_raw_mode()?;
     let mut last_ui_update = Instant::now();
     const UI_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
-    
+    let mut ui_dirty = true;
+    // Bar and beat of the last click since starting
+    let mut position = None;
+
     loop {
-        let should_update_ui = {
-            let state_guard = state.lock().unwrap();
-            state_guard.ui_dirty && last_ui_update.elapsed() >= UI_UPDATE_INTERVAL
-        };
-        
-        if should_update_ui {
-            display_ui(&state)?;
-            {
-                let mut state_guard = state.lock().unwrap();
-                state_guard.ui_dirty = false;
-            }
+        let until_ui_update = UI_UPDATE_INTERVAL.saturating_sub(last_ui_update.elapsed());
+
+        if ui_dirty && until_ui_update.is_zero() {
+            // By the time of a redraw the engine has taken the last key's command,
+            // so its snapshot has the settings to show and save
+            let snapshot = metronome.snapshot();
+            settings.follow(&snapshot);
+            display_ui(&snapshot, &settings, position, sounds)?;
+            save_settings(&settings, sounds, &mut config);
+            ui_dirty = false;
             last_ui_update = Instant::now();
+            continue;
         }
 
-        if let Ok(cmd) = audio_rx.try_recv() {
-            match cmd {
-                AudioCommand::PlayTick(sound_data) => {
-                    let source = rodio::buffer::SamplesBuffer::new(1, 44100, sound_data);
-                    sink.append(source);
+        // Sleep until the next key or engine event; a pending redraw bounds the wait
+        let ui_event = if ui_dirty {
+            match ui_rx.recv_timeout(until_ui_update) {
+                Ok(ui_event) => ui_event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match ui_rx.recv() {
+                Ok(ui_event) => ui_event,
+                Err(_) => break,
+            }
+        };
+
+        match ui_event {
+            UiEvent::Engine(MetronomeEvent::Beat { event, .. }) => {
+                position = Some((event.bar, event.beat));
+                if settings.is_last_beat(&event) {
+                    metronome.stop();
                 }
-                AudioCommand::Stop => break,
+                ui_dirty = true;
             }
-        }
-        
-        if let Ok(_) = tick_rx.try_recv() {
-            let mut state_guard = state.lock().unwrap();
-            state_guard.ui_dirty = true;
-        }
-        
-        if poll(Duration::from_millis(16))? {
-            match read()? {
-                Event::Key(key_event) => {
-                    if key_event.kind == KeyEventKind::Press {
-                        let mut needs_ui_update = true;
-                        match key_event.code {
-                            KeyCode::Char('q') => {
-                                let _ = audio_tx.send(AudioCommand::Stop);
-                                break;
-                            }
-                            KeyCode::Char(' ') => toggle_metronome(&state),
-                            KeyCode::Char('r') => toggle_random_mode(&state),
-                            KeyCode::Up => adjust_bpm(&state, 5),
-                            KeyCode::Down => adjust_bpm(&state, -5),
-                            KeyCode::Right => adjust_bpm(&state, 1),
-                            KeyCode::Left => adjust_bpm(&state, -1),
-                            KeyCode::Char('+') => adjust_random_count(&state, 10),
-                            KeyCode::Char('-') => adjust_random_count(&state, -10),
-                            KeyCode::Char('s') => cycle_sound(&state, true),
-                            KeyCode::Char('a') => cycle_sound(&state, false),
-                            KeyCode::Char('t') => {
-                                test_current_sound(&state, &sound_cache, &audio_tx);
-                                needs_ui_update = false;
-                            }
-                            _ => needs_ui_update = false,
-                        }
-                        
-                        if needs_ui_update {
-                            let mut state_guard = state.lock().unwrap();
-                            state_guard.ui_dirty = true;
+            UiEvent::Engine(MetronomeEvent::Started { .. }) => {
+                position = None;
+                ui_dirty = true;
+            }
+            UiEvent::Engine(_) => ui_dirty = true,
+            // Raw mode turns Ctrl-C into a key press, so no signal reaches the interface
+            UiEvent::Interrupted => break,
+            UiEvent::Key(key_event) => {
+                if key_event.kind == KeyEventKind::Press {
+                    let mut needs_ui_update = true;
+                    match key_event.code {
+                        KeyCode::Char('q') => break,
+                        KeyCode::Char(' ') => toggle_metronome(&metronome),
+                        KeyCode::Char('r') => toggle_random_mode(&metronome, &settings),
+                        KeyCode::Up => adjust_bpm(&metronome, 5),
+                        KeyCode::Down => adjust_bpm(&metronome, -5),
+                        KeyCode::Right => adjust_bpm(&metronome, 1),
+                        KeyCode::Left => adjust_bpm(&metronome, -1),
+                        KeyCode::Char('+') => adjust_random_count(&metronome, &mut settings, 10),
+                        KeyCode::Char('-') => adjust_random_count(&metronome, &mut settings, -10),
+                        KeyCode::Char('s') => cycle_sound(&metronome, sounds, true),
+                        KeyCode::Char('a') => cycle_sound(&metronome, sounds, false),
+                        KeyCode::Char('t') => {
+                            test_current_sound(&metronome, sounds);
+                            needs_ui_update = false;
                         }
+                        _ => needs_ui_update = false,
                     }
+                    ui_dirty |= needs_ui_update;
                 }
-                _ => {}
             }
         }
-        
-        thread::sleep(Duration::from_millis(1));
     }
-    
+
     disable_raw_mode()?;
+    // Random mode may have moved the tempo since the last redraw
+    settings.follow(&metronome.snapshot());
+    save_settings(&settings, sounds, &mut config);
     println!("\nMetronome stopped. Goodbye!");
     Ok(())
 }
 
-fn metronome_loop(
-    state: Arc<Mutex<MetronomeState>>,
-    sound_cache: Arc<SoundCache>,
-    tick_tx: mpsc::Sender<()>,
-    audio_tx: mpsc::Sender<AudioCommand>,
-) {
-    let mut last_tick = Instant::now();
-    let mut current_interval = Duration::from_millis(500);
-    let mut rng = rand::thread_rng();
-    
-    loop {
-        let should_tick = {
-            let state_guard = state.lock().unwrap();
-            if !state_guard.is_running {
-                thread::sleep(Duration::from_millis(10));
-                continue;
-            }
-            
-            let new_interval = Duration::from_millis(60000 / state_guard.bpm as u64);
-            if new_interval != current_interval {
-                current_interval = new_interval;
-            }
-            
-            last_tick.elapsed() >= current_interval
-        };
-        
-        if should_tick {
-            let sound_data = {
-                let state_guard = state.lock().unwrap();
-                sound_cache.get_sound(state_guard.sound_type).clone()
-            };
-            
-            let _ = audio_tx.send(AudioCommand::PlayTick(sound_data));
-            last_tick = Instant::now();
-            
-            {
-                let mut state_guard = state.lock().unwrap();
-                if state_guard.random_mode {
-                    if state_guard.remaining_ticks == 0 {
-                        state_guard.remaining_ticks = state_guard.random_count;
-                    }
-                    
-                    state_guard.remaining_ticks -= 1;
-                    
-                    if state_guard.remaining_ticks == 0 {
-                        state_guard.bpm = rng.gen_range(60..=200);
-                        state_guard.remaining_ticks = state_guard.random_count;
-                    }
+// `--no-tui`: starts straight away and prints a line per beat until the bars are
+// played, or without --bars until Ctrl-C
+fn play_plain(
+    metronome: &Metronome,
+    settings: &mut PlaySettings,
+    ui_rx: &mpsc::Receiver<UiEvent>,
+    sounds: &Sounds,
+    config: &mut Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    metronome.start();
+    let beats_per_bar = settings.beats_per_bar;
+
+    while let Ok(ui_event) = ui_rx.recv() {
+        match ui_event {
+            UiEvent::Engine(MetronomeEvent::Beat { event, .. }) => {
+                let accent = if event.beat == 0 { "●" } else { "○" };
+                println!("Bar {:>3}  Beat {:>2}/{}  {}  {} BPM", event.bar + 1, event.beat + 1, beats_per_bar, accent, event.bpm);
+                if settings.is_last_beat(&event) {
+                    break;
                 }
             }
-            
-            let _ = tick_tx.send(());
-        } else {
-            let time_to_next_tick = current_interval.saturating_sub(last_tick.elapsed());
-            let sleep_time = time_to_next_tick.min(Duration::from_millis(5));
-            thread::sleep(sleep_time);
+            UiEvent::Interrupted => break,
+            UiEvent::Engine(_) | UiEvent::Key(_) => {}
         }
     }
+
+    metronome.stop();
+    settings.follow(&metronome.snapshot());
+    save_settings(settings, sounds, config);
+    Ok(())
+}
+
+// Sends UiEvent::Interrupted on the first SIGINT or SIGTERM
+#[cfg(unix)]
+fn forward_interrupts(ui_tx: mpsc::Sender<UiEvent>) -> io::Result<()> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            let _ = ui_tx.send(UiEvent::Interrupted);
+        }
+    });
+    Ok(())
+}
+
+// Ctrl-C ends the process here without saving the run
+#[cfg(not(unix))]
+fn forward_interrupts(_ui_tx: mpsc::Sender<UiEvent>) -> io::Result<()> {
+    Ok(())
+}
+
+// `cli-metronome render`: the same clicks through the engine's offline renderer, into a WAV file
+fn render(options: &PlayOptions, output: &Path, sounds: &Sounds) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_settings();
+    let mut settings = PlaySettings::from_config(&config, sounds);
+    settings.apply_options(options, sounds)?;
+    let bars = settings.bars.unwrap_or(DEFAULT_RENDER_BARS);
+
+    let mut renderer = settings.builder(&config, sounds).renderer();
+    renderer.set_bars(bars);
+    let samples = render_all(&mut renderer);
+
+    save_wav(output, &samples, renderer.sample_rate())?;
+    println!(
+        "Wrote {} bars of {}/{} with {} to {} ({:.1} s)",
+        bars,
+        settings.beats_per_bar,
+        settings.beat_unit,
+        sounds.name(settings.sound),
+        output.display(),
+        samples.len() as f64 / renderer.sample_rate() as f64,
+    );
+    Ok(())
 }
 
-fn display_ui(state: &Arc<Mutex<MetronomeState>>) -> Result<(), Box<dyn std::error::Error>> {
-    let state_guard = state.lock().unwrap();
-    
+fn display_ui(
+    snapshot: &MetronomeSnapshot,
+    settings: &PlaySettings,
+    position: Option<(u32, u32)>,
+    sounds: &Sounds,
+) -> Result<(), Box<dyn std::error::Error>> {
     execute!(
         io::stdout(),
         Clear(ClearType::All),
         cursor::MoveTo(0, 0),
     )?;
-    
+
     println!("🎵 CLI METRONOME 🎵\n");
-    
+
     execute!(
         io::stdout(),
         SetForegroundColor(Color::Cyan),
-        Print(format!("BPM: {}\n", state_guard.bpm)),
+        Print(format!("BPM: {}\n", snapshot.bpm)),
         ResetColor,
     )?;
-    
+
+    // Bar and beat of the last click while running
+    let position = match (snapshot.is_running, position) {
+        (true, Some((bar, beat))) => format!("  Bar {}  Beat {}", bar + 1, beat + 1),
+        _ => String::new(),
+    };
+    let length = match settings.bars {
+        Some(bars) => format!("  ({} bars)", bars),
+        None => String::new(),
+    };
+    println!("Meter: {}/{}{}{}", settings.beats_per_bar, settings.beat_unit, length, position);
+
     execute!(
         io::stdout(),
         Print("Sound: "),
         SetForegroundColor(Color::Magenta),
-        Print(format!("{}\n", state_guard.sound_type.name())),
+        Print(format!("{}\n", sounds.name(snapshot.sound_type))),
         ResetColor,
     )?;
-    
-    let status = if state_guard.is_running { "RUNNING" } else { "STOPPED" };
-    let status_color = if state_guard.is_running { Color::Green } else { Color::Red };
-    
+
+    let status = if snapshot.is_running { "RUNNING" } else { "STOPPED" };
+    let status_color = if snapshot.is_running { Color::Green } else { Color::Red };
+
     execute!(
         io::stdout(),
         Print("Status: "),
@@ -320,30 +447,30 @@ fn display_ui(state: &Arc<Mutex<MetronomeState>>) -> Result<(), Box<dyn std::err
         Print(format!("{}\n", status)),
         ResetColor,
     )?;
-    
-    if state_guard.random_mode {
+
+    if settings.random_mode {
         execute!(
             io::stdout(),
             SetForegroundColor(Color::Yellow),
             Print("🎲 RANDOM MODE ACTIVE\n"),
             ResetColor,
         )?;
-        
-        if state_guard.is_running {
-            execute!(
+
+        // The mode's own countdown to the next tempo
+        match (&snapshot.panel.progress, snapshot.is_running) {
+            (Some(progress), true) => execute!(
                 io::stdout(),
-                Print(format!("Remaining ticks: {}\n", state_guard.remaining_ticks)),
-            )?;
-        } else {
-            execute!(
+                Print(format!("{}\n", progress.label)),
+            )?,
+            _ => execute!(
                 io::stdout(),
                 SetForegroundColor(Color::DarkGrey),
                 Print("(Start metronome to begin countdown)\n"),
                 ResetColor,
-            )?;
+            )?,
         }
-        
-        println!("Random count setting: {}", state_guard.random_count);
+
+        println!("Random count setting: {}", settings.random_count);
     } else {
         execute!(
             io::stdout(),
@@ -352,7 +479,7 @@ fn display_ui(state: &Arc<Mutex<MetronomeState>>) -> Result<(), Box<dyn std::err
             ResetColor,
         )?;
     }
-    
+
     println!("\n📋 CONTROLS:");
     println!("  SPACE     - Start/Stop metronome");
     println!("  R         - Toggle random mode");
@@ -363,74 +490,57 @@ fn display_ui(state: &Arc<Mutex<MetronomeState>>) -> Result<(), Box<dyn std::err
     println!("  A         - Previous sound");
     println!("  T         - Test current sound");
     println!("  Q         - Quit");
-    
+
     println!("\n🔊 Available sounds:");
-    println!("  Beep • Kick • Click • Cowbell • Hi-hat • Square • Triangle • Woodblock");
-    
-    println!("\n💡 Random mode will change BPM every {} ticks", state_guard.random_count);
-    
+    let sound_names: Vec<&str> = sounds.names().collect();
+    println!("  {}", sound_names.join(" • "));
+
+    println!("\n💡 Random mode will change BPM every {} ticks", settings.random_count);
+
     io::stdout().flush()?;
-    
+
     Ok(())
 }
 
-fn toggle_metronome(state: &Arc<Mutex<MetronomeState>>) {
-    let mut state_guard = state.lock().unwrap();
-    state_guard.is_running = !state_guard.is_running;
-    
-    if state_guard.is_running && state_guard.random_mode && state_guard.remaining_ticks == 0 {
-        state_guard.remaining_ticks = state_guard.random_count;
+fn toggle_metronome(metronome: &Metronome) {
+    if metronome.snapshot().is_running {
+        metronome.stop();
+    } else {
+        metronome.start();
     }
 }
 
-fn toggle_random_mode(state: &Arc<Mutex<MetronomeState>>) {
-    let mut state_guard = state.lock().unwrap();
-    state_guard.random_mode = !state_guard.random_mode;
-    
-    if state_guard.random_mode {
-        if state_guard.is_running {
-            state_guard.remaining_ticks = state_guard.random_count;
-        }
-    } else {
-        state_guard.remaining_ticks = 0;
+// Random mode starts counting down from the count set here, which may have
+// changed while the standard mode played
+fn toggle_random_mode(metronome: &Metronome, settings: &PlaySettings) {
+    let random = metronome.snapshot().mode != app_mode(true);
+    metronome.send(MetronomeCommand::ChangeMode(app_mode(random)));
+    if random {
+        let value = SettingValue::Number(settings.random_count);
+        metronome.send(MetronomeCommand::UpdateModeSetting { key: "count", value });
     }
 }
 
-fn adjust_bpm(state: &Arc<Mutex<MetronomeState>>, change: i32) {
-    let mut state_guard = state.lock().unwrap();
-    let new_bpm = (state_guard.bpm as i32 + change).max(30).min(300) as u32;
-    state_guard.bpm = new_bpm;
+fn adjust_bpm(metronome: &Metronome, change: i32) {
+    let bpm = metronome.snapshot().bpm as i32 + change;
+    metronome.set_tempo(bpm.clamp(30, 300) as u32);
 }
 
-fn adjust_random_count(state: &Arc<Mutex<MetronomeState>>, change: i32) {
-    let mut state_guard = state.lock().unwrap();
-    let new_count = (state_guard.random_count as i32 + change).max(10).min(1000) as u32;
-    state_guard.random_count = new_count;
-    
-    if state_guard.random_mode && state_guard.is_running {
-        if new_count > state_guard.remaining_ticks {
-            state_guard.remaining_ticks = new_count;
-        }
+fn adjust_random_count(metronome: &Metronome, settings: &mut PlaySettings, change: i32) {
+    settings.random_count = (settings.random_count as i32 + change).clamp(10, 1000) as u32;
+    if metronome.snapshot().mode == app_mode(true) {
+        let value = SettingValue::Number(settings.random_count);
+        metronome.send(MetronomeCommand::UpdateModeSetting { key: "count", value });
     }
 }
 
-fn cycle_sound(state: &Arc<Mutex<MetronomeState>>, forward: bool) {
-    let mut state_guard = state.lock().unwrap();
-    state_guard.sound_type = if forward {
-        state_guard.sound_type.next()
-    } else {
-        state_guard.sound_type.prev()
-    };
+fn cycle_sound(metronome: &Metronome, sounds: &Sounds, forward: bool) {
+    let sound = sounds.cycle(metronome.snapshot().sound_type, forward);
+    metronome.send(MetronomeCommand::ChangeSoundType(sound));
 }
 
-fn test_current_sound(
-    state: &Arc<Mutex<MetronomeState>>, 
-    sound_cache: &Arc<SoundCache>,
-    audio_tx: &mpsc::Sender<AudioCommand>
-) {
-    let sound_data = {
-        let state_guard = state.lock().unwrap();
-        sound_cache.get_sound(state_guard.sound_type).clone()
-    };
-    let _ = audio_tx.send(AudioCommand::PlayTick(sound_data));
+fn test_current_sound(metronome: &Metronome, sounds: &Sounds) {
+    if let Some(samples) = sounds.samples(metronome.snapshot().sound_type) {
+        metronome.play(&samples, 1.0);
+    }
 }
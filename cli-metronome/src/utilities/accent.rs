@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::time::Duration;
+
+use metronome_engine::utilities::mode::{BarChange, MetronomeModeBehavior, ModePanel, Setting, SettingValue};
+use metronome_engine::utilities::timeline::{Pattern, TimelineEvent};
+
+/// One of the engine's modes with the first beat of every bar accented, as
+/// this app plays and renders it. Everything else is left to the mode.
+pub struct AccentedDownbeats {
+    mode: Box<dyn MetronomeModeBehavior>,
+    accents: Vec<bool>,
+}
+
+impl AccentedDownbeats {
+    pub fn new(mode: Box<dyn MetronomeModeBehavior>, beats_per_bar: u32) -> Self {
+        let mut accents = vec![false; beats_per_bar.max(1) as usize];
+        accents[0] = true;
+        Self { mode, accents }
+    }
+}
+
+impl MetronomeModeBehavior for AccentedDownbeats {
+    fn name(&self) -> &'static str {
+        self.mode.name()
+    }
+
+    fn icon(&self) -> &'static str {
+        self.mode.icon()
+    }
+
+    fn on_start(&mut self) {
+        self.mode.on_start();
+    }
+
+    fn pattern(&self) -> Pattern {
+        self.mode.pattern()
+    }
+
+    fn is_countdown(&self) -> bool {
+        self.mode.is_countdown()
+    }
+
+    fn on_bar(&mut self, bar: u32, bpm: u32) -> Result<Option<BarChange>, Box<dyn Error>> {
+        let mut change = self.mode.on_bar(bar, bpm)?.unwrap_or_default();
+        change.accent_pattern.get_or_insert_with(|| self.accents.clone());
+        Ok(Some(change))
+    }
+
+    fn on_event(&mut self, event: &TimelineEvent) -> bool {
+        self.mode.on_event(event)
+    }
+
+    fn on_tick(&mut self, elapsed: Duration) -> Option<Duration> {
+        self.mode.on_tick(elapsed)
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        self.mode.settings()
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        self.mode.apply_setting(key, value);
+    }
+
+    fn panel(&self) -> ModePanel {
+        self.mode.panel()
+    }
+}
@@ -0,0 +1,193 @@
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+use std::path::PathBuf;
+
+// Hours of playing at any tempo, and few enough that every beat counts in a u32
+const MAX_BARS: i64 = 10_000;
+
+// Beats per bar over the note value of a beat. The tempo counts beats
+// whatever their value, so the note value is only shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Meter {
+    pub beats: u32,
+    pub unit: u32,
+}
+
+// Anything left out keeps the value from the saved settings
+#[derive(Clone, Debug, Default)]
+pub struct PlayOptions {
+    pub bpm: Option<u32>,
+    pub meter: Option<Meter>,
+    pub sound: Option<String>,
+    pub bars: Option<u32>,
+    pub random: bool,
+    pub random_count: Option<u32>,
+}
+
+pub enum CliCommand {
+    // The interactive metronome, or a plain beat printout with --no-tui
+    Play { options: PlayOptions, no_tui: bool },
+    Render { options: PlayOptions, output: PathBuf },
+    ListSounds,
+    Stats,
+}
+
+pub struct CliArgs {
+    pub profile: Option<String>,
+    pub command: CliCommand,
+}
+
+pub fn parse_args() -> CliArgs {
+    let matches = command().get_matches();
+    let profile = matches.get_one::<String>("profile").cloned();
+    let command = match matches.subcommand() {
+        Some(("play", play)) => CliCommand::Play {
+            options: play_options(play),
+            no_tui: play.get_flag("no-tui"),
+        },
+        Some(("render", render)) => CliCommand::Render {
+            options: play_options(render),
+            output: render.get_one::<PathBuf>("output").cloned().unwrap_or_default(),
+        },
+        Some(("list-sounds", _)) => CliCommand::ListSounds,
+        Some(("stats", _)) => CliCommand::Stats,
+        // No subcommand plays, so the options also work without `play`
+        _ => CliCommand::Play {
+            options: play_options(&matches),
+            no_tui: matches.get_flag("no-tui"),
+        },
+    };
+    CliArgs { profile, command }
+}
+
+fn command() -> Command {
+    let no_tui = Arg::new("no-tui")
+        .long("no-tui")
+        .action(ArgAction::SetTrue)
+        .help("Print each beat instead of drawing the interface, for scripts and pipes");
+
+    Command::new("cli-metronome")
+        .about("A terminal metronome")
+        .args_conflicts_with_subcommands(true)
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .value_name("NAME")
+                .help("Use this profile's settings, presets and history"),
+        )
+        .args(play_args())
+        .arg(no_tui.clone())
+        .subcommand(
+            Command::new("play")
+                .about("Play the metronome (the default)")
+                .args(play_args())
+                .arg(no_tui),
+        )
+        .subcommand(
+            Command::new("render")
+                .about("Write the clicks to a WAV file instead of playing them")
+                .args(play_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                        .help("WAV file to write"),
+                ),
+        )
+        .subcommand(Command::new("list-sounds").about("List the built-in and custom sounds"))
+        .subcommand(Command::new("stats").about("Show practice time per day and week, streaks and tempo ranges"))
+}
+
+fn play_args() -> [Arg; 6] {
+    [
+        Arg::new("bpm")
+            .long("bpm")
+            .value_name("BPM")
+            .value_parser(value_parser!(u32).range(30..=300))
+            .help("Tempo, 30-300"),
+        Arg::new("meter")
+            .long("meter")
+            .value_name("BEATS/UNIT")
+            .value_parser(parse_meter)
+            .help("Time signature for this run, e.g. 4/4 or 7/8; the tempo counts beats of either value and the first of each bar is accented"),
+        Arg::new("sound")
+            .long("sound")
+            .value_name("NAME")
+            .help("Click sound, see list-sounds"),
+        Arg::new("bars")
+            .long("bars")
+            .value_name("COUNT")
+            .value_parser(value_parser!(u32).range(1..=MAX_BARS))
+            .help("Stop after this many bars, up to 10000"),
+        Arg::new("random")
+            .long("random")
+            .action(ArgAction::SetTrue)
+            .help("Start in random mode"),
+        Arg::new("random-count")
+            .long("random-count")
+            .value_name("TICKS")
+            .value_parser(value_parser!(u32).range(10..=1000))
+            .help("Ticks between random tempo changes, 10-1000"),
+    ]
+}
+
+fn play_options(matches: &ArgMatches) -> PlayOptions {
+    PlayOptions {
+        bpm: matches.get_one::<u32>("bpm").copied(),
+        meter: matches.get_one::<Meter>("meter").copied(),
+        sound: matches.get_one::<String>("sound").cloned(),
+        bars: matches.get_one::<u32>("bars").copied(),
+        random: matches.get_flag("random"),
+        random_count: matches.get_one::<u32>("random-count").copied(),
+    }
+}
+
+fn parse_meter(value: &str) -> Result<Meter, String> {
+    let (beats, unit) = value.split_once('/').ok_or("expected BEATS/UNIT, e.g. 7/8")?;
+    let beats: u32 = beats.trim().parse().map_err(|_| format!("'{}' is not a number of beats", beats))?;
+    let unit: u32 = unit.trim().parse().map_err(|_| format!("'{}' is not a note value", unit))?;
+    if !(1..=32).contains(&beats) {
+        return Err("use 1 to 32 beats per bar".to_string());
+    }
+    if !matches!(unit, 1 | 2 | 4 | 8 | 16 | 32) {
+        return Err("the note value must be 1, 2, 4, 8, 16 or 32".to_string());
+    }
+    Ok(Meter { beats, unit })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meters_take_beats_over_a_note_value() {
+        assert_eq!(parse_meter("4/4"), Ok(Meter { beats: 4, unit: 4 }));
+        assert_eq!(parse_meter("7/8"), Ok(Meter { beats: 7, unit: 8 }));
+        assert_eq!(parse_meter(" 12 / 16 "), Ok(Meter { beats: 12, unit: 16 }));
+        assert_eq!(parse_meter("1/1"), Ok(Meter { beats: 1, unit: 1 }));
+        assert_eq!(parse_meter("32/32"), Ok(Meter { beats: 32, unit: 32 }));
+    }
+
+    #[test]
+    fn note_values_are_powers_of_two() {
+        for meter in ["3/3", "3/5", "4/6", "4/0", "4/64"] {
+            assert!(parse_meter(meter).is_err(), "{} was accepted", meter);
+        }
+    }
+
+    #[test]
+    fn beats_stay_between_1_and_32() {
+        assert!(parse_meter("0/4").is_err());
+        assert!(parse_meter("33/4").is_err());
+    }
+
+    #[test]
+    fn anything_else_is_rejected() {
+        for meter in ["", "4", "4/", "/4", "four/4", "4/quarter", "-1/4", "4/4/4"] {
+            assert!(parse_meter(meter).is_err(), "{} was accepted", meter);
+        }
+    }
+}
@@ -1 +1,5 @@
-pub mod sound;
\ No newline at end of file
+pub mod accent;
+pub mod sound;
+pub mod stats;
+pub mod args;
+pub mod render;
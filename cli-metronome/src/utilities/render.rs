@@ -0,0 +1,38 @@
+use std::error::Error;
+use std::path::Path;
+
+use metronome_engine::Renderer;
+
+// Frames asked of the renderer at a time, about a second
+const CHUNK_FRAMES: usize = 44100;
+
+// Everything the renderer plays until it ends, including the last click's ring-out
+pub fn render_all(renderer: &mut Renderer) -> Vec<f32> {
+    let mut samples = Vec::new();
+    loop {
+        let start = samples.len();
+        samples.resize(start + CHUNK_FRAMES, 0.0);
+        let rendered = renderer.render(&mut samples[start..], |_, _| {});
+        samples.truncate(start + rendered);
+        if rendered < CHUNK_FRAMES {
+            return samples;
+        }
+    }
+}
+
+// 16-bit mono; the renderer already limits the mix like live playback
+pub fn save_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), Box<dyn Error>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
@@ -1,153 +1,74 @@
-use std::f32::consts::PI;
-use rand::Rng;
-
-pub fn create_click_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let duration_ms = 10;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
-
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let envelope = (-t * 50.0).exp();
-        let sample = (t * 2000.0 * 2.0 * PI).sin() * envelope * 0.5;
-        wave.push(sample);
-    }
-    wave
+use std::sync::Arc;
+
+use metronome_engine::utilities::loudness::match_loudness;
+use metronome_engine::utilities::sample::CustomSound;
+use metronome_engine::{Sound, builtin_sound};
+
+// In the order the interface cycles through them, under the names the config files share
+const BUILTIN_SOUNDS: [(Sound, &str); 8] = [
+    (Sound::Beep, "Beep"),
+    (Sound::Kick, "Kick"),
+    (Sound::Click, "Click"),
+    (Sound::Cowbell, "Cowbell"),
+    (Sound::HiHat, "Hi-hat"),
+    (Sound::Square, "Square"),
+    (Sound::Triangle, "Triangle"),
+    (Sound::Woodblock, "Woodblock"),
+];
+
+/// Every sound the app offers, by its key in the engine's sound cache: the
+/// built-in ones, then the custom sounds in the order the engine is given them.
+pub struct Sounds {
+    custom: Vec<CustomSound>,
+    // (key, name) in cycling order
+    names: Vec<(u32, String)>,
 }
 
-pub fn create_wood_block_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let duration_ms = 80;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
-
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let envelope = (-t * 15.0).exp();
-
-        let freq1 = 1200.0;
-        let freq2 = 800.0;
-        let sample1 = (t * freq1 * 2.0 * PI).sin() * 0.3;
-        let sample2 = (t * freq2 * 2.0 * PI).sin() * 0.2;
-        let sample = (sample1 + sample2) * envelope;
-        wave.push(sample);
+impl Sounds {
+    pub fn new(custom: Vec<CustomSound>) -> Self {
+        let builtin = BUILTIN_SOUNDS.iter().map(|(sound, name)| (sound.key(), name.to_string()));
+        let added = custom.iter().enumerate().map(|(i, sound)| (Sound::Custom(i as u32).key(), sound.name.clone()));
+        let names = builtin.chain(added).collect();
+        Self { custom, names }
     }
-    wave
-}
-
-pub fn create_cowbell_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let duration_ms = 120;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
 
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let envelope = (-t * 8.0).exp();
-
-        let fundamental = 800.0;
-        let sample = ((t * fundamental * 2.0 * PI).sin() * 0.4
-            + (t * fundamental * 2.4 * 2.0 * PI).sin() * 0.3
-            + (t * fundamental * 3.2 * 2.0 * PI).sin() * 0.2
-            + (t * fundamental * 4.1 * 2.0 * PI).sin() * 0.1)
-            * envelope;
-        wave.push(sample);
+    pub fn custom(&self) -> &[CustomSound] {
+        &self.custom
     }
-    wave
-}
-
-pub fn create_kick_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let duration_ms = 150;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
 
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let envelope = (-t * 12.0).exp();
-
-        let freq = 60.0 * (-t * 10.0).exp();
-        let sample = (t * freq * 2.0 * PI).sin() * envelope * 0.6;
-        wave.push(sample);
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|(_, name)| name.as_str())
     }
-    wave
-}
-
-pub fn create_hihat_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let duration_ms = 60;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
 
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    let mut rng = rand::thread_rng();
-
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let envelope = (-t * 25.0).exp();
-
-        let noise: f32 = rng.gen_range(-1.0..1.0);
-        let filtered_noise = noise * envelope * 0.3;
-
-        let high_freq = (t * 8000.0 * 2.0 * PI).sin() * envelope * 0.1;
-
-        let sample = filtered_noise + high_freq;
-        wave.push(sample);
+    pub fn name(&self, key: u32) -> &str {
+        self.names.iter().find(|(sound, _)| *sound == key).map_or("?", |(_, name)| name)
     }
-    wave
-}
-
-pub fn create_triangle_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let frequency = 800.0;
-    let duration_ms = 80;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
-
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let phase = (t * frequency) % 1.0;
 
-        let sample = if phase < 0.5 {
-            4.0 * phase - 1.0
-        } else {
-            3.0 - 4.0 * phase
-        } * 0.3;
-
-        wave.push(sample);
+    // Case and punctuation are ignored, so "hihat" finds "Hi-hat"
+    pub fn find(&self, name: &str) -> Option<u32> {
+        let simplify = |name: &str| name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+        let wanted = simplify(name);
+        self.names.iter().find(|(_, name)| simplify(name) == wanted).map(|(key, _)| *key)
     }
-    wave
-}
-
-pub fn create_square_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let frequency = 600.0;
-    let duration_ms = 60;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
 
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let phase = (t * frequency) % 1.0;
-
-        let envelope = (-t * 10.0).exp();
-        let sample = if phase < 0.5 { 1.0 } else { -1.0 } * 0.3 * envelope;
-        wave.push(sample);
+    // The sound after `key`, or before it, wrapping around
+    pub fn cycle(&self, key: u32, forward: bool) -> u32 {
+        let count = self.names.len();
+        let index = self.names.iter().position(|(sound, _)| *sound == key).unwrap_or(0);
+        let next = if forward { index + 1 } else { index + count - 1 };
+        self.names[next % count].0
     }
-    wave
-}
-
-pub fn create_beep_sound() -> Vec<f32> {
-    let sample_rate = 44100;
-    let frequency = 800.0;
-    let duration_ms = 50;
-    let samples = (sample_rate * duration_ms / 1000) as usize;
 
-    let mut wave: Vec<f32> = Vec::with_capacity(samples);
-    for i in 0..samples {
-        let t = i as f32 / sample_rate as f32;
-        let sample = (t * frequency * 2.0 * PI).sin() * 0.3;
-        wave.push(sample);
+    // The samples at the level the engine plays them, to audition a sound
+    pub fn samples(&self, key: u32) -> Option<Arc<[f32]>> {
+        let samples = match Sound::from_key(key)? {
+            Sound::Custom(i) => {
+                let mut samples = self.custom.get(i as usize)?.samples.clone();
+                match_loudness(&mut samples);
+                samples
+            }
+            _ => builtin_sound(key)?,
+        };
+        Some(samples.into())
     }
-    wave
 }
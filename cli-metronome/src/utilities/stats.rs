@@ -0,0 +1,69 @@
+use chrono::Local;
+use metronome_engine::utilities::history::{PracticeStats, TEMPO_RANGE_WIDTH, history_path, load_history, practice_stats};
+
+const DAYS_SHOWN: u32 = 7;
+const WEEKS_SHOWN: u32 = 4;
+const BAR_WIDTH: usize = 30;
+
+// `cli-metronome stats`: practice time per day and week, streaks and time per tempo range
+pub fn print_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let path = history_path().ok_or("no config directory available")?;
+    let (records, errors) = load_history(&path);
+    for error in errors {
+        eprintln!("Skipping session {}", error);
+    }
+    if records.is_empty() {
+        println!("No practice sessions recorded yet in {}", path.display());
+        return Ok(());
+    }
+
+    let stats = practice_stats(&records, Local::now().date_naive(), DAYS_SHOWN, WEEKS_SHOWN);
+    print_summary(&stats);
+
+    println!("\n📅 Last {} days:", DAYS_SHOWN);
+    let longest_day = stats.per_day.iter().map(|(_, seconds)| *seconds).fold(0.0, f64::max);
+    for (day, seconds) in &stats.per_day {
+        println!("  {}  {:<width$} {}", day.format("%a %d %b"), bar(*seconds, longest_day), minutes(*seconds), width = BAR_WIDTH);
+    }
+
+    println!("\n🗓️  Last {} weeks:", WEEKS_SHOWN);
+    let longest_week = stats.per_week.iter().map(|(_, seconds)| *seconds).fold(0.0, f64::max);
+    for (week, seconds) in &stats.per_week {
+        println!("  Week of {}  {:<width$} {}", week.format("%d %b"), bar(*seconds, longest_week), minutes(*seconds), width = BAR_WIDTH);
+    }
+
+    println!("\n🎚️  Time per tempo:");
+    let longest_range = stats.per_tempo.values().copied().fold(0.0, f64::max);
+    for (range, seconds) in &stats.per_tempo {
+        let label = format!("{}-{} BPM", range, range + TEMPO_RANGE_WIDTH - 1);
+        println!("  {:<12} {:<width$} {}", label, bar(*seconds, longest_range), minutes(*seconds), width = BAR_WIDTH);
+    }
+    Ok(())
+}
+
+fn print_summary(stats: &PracticeStats) {
+    println!("🎵 PRACTICE STATS 🎵\n");
+    println!("Sessions: {}", stats.sessions);
+    println!("Total practice: {}", minutes(stats.total_seconds));
+    println!("Current streak: {} day{}", stats.current_streak, if stats.current_streak == 1 { "" } else { "s" });
+    println!("Longest streak: {} day{}", stats.longest_streak, if stats.longest_streak == 1 { "" } else { "s" });
+    if stats.countdowns_completed > 0 {
+        println!("Countdowns completed: {}", stats.countdowns_completed);
+    }
+}
+
+fn bar(seconds: f64, longest: f64) -> String {
+    if longest <= 0.0 {
+        return String::new();
+    }
+    "█".repeat((seconds / longest * BAR_WIDTH as f64).round() as usize)
+}
+
+fn minutes(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{} min", minutes)
+    }
+}
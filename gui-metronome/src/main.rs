@@ -1,323 +1,516 @@
 use crossterm::{
     cursor,
-    event::{Event, KeyCode, KeyEventKind, poll, read},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyEvent, KeyEventKind, MouseButton, MouseEvent,
+        MouseEventKind, read,
+    },
     execute,
     terminal::{Clear, ClearType, disable_raw_mode, enable_raw_mode},
 };
-use rand::Rng;
-use rodio::{OutputStream, Sink};
+use metronome_engine::utilities::config::{Config, config_path, load_config_or_default, save_config};
+use metronome_engine::utilities::history::{history_path, record_history};
+use metronome_engine::utilities::kit::{builtin_kits, kits_manifest_path, load_kits};
+use metronome_engine::utilities::mode::{Setting, SettingValue};
+use metronome_engine::utilities::preset::{Preset, load_presets, presets_dir, save_preset};
+use metronome_engine::utilities::profile::{profile_from_args, select_profile};
+use metronome_engine::utilities::sample::{custom_sounds_dir, load_custom_sounds};
+use metronome_engine::utilities::script::{load_script_modes, scripts_dir};
+use metronome_engine::utilities::timeline::TimelineEvent;
+use metronome_engine::{Metronome, MetronomeCommand, MetronomeEvent, MetronomeSnapshot};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Position};
+use std::collections::VecDeque;
 use std::io::{self, BufWriter, Write};
-use std::sync::atomic::Ordering;
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::utilities::{
-    cache::{SoundCache, UICache},
-    display::display_enhanced_ui,
-    state::AtomicState,
+    cache::SoundCache,
+    display::{Screen, bar_volume, display_enhanced_ui, sound_at, volume_bar},
+    keys::{Action, KeyBindings, TEMPO_PRESETS},
+    layout::{Panel, ScreenLayout},
+    profiles::choose_profile,
+    settings::{Field, add_section, fields, remove_section, step},
+    sound_type::{SoundType, register_custom_sounds},
 };
 mod utilities;
 
-enum AudioCommand {
-    PlayTick(Vec<f32>),
-    Stop,
+// Everything the UI loop wakes up for, so it can block on a single channel
+enum UiEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Engine(MetronomeEvent),
+    // The terminal changed size, so the layout has to be worked out again
+    Resize,
+    // Something went wrong off the UI thread, to show in the footer
+    Problem(String),
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let state = Arc::new(AtomicState::new());
-    let sound_cache = Arc::new(SoundCache::new());
-    let ui_cache = Arc::new(Mutex::new(UICache::new()));
-
-    let (tick_tx, tick_rx) = mpsc::channel();
-    let (audio_tx, audio_rx) = mpsc::channel::<AudioCommand>();
+// Shown in the footer, newest last; only the latest are kept, so a problem
+// repeating every frame doesn't grow without end
+struct Problems(VecDeque<String>);
 
-    let (_stream, stream_handle) = OutputStream::try_default()?;
-    let sink = Sink::try_new(&stream_handle)?;
+impl Problems {
+    const LIMIT: usize = 20;
 
-    let state_clone = Arc::clone(&state);
-    let sound_cache_clone = Arc::clone(&sound_cache);
-    let tick_tx_clone = tick_tx.clone();
-    let audio_tx_clone = audio_tx.clone();
+    fn new() -> Self {
+        Self(VecDeque::new())
+    }
 
-    thread::spawn(move || {
-        metronome_loop(
-            state_clone,
-            sound_cache_clone,
-            tick_tx_clone,
-            audio_tx_clone,
-        );
-    });
+    fn push(&mut self, message: String) {
+        if self.0.len() == Self::LIMIT {
+            self.0.pop_front();
+        }
+        self.0.push_back(message);
+    }
+}
 
-    enable_raw_mode()?;
-    execute!(io::stdout(), cursor::Hide, Clear(ClearType::All))?;
+impl Extend<String> for Problems {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, messages: I) {
+        for message in messages {
+            self.push(message);
+        }
+    }
+}
 
-    let stdout = io::stdout();
-    let mut buffered_stdout = BufWriter::new(stdout);
+// Puts the terminal back however the UI ends, so an error doesn't leave the shell raw
+struct TerminalGuard;
 
-    let mut last_ui_update = Instant::now();
-    const UI_UPDATE_INTERVAL: Duration = Duration::from_millis(16);
-
-    let mut input_check_time = Instant::now();
-    const INPUT_CHECK_INTERVAL: Duration = Duration::from_millis(8);
+impl TerminalGuard {
+    fn enable() -> io::Result<Self> {
+        enable_raw_mode()?;
+        Ok(Self)
+    }
+}
 
-    loop {
-        let now = Instant::now();
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            DisableMouseCapture,
+            cursor::Show,
+            Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        );
+        let _ = disable_raw_mode();
+    }
+}
 
-        let should_update_ui = now.duration_since(last_ui_update) >= UI_UPDATE_INTERVAL
-            || state.ui_dirty.load(Ordering::Relaxed);
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = Metronome::builder();
+    // The picker and the UI own the screen, so nothing goes to stderr while they run
+    let mut problems = Problems::new();
+
+    if let Some(dir) = custom_sounds_dir() {
+        let (custom_sounds, errors) = load_custom_sounds(&dir);
+        for error in errors {
+            problems.push(format!("Skipping custom sound {}", error));
+        }
+        // In the same order as SoundType::Custom, so both sides agree on the keys
+        for custom_sound in &custom_sounds {
+            builder = builder.custom_sound(custom_sound.samples.clone());
+        }
+        register_custom_sounds(custom_sounds);
+    }
 
-        if should_update_ui {
-            display_enhanced_ui(&state, &ui_cache, &mut buffered_stdout)?;
-            state.ui_dirty.store(false, Ordering::Relaxed);
-            last_ui_update = now;
+    // --profile opens that profile straight away; otherwise the picker asks, and quitting it quits the app
+    let args: Vec<String> = std::env::args().collect();
+    let profile = match profile_from_args(&args) {
+        Some(name) => name,
+        None => match choose_profile()? {
+            Some(name) => name,
+            None => return Ok(()),
+        },
+    };
+    select_profile(&profile)?;
+
+    let (mut config, error) = load_config_or_default();
+    problems.extend(error);
+
+    // Kits and script modes are loaded like in gui-metronome2, so the saved ones are found
+    let resolve = |name: &str| SoundType::find(name).map(|sound| sound.key());
+    let mut kits = builtin_kits();
+    if let Some(path) = kits_manifest_path() {
+        match load_kits(&path, resolve) {
+            Ok(user_kits) => kits.extend(user_kits),
+            Err(message) => problems.push(format!("Failed to load sound kits: {}", message)),
+        }
+    }
+    let kit_names: Vec<String> = kits.iter().map(|kit| kit.name.clone()).collect();
+    if let Some(dir) = scripts_dir() {
+        let (script_modes, errors) = load_script_modes(&dir);
+        for error in errors {
+            problems.push(format!("Skipping script mode {}", error));
+        }
+        for mode in script_modes {
+            builder = builder.mode(mode);
         }
+    }
 
-        if let Ok(cmd) = audio_rx.try_recv() {
-            match cmd {
-                AudioCommand::PlayTick(sound_data) => {
-                    let source = rodio::buffer::SamplesBuffer::new(1, 44100, sound_data);
-                    sink.append(source);
-                }
-                AudioCommand::Stop => break,
+    let metronome = builder.kits(kits).config(&config, resolve).build()?;
+    let (ui_tx, ui_rx) = mpsc::channel();
+    // Subscribed first, so a session is written before the UI hears it ended
+    if let Some(path) = history_path() {
+        let problem_tx = ui_tx.clone();
+        record_history(&metronome, path, move |message| {
+            // Once the UI has closed the terminal is back to normal
+            if let Err(mpsc::SendError(UiEvent::Problem(message))) = problem_tx.send(UiEvent::Problem(message)) {
+                eprintln!("{}", message);
             }
+        });
+    }
+    let engine_events = metronome.subscribe();
+
+    let (bindings, key_errors) = KeyBindings::from_config(&config.keys);
+    problems.extend(key_errors);
+
+    // Number keys recall presets in name order
+    let mut presets = Vec::new();
+    if let Some(dir) = presets_dir() {
+        let errors;
+        (presets, errors) = load_presets(&dir);
+        for error in errors {
+            problems.push(format!("Skipping preset {}", error));
         }
+    }
+    let sound_cache = SoundCache::new();
 
-        if let Ok(_) = tick_rx.try_recv() {
-            state.ui_dirty.store(true, Ordering::Relaxed);
+    let engine_tx = ui_tx.clone();
+    thread::spawn(move || {
+        for event in engine_events {
+            if engine_tx.send(UiEvent::Engine(event)).is_err() {
+                break;
+            }
         }
+    });
 
-        if now.duration_since(input_check_time) >= INPUT_CHECK_INTERVAL {
-            if poll(Duration::from_millis(0))? {
-                match read()? {
-                    Event::Key(key_event) => {
-                        if key_event.kind == KeyEventKind::Press {
-                            let mut needs_ui_update = true;
-                            match key_event.code {
-                                KeyCode::Char('q') | KeyCode::Esc => {
-                                    let _ = audio_tx.send(AudioCommand::Stop);
-                                    break;
-                                }
-                                KeyCode::Char(' ') | KeyCode::Enter => toggle_metronome(&state),
-                                KeyCode::Char('r') => toggle_random_mode(&state),
-                                KeyCode::Up => adjust_bpm(&state, 5),
-                                KeyCode::Down => adjust_bpm(&state, -5),
-                                KeyCode::Right => adjust_bpm(&state, 1),
-                                KeyCode::Left => adjust_bpm(&state, -1),
-                                KeyCode::Char('+') | KeyCode::Char('=') => {
-                                    adjust_random_count(&state, 10)
-                                }
-                                KeyCode::Char('-') | KeyCode::Char('_') => {
-                                    adjust_random_count(&state, -10)
-                                }
-                                KeyCode::Char('s') | KeyCode::Char('n') => {
-                                    cycle_sound(&state, true)
-                                }
-                                KeyCode::Char('a') | KeyCode::Char('p') => {
-                                    cycle_sound(&state, false)
-                                }
-                                KeyCode::Char('t') => {
-                                    test_current_sound(&state, &sound_cache, &audio_tx);
-                                    needs_ui_update = false;
-                                }
-                                KeyCode::Char('v') => adjust_volume(&state, 10),
-                                KeyCode::Char('c') => adjust_volume(&state, -10),
-                                KeyCode::F(1) => set_preset_bpm(&state, 60),
-                                KeyCode::F(2) => set_preset_bpm(&state, 120),
-                                KeyCode::F(3) => set_preset_bpm(&state, 180),
-                                KeyCode::F(4) => set_preset_bpm(&state, 200),
-                                _ => needs_ui_update = false,
-                            }
-
-                            if needs_ui_update {
-                                state.ui_dirty.store(true, Ordering::Relaxed);
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+    // Terminal input blocks in its own thread and is forwarded to the UI loop
+    thread::spawn(move || {
+        while let Ok(event) = read() {
+            let ui_event = match event {
+                Event::Key(key_event) => UiEvent::Key(key_event),
+                // Plain movement is reported too, but nothing reacts to it
+                Event::Mouse(mouse_event) if mouse_event.kind != MouseEventKind::Moved => UiEvent::Mouse(mouse_event),
+                Event::Resize(..) => UiEvent::Resize,
+                _ => continue,
+            };
+            if ui_tx.send(ui_event).is_err() {
+                break;
             }
-            input_check_time = now;
         }
+    });
 
-        thread::sleep(Duration::from_millis(1));
-    }
-
-    execute!(buffered_stdout, cursor::Show, Clear(ClearType::All))?;
-    buffered_stdout.flush()?;
-    disable_raw_mode()?;
-
-    println!("\n* ======================================= *");
-    println!("   Thank you for using CLI Metronome!");
-    println!("   Keep the rhythm alive! ♪");
-    println!("* ======================================= *\n");
+    let guard = TerminalGuard::enable()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(BufWriter::new(io::stdout())))?;
+    // Mouse reports are plain escape sequences, so they pass through tmux and SSH
+    execute!(terminal.backend_mut(), EnableMouseCapture)?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
 
-    Ok(())
-}
+    // Frame interval for the beat animation; nothing is redrawn on a timer while stopped
+    const UI_UPDATE_INTERVAL: Duration = Duration::from_millis(16);
+    // Commands are applied on the engine thread, so the UI watches for the new state this long
+    const COMMAND_SETTLE_TIME: Duration = Duration::from_millis(250);
 
-fn metronome_loop(
-    state: Arc<AtomicState>,
-    sound_cache: Arc<SoundCache>,
-    tick_tx: mpsc::Sender<()>,
-    audio_tx: mpsc::Sender<AudioCommand>,
-) {
-    let mut last_tick = Instant::now();
-    let mut current_interval = Duration::from_millis(500);
-    let mut rng = rand::thread_rng();
+    let mut last_ui_update = Instant::now();
+    let mut ui_dirty = true;
+    let mut waiting_since: Option<Instant> = None;
+    // Holding on to the drawn snapshot means any newer one is a different allocation
+    let mut drawn = metronome.snapshot();
+    let mut beat: Option<TimelineEvent> = None;
+    // Index into the fields of the current mode's settings
+    let mut selected_field = 0;
+    // Where the last frame put everything, to find what the mouse is on
+    let mut layout: Option<ScreenLayout> = None;
+    // Set while the left button, pressed on the volume bar, is held
+    let mut dragging_volume = false;
 
     loop {
-        let should_tick = {
-            if !state.is_running.load(Ordering::Relaxed) {
-                thread::sleep(Duration::from_millis(5));
-                continue;
+        let snapshot = metronome.snapshot();
+        let changed = !Arc::ptr_eq(&snapshot, &drawn);
+        let is_running = snapshot.is_running;
+        let watching = is_running || waiting_since.is_some_and(|since| since.elapsed() < COMMAND_SETTLE_TIME);
+        let until_next_frame = UI_UPDATE_INTERVAL.saturating_sub(last_ui_update.elapsed());
+
+        if ui_dirty || ((changed || is_running) && until_next_frame.is_zero()) {
+            let mode_fields = fields(&snapshot.settings);
+            selected_field = selected_field.min(mode_fields.len().saturating_sub(1));
+            let screen = Screen {
+                snapshot: &snapshot,
+                beat: beat.as_ref(),
+                field: mode_fields.get(selected_field).copied(),
+                presets: &presets,
+                bindings: &bindings,
+                problems: &problems.0,
+            };
+            terminal.draw(|frame| layout = display_enhanced_ui(frame, &screen))?;
+            // Changes made while stopped are saved as soon as the engine has them;
+            // the mode may move the tempo while running, so those wait for the stop
+            if changed && !is_running {
+                save_settings(&snapshot, &mut config, &kit_names, &mut problems);
             }
+            ui_dirty = false;
+            drawn = snapshot;
+            last_ui_update = Instant::now();
+            continue;
+        }
 
-            let bpm = state.bpm.load(Ordering::Relaxed);
-            let new_interval = Duration::from_millis(60000 / bpm as u64);
-            if new_interval != current_interval {
-                current_interval = new_interval;
+        let ui_event = if watching {
+            match ui_rx.recv_timeout(until_next_frame.max(Duration::from_millis(1))) {
+                Ok(ui_event) => ui_event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            waiting_since = None;
+            match ui_rx.recv() {
+                Ok(ui_event) => ui_event,
+                Err(_) => break,
             }
-
-            last_tick.elapsed() >= current_interval
         };
 
-        if should_tick {
-            state.update_tick();
-
-            let sound_type = state.get_sound_type();
-            let mut sound_data = sound_cache.get_sound(sound_type).clone();
-
-            let volume = state.volume.load(Ordering::Relaxed) as f32 / 100.0;
-            for sample in &mut sound_data {
-                *sample *= volume;
+        match ui_event {
+            UiEvent::Resize => ui_dirty = true,
+            UiEvent::Problem(message) => {
+                problems.push(message);
+                ui_dirty = true;
             }
-
-            let _ = audio_tx.send(AudioCommand::PlayTick(sound_data));
-            last_tick = Instant::now();
-
-            if state.random_mode.load(Ordering::Relaxed) {
-                let mut remaining = state.remaining_ticks.load(Ordering::Relaxed);
-                if remaining == 0 {
-                    remaining = state.random_count.load(Ordering::Relaxed);
+            UiEvent::Engine(event) => match event {
+                MetronomeEvent::Beat { event, .. } => beat = Some(event),
+                MetronomeEvent::Started { .. } => {
+                    beat = None;
+                    ui_dirty = true;
                 }
-
-                remaining -= 1;
-                state.remaining_ticks.store(remaining, Ordering::Relaxed);
-
-                if remaining == 0 {
-                    let new_bpm = rng.gen_range(60..=150); // min/max random value
-                    state.bpm.store(new_bpm, Ordering::Relaxed);
-                    state.remaining_ticks.store(
-                        state.random_count.load(Ordering::Relaxed),
-                        Ordering::Relaxed,
-                    );
+                MetronomeEvent::ModeChanged { .. } => {
+                    selected_field = 0;
+                    ui_dirty = true;
+                }
+                MetronomeEvent::Error { message } => {
+                    problems.push(message);
+                    ui_dirty = true;
+                }
+                _ => ui_dirty = true,
+            },
+            UiEvent::Mouse(mouse_event) => {
+                let Some(layout) = &layout else {
+                    continue;
+                };
+                let (column, row) = (mouse_event.column, mouse_event.row);
+                let bar = volume_bar(layout);
+                let command = match mouse_event.kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        if let Some(sound) = sound_at(layout, column, row) {
+                            Some(MetronomeCommand::ChangeSoundType(sound.key()))
+                        } else if let Some(bar) = bar.filter(|bar| bar.contains(Position { x: column, y: row })) {
+                            dragging_volume = true;
+                            Some(MetronomeCommand::ChangeVolume(bar_volume(bar, column)))
+                        } else if layout.panel_at(column, row) == Some(Panel::Status) {
+                            Some(if is_running { MetronomeCommand::Stop } else { MetronomeCommand::Start })
+                        } else {
+                            None
+                        }
+                    }
+                    // Anywhere along the row once the drag started on the bar
+                    MouseEventKind::Drag(MouseButton::Left) if dragging_volume => {
+                        bar.map(|bar| MetronomeCommand::ChangeVolume(bar_volume(bar, column)))
+                    }
+                    MouseEventKind::Up(_) => {
+                        dragging_volume = false;
+                        None
+                    }
+                    MouseEventKind::ScrollUp if layout.panel_at(column, row) == Some(Panel::Bpm) => {
+                        Some(adjust_bpm(&snapshot, 1))
+                    }
+                    MouseEventKind::ScrollDown if layout.panel_at(column, row) == Some(Panel::Bpm) => {
+                        Some(adjust_bpm(&snapshot, -1))
+                    }
+                    _ => None,
+                };
+                if let Some(command) = command {
+                    metronome.send(command);
+                    waiting_since = Some(Instant::now());
+                }
+            }
+            UiEvent::Key(key_event) => {
+                if key_event.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let mode_fields = fields(&snapshot.settings);
+                let field = mode_fields.get(selected_field).copied();
+                let setting = field.and_then(|field| snapshot.settings.get(field.setting()));
+                let command = match bindings.action(&key_event) {
+                    Some(Action::Quit) => break,
+                    Some(Action::StartStop) if is_running => Some(MetronomeCommand::Stop),
+                    Some(Action::StartStop) => Some(MetronomeCommand::Start),
+                    Some(Action::RandomMode) => toggle_random_mode(&snapshot),
+                    Some(Action::BpmUp) => Some(adjust_bpm(&snapshot, 5)),
+                    Some(Action::BpmDown) => Some(adjust_bpm(&snapshot, -5)),
+                    Some(Action::BpmUpFine) => Some(adjust_bpm(&snapshot, 1)),
+                    Some(Action::BpmDownFine) => Some(adjust_bpm(&snapshot, -1)),
+                    Some(Action::NextMode) => Some(cycle_mode(&snapshot, true)),
+                    Some(Action::PreviousMode) => Some(cycle_mode(&snapshot, false)),
+                    Some(Action::NextSetting) => {
+                        selected_field = (selected_field + 1) % mode_fields.len().max(1);
+                        ui_dirty = true;
+                        None
+                    }
+                    Some(Action::PreviousSetting) => {
+                        selected_field = (selected_field + mode_fields.len().max(1) - 1) % mode_fields.len().max(1);
+                        ui_dirty = true;
+                        None
+                    }
+                    Some(Action::SettingUp) => update_setting(setting, field.and_then(|field| step(setting?, field, true))),
+                    Some(Action::SettingDown) => {
+                        update_setting(setting, field.and_then(|field| step(setting?, field, false)))
+                    }
+                    Some(Action::AddSection) => {
+                        let value = field.and_then(|field| add_section(setting?, field));
+                        // The new section is the one after the selected one
+                        if value.is_some() && matches!(field, Some(Field::SectionBpm(..) | Field::SectionBeats(..))) {
+                            selected_field += 2;
+                        }
+                        update_setting(setting, value)
+                    }
+                    Some(Action::RemoveSection) => update_setting(setting, field.and_then(|field| remove_section(setting?, field))),
+                    Some(Action::NextSound) => Some(cycle_sound(&snapshot, true)),
+                    Some(Action::PreviousSound) => Some(cycle_sound(&snapshot, false)),
+                    Some(Action::TestSound) => {
+                        let sound = SoundType::from_key(snapshot.sound_type).unwrap_or_default();
+                        metronome.play(sound_cache.get_sound(sound), snapshot.volume as f32 / 100.0);
+                        None
+                    }
+                    Some(Action::VolumeUp) => Some(adjust_volume(&snapshot, 10)),
+                    Some(Action::VolumeDown) => Some(adjust_volume(&snapshot, -10)),
+                    Some(Action::Tempo(slot)) => Some(MetronomeCommand::ChangeBpm(TEMPO_PRESETS[slot])),
+                    Some(Action::RecallPreset(slot)) => {
+                        presets.get(slot).map(|preset| recall_preset(&mut config, preset, &mut problems))
+                    }
+                    Some(Action::SavePreset(slot)) => {
+                        store_preset(&snapshot, &mut config, &kit_names, &mut presets, slot, &mut problems);
+                        ui_dirty = true;
+                        None
+                    }
+                    None => None,
+                };
+                if let Some(command) = command {
+                    metronome.send(command);
+                    waiting_since = Some(Instant::now());
                 }
             }
-
-            let _ = tick_tx.send(());
-        } else {
-            let time_to_next_tick = current_interval.saturating_sub(last_tick.elapsed());
-            let sleep_time = time_to_next_tick.min(Duration::from_millis(2));
-            thread::sleep(sleep_time);
         }
     }
-}
 
-fn toggle_metronome(state: &Arc<AtomicState>) {
-    let was_running = state.is_running.load(Ordering::Relaxed);
-    state.is_running.store(!was_running, Ordering::Relaxed);
-
-    if !was_running
-        && state.random_mode.load(Ordering::Relaxed)
-        && state.remaining_ticks.load(Ordering::Relaxed) == 0
-    {
-        state.remaining_ticks.store(
-            state.random_count.load(Ordering::Relaxed),
-            Ordering::Relaxed,
-        );
+    terminal.backend_mut().flush()?;
+    drop(guard);
+    // The mode may have moved the tempo since the last stop
+    let mut problems = Problems::new();
+    save_settings(&metronome.snapshot(), &mut config, &kit_names, &mut problems);
+    // Dropping the engine saves the practice session still running; without
+    // the UI to receive them, its problems are printed
+    drop(ui_rx);
+    drop(metronome);
+    for problem in problems.0 {
+        eprintln!("{}", problem);
     }
 
-    if !was_running {
-        state.tick_count.store(0, Ordering::Relaxed);
-    }
+    println!("\n* ======================================= *");
+    println!("   Thank you for using CLI Metronome!");
+    println!("   Keep the rhythm alive! ♪");
+    println!("* ======================================= *\n");
 
-    state.ui_dirty.store(true, Ordering::Relaxed);
+    Ok(())
 }
 
-fn toggle_random_mode(state: &Arc<AtomicState>) {
-    let was_random = state.random_mode.load(Ordering::Relaxed);
-    state.random_mode.store(!was_random, Ordering::Relaxed);
-
-    if !was_random {
-        if state.is_running.load(Ordering::Relaxed) {
-            state.remaining_ticks.store(
-                state.random_count.load(Ordering::Relaxed),
-                Ordering::Relaxed,
-            );
-        }
-    } else {
-        state.remaining_ticks.store(0, Ordering::Relaxed);
+// Writes the config file when anything in it changed
+fn save_settings(snapshot: &MetronomeSnapshot, config: &mut Config, kit_names: &[String], problems: &mut Problems) {
+    let sound = SoundType::from_key(snapshot.sound_type).unwrap_or_default();
+    let kit = snapshot.kit.checked_sub(1).and_then(|i| kit_names.get(i)).map_or("", String::as_str);
+    let mut updated = config.clone();
+    updated.capture(snapshot, sound.name(), kit);
+    if updated == *config {
+        return;
     }
-    state.ui_dirty.store(true, Ordering::Relaxed);
+    *config = updated;
+    write_settings(config, problems);
 }
 
-fn adjust_bpm(state: &Arc<AtomicState>, change: i32) {
-    let current = state.bpm.load(Ordering::Relaxed);
-    let new_bpm = (current as i32 + change).max(30).min(300) as u32;
-    state.bpm.store(new_bpm, Ordering::Relaxed);
-    state.ui_dirty.store(true, Ordering::Relaxed);
+fn write_settings(config: &Config, problems: &mut Problems) {
+    if let Some(path) = config_path()
+        && let Err(message) = save_config(&path, config)
+    {
+        problems.push(format!("Failed to save settings: {}", message));
+    }
 }
 
-fn adjust_random_count(state: &Arc<AtomicState>, change: i32) {
-    let current = state.random_count.load(Ordering::Relaxed);
-    let new_count = (current as i32 + change).max(10).min(1000) as u32;
-    state.random_count.store(new_count, Ordering::Relaxed);
+// The whole preset goes to the engine at once and becomes the saved config too,
+// so the other frontends start from it
+fn recall_preset(config: &mut Config, preset: &Preset, problems: &mut Problems) -> MetronomeCommand {
+    preset.apply_to(config);
+    write_settings(config, problems);
+    MetronomeCommand::LoadConfig {
+        config: Box::new(preset.config.clone()),
+        sound_type: SoundType::find(&preset.config.sound).map(|sound| sound.key()),
+    }
+}
 
-    if state.random_mode.load(Ordering::Relaxed) && state.is_running.load(Ordering::Relaxed) {
-        let remaining = state.remaining_ticks.load(Ordering::Relaxed);
-        if new_count > remaining {
-            state.remaining_ticks.store(new_count, Ordering::Relaxed);
+// Saves over the preset in that slot, or adds a new one named after the slot
+fn store_preset(
+    snapshot: &MetronomeSnapshot,
+    config: &mut Config,
+    kit_names: &[String],
+    presets: &mut Vec<Preset>,
+    slot: usize,
+    problems: &mut Problems,
+) {
+    let Some(dir) = presets_dir() else {
+        return;
+    };
+    save_settings(snapshot, config, kit_names, problems);
+    let name = match presets.get(slot) {
+        Some(preset) => preset.name.clone(),
+        None => format!("Preset {}", slot + 1),
+    };
+    match save_preset(&dir, &name, config) {
+        Ok(preset) => {
+            presets.retain(|existing| existing.name != preset.name);
+            let index = presets.partition_point(|existing| existing.name < preset.name);
+            presets.insert(index, preset);
         }
+        Err(message) => problems.push(format!("Failed to save preset: {}", message)),
     }
-    state.ui_dirty.store(true, Ordering::Relaxed);
 }
 
-fn adjust_volume(state: &Arc<AtomicState>, change: i32) {
-    let current = state.volume.load(Ordering::Relaxed);
-    let new_volume = (current as i32 + change).max(0).min(100) as u32;
-    state.volume.store(new_volume, Ordering::Relaxed);
-    state.ui_dirty.store(true, Ordering::Relaxed);
+// Switches between Random and Standard, whatever mode was on before
+fn toggle_random_mode(snapshot: &MetronomeSnapshot) -> Option<MetronomeCommand> {
+    let position = |name: &str| snapshot.modes.iter().position(|(_, mode)| *mode == name);
+    let random = position("Random")?;
+    let mode = if snapshot.mode == random { position("Standard")? } else { random };
+    Some(MetronomeCommand::ChangeMode(mode))
 }
 
-fn set_preset_bpm(state: &Arc<AtomicState>, bpm: u32) {
-    state.bpm.store(bpm, Ordering::Relaxed);
-    state.ui_dirty.store(true, Ordering::Relaxed);
+fn cycle_mode(snapshot: &MetronomeSnapshot, forward: bool) -> MetronomeCommand {
+    let count = snapshot.modes.len().max(1);
+    let mode = if forward { snapshot.mode + 1 } else { snapshot.mode + count - 1 };
+    MetronomeCommand::ChangeMode(mode % count)
 }
 
-fn cycle_sound(state: &Arc<AtomicState>, forward: bool) {
-    let current = state.get_sound_type();
-    let new_sound = if forward {
-        current.next()
-    } else {
-        current.prev()
-    };
-    state.set_sound_type(new_sound);
-    state.ui_dirty.store(true, Ordering::Relaxed);
+fn update_setting(setting: Option<&Setting>, value: Option<SettingValue>) -> Option<MetronomeCommand> {
+    Some(MetronomeCommand::UpdateModeSetting { key: setting?.key, value: value? })
 }
 
-fn test_current_sound(
-    state: &Arc<AtomicState>,
-    sound_cache: &Arc<SoundCache>,
-    audio_tx: &mpsc::Sender<AudioCommand>,
-) {
-    let sound_type = state.get_sound_type();
-    let mut sound_data = sound_cache.get_sound(sound_type).clone();
+fn adjust_bpm(snapshot: &MetronomeSnapshot, change: i32) -> MetronomeCommand {
+    let new_bpm = (snapshot.bpm as i32 + change).clamp(30, 300) as u32;
+    MetronomeCommand::ChangeBpm(new_bpm)
+}
 
-    let volume = state.volume.load(Ordering::Relaxed) as f32 / 100.0;
-    for sample in &mut sound_data {
-        *sample *= volume;
-    }
+fn adjust_volume(snapshot: &MetronomeSnapshot, change: i32) -> MetronomeCommand {
+    let new_volume = (snapshot.volume as i32 + change).clamp(0, 100) as u32;
+    MetronomeCommand::ChangeVolume(new_volume)
+}
 
-    let _ = audio_tx.send(AudioCommand::PlayTick(sound_data));
+fn cycle_sound(snapshot: &MetronomeSnapshot, forward: bool) -> MetronomeCommand {
+    let current = SoundType::from_key(snapshot.sound_type).unwrap_or_default();
+    let new_sound = if forward { current.next() } else { current.prev() };
+    MetronomeCommand::ChangeSoundType(new_sound.key())
 }
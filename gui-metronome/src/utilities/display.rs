@@ -1,396 +1,494 @@
-use crossterm::{
-    cursor, execute,
-    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
-    terminal::{Clear, ClearType},
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    symbols::border,
+    text::{Line, Span},
+    widgets::{Block, Borders, Padding, Paragraph, Wrap},
 };
 
-use std::io::{BufWriter, Stdout, Write};
-use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
 use std::time::Duration;
 
-use crate::utilities::{cache::UICache, sound_type::SoundType, state::AtomicState};
-
-const TITLE_ROW: u16 = 1;
-const SUBTITLE_ROW: u16 = 2;
-const DIVIDER_ROW: u16 = 3;
-const MAIN_PANEL_START: u16 = 5;
-const ANIMATION_ROW: u16 = 6;
-const BPM_PANEL_ROW: u16 = 8;
-const SOUND_PANEL_ROW: u16 = 10;
-const STATUS_PANEL_ROW: u16 = 12;
-const RANDOM_PANEL_ROW: u16 = 14;
-const VOLUME_PANEL_ROW: u16 = 16;
-const CONTROLS_SECTION_START: u16 = 19;
-const CONTROLS_TITLE_ROW: u16 = 20;
-const CONTROLS_START_ROW: u16 = 21;
-const SOUNDS_SECTION_ROW: u16 = 32;
-const FOOTER_ROW: u16 = 35;
-
-pub fn display_enhanced_ui(
-    state: &Arc<AtomicState>,
-    ui_cache: &Arc<Mutex<UICache>>,
-    writer: &mut BufWriter<Stdout>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut cache = ui_cache.lock().unwrap();
-
-    let current_bpm = state.bpm.load(Ordering::Relaxed);
-    let current_sound = state.get_sound_type();
-    let current_status = state.is_running.load(Ordering::Relaxed);
-    let current_random_mode = state.random_mode.load(Ordering::Relaxed);
-    let current_remaining_ticks = state.remaining_ticks.load(Ordering::Relaxed);
-    let current_random_count = state.random_count.load(Ordering::Relaxed);
-    let current_tick_count = state.tick_count.load(Ordering::Relaxed);
-    let current_volume = state.volume.load(Ordering::Relaxed);
-
-    if cache.first_render {
-        execute!(writer, Clear(ClearType::All))?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(25, TITLE_ROW),
-            SetAttribute(Attribute::Bold),
-            SetForegroundColor(Color::Magenta),
-            Print("🎵 ═══ "),
-            SetForegroundColor(Color::Blue),
-            Print("CLI METRONOME STUDIO"),
-            SetForegroundColor(Color::Magenta),
-            Print(" ═══ 🎵"),
-            ResetColor,
-        )?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(30, SUBTITLE_ROW),
-            SetForegroundColor(Color::DarkGrey),
-            Print("♪ Professional Rhythm Training Tool ♪"),
-            ResetColor,
-        )?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(10, DIVIDER_ROW),
-            SetForegroundColor(Color::Cyan),
-            Print("==================================================================="),
-            ResetColor,
-        )?;
-
-        cache.first_render = false;
+use metronome_engine::MetronomeSnapshot;
+use metronome_engine::utilities::mode::{Setting, SettingKind, SettingValue};
+use metronome_engine::utilities::preset::Preset;
+use metronome_engine::utilities::timeline::TimelineEvent;
+
+use crate::utilities::{
+    keys::{Action, KeyBindings, TEMPO_PRESETS},
+    layout::{MIN_HEIGHT, MIN_WIDTH, Panel, ScreenLayout},
+    settings::Field,
+    sound_type::SoundType,
+};
+
+// The panels keep their plain ASCII boxes
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+// One line of the control panel per entry, with the keys filled in from the active bindings
+const CONTROLS: [(&str, &[Action], &str, Color); 16] = [
+    ("⏯️ ", &[Action::StartStop], "Start/Stop metronome", Color::Green),
+    ("🎲", &[Action::RandomMode], "Toggle random BPM mode", Color::Yellow),
+    ("🎛️", &[Action::NextMode, Action::PreviousMode], "Next/previous mode", Color::Yellow),
+    ("⬆️⬇️", &[Action::BpmUp, Action::BpmDown], "Adjust BPM by ±5", Color::Cyan),
+    ("⬅️➡️", &[Action::BpmDownFine, Action::BpmUpFine], "Adjust BPM by ±1", Color::Cyan),
+    ("🔀", &[Action::NextSetting, Action::PreviousSetting], "Select mode setting", Color::Magenta),
+    ("➕➖", &[Action::SettingUp, Action::SettingDown], "Change selected setting", Color::Magenta),
+    ("📋", &[Action::AddSection, Action::RemoveSection], "Add/remove practice section", Color::Magenta),
+    ("🔊", &[Action::NextSound], "Next sound", Color::Blue),
+    ("🔉", &[Action::PreviousSound], "Previous sound", Color::Blue),
+    ("🧪", &[Action::TestSound], "Test current sound", Color::White),
+    ("🔊", &[Action::VolumeUp, Action::VolumeDown], "Volume up/down", Color::Cyan),
+    ("⚡", &TEMPO_ACTIONS, "BPM presets", Color::Red),
+    ("🎼", &RECALL_PRESET_ACTIONS, "Recall saved preset", Color::Green),
+    ("💾", &SAVE_PRESET_ACTIONS, "Save setup as preset", Color::Green),
+    ("❌", &[Action::Quit], "Quit application", Color::Red),
+];
+
+// Cells in the volume bar, which the mouse can drag
+const VOLUME_BAR_WIDTH: u16 = 15;
+
+const TEMPO_ACTIONS: [Action; 4] = [Action::Tempo(0), Action::Tempo(1), Action::Tempo(2), Action::Tempo(3)];
+
+const RECALL_PRESET_ACTIONS: [Action; 9] = [
+    Action::RecallPreset(0),
+    Action::RecallPreset(1),
+    Action::RecallPreset(2),
+    Action::RecallPreset(3),
+    Action::RecallPreset(4),
+    Action::RecallPreset(5),
+    Action::RecallPreset(6),
+    Action::RecallPreset(7),
+    Action::RecallPreset(8),
+];
+
+const SAVE_PRESET_ACTIONS: [Action; 9] = [
+    Action::SavePreset(0),
+    Action::SavePreset(1),
+    Action::SavePreset(2),
+    Action::SavePreset(3),
+    Action::SavePreset(4),
+    Action::SavePreset(5),
+    Action::SavePreset(6),
+    Action::SavePreset(7),
+    Action::SavePreset(8),
+];
+
+// Everything the screen shows, read once per frame
+pub struct Screen<'a> {
+    pub snapshot: &'a MetronomeSnapshot,
+    // The last click played, for the bar and beat
+    pub beat: Option<&'a TimelineEvent>,
+    // The selected mode setting
+    pub field: Option<Field>,
+    pub presets: &'a [Preset],
+    pub bindings: &'a KeyBindings,
+    // Problems with the configured keys or from the engine, shown in the footer
+    pub problems: &'a VecDeque<String>,
+}
+
+// Draws the whole screen into the frame; ratatui only sends what changed since the last one.
+// Returns where everything went, for the mouse, or None when the terminal is too small.
+pub fn display_enhanced_ui(frame: &mut Frame, screen: &Screen) -> Option<ScreenLayout> {
+    let area = frame.size();
+    let (mode_rows, _) = mode_lines(screen, u16::MAX);
+    let Some(layout) = ScreenLayout::new(area, CONTROLS.len() as u16, mode_rows.len() as u16) else {
+        let message = format!(
+            "Terminal too small ({}x{}), the metronome needs at least {}x{}",
+            area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+        );
+        frame.render_widget(Paragraph::new(message).yellow().wrap(Wrap { trim: true }), area);
+        return None;
+    };
+    let snapshot = screen.snapshot;
+
+    draw_header(frame, &layout);
+
+    if let Some(rect) = layout.animation {
+        let block = Block::default().borders(Borders::ALL).border_set(ASCII_BORDER).padding(Padding::horizontal(1));
+        // Room for the symbol in front of the beat line
+        let width = block.inner(rect).width.saturating_sub(3) as usize;
+        let animation = generate_enhanced_tick_animation(snapshot, width);
+        let style = if snapshot.is_running {
+            Style::new().fg(Color::Green).add_modifier(Modifier::BOLD)
+        } else {
+            Style::new().fg(Color::DarkGray)
+        };
+        frame.render_widget(Paragraph::new(Span::styled(animation, style)).block(block), rect);
     }
 
-    let animation = generate_enhanced_tick_animation(state);
-    if animation != cache.animation_buffer {
-        cache.animation_buffer = animation.clone();
-
-        execute!(writer, cursor::MoveTo(5, ANIMATION_ROW))?;
-        draw_box_border(writer, 5, ANIMATION_ROW, 80, 3)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(7, ANIMATION_ROW + 1),
-            Clear(ClearType::UntilNewLine),
-        )?;
-
-        if current_status {
-            execute!(
-                writer,
-                SetForegroundColor(Color::Green),
-                SetAttribute(Attribute::Bold),
-                Print(&animation),
-                ResetColor,
-            )?;
+    for (panel, rect) in &layout.panels {
+        let lines = match panel {
+            Panel::Bpm => bpm_panel(snapshot),
+            Panel::Sound => sound_panel(snapshot),
+            Panel::Status => status_panel(snapshot, screen.beat),
+            Panel::Mode => mode_panel(snapshot),
+            Panel::Volume => volume_panel(snapshot, volume_bar(&layout).map_or(VOLUME_BAR_WIDTH, |bar| bar.width)),
+        };
+        let block = if layout.bordered {
+            Block::default().borders(Borders::ALL).border_set(ASCII_BORDER).padding(Padding::horizontal(1))
         } else {
-            execute!(
-                writer,
-                SetForegroundColor(Color::DarkGrey),
-                Print(&animation),
-                ResetColor,
-            )?;
+            Block::default().padding(Padding::horizontal(1))
+        };
+        frame.render_widget(Paragraph::new(lines).block(block), *rect);
+    }
+
+    if let Some(rect) = layout.mode {
+        let (mut lines, selected) = mode_lines(screen, rect.width);
+        // Cut down, the mode line stays and the rest scrolls to keep the selected setting in view
+        let rows = rect.height as usize;
+        if lines.len() > rows && rows > 1 {
+            let first = selected.saturating_sub(rows - 2).clamp(1, lines.len() - (rows - 1));
+            lines.drain(1..first);
         }
+        frame.render_widget(Paragraph::new(lines), rect);
     }
 
-    if current_bpm != cache.last_bpm || cache.first_render {
-        execute!(writer, cursor::MoveTo(10, BPM_PANEL_ROW))?;
-        draw_box_border(writer, 10, BPM_PANEL_ROW, 30, 4)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(12, BPM_PANEL_ROW + 1),
-            SetForegroundColor(Color::Yellow),
-            SetAttribute(Attribute::Bold),
-            Print("⚡ BPM: "),
-            SetForegroundColor(Color::White),
-            Print(&format!("{:3}", current_bpm)),
-            ResetColor,
-        )?;
-
-        let bpm_progress = (current_bpm - 30) as f64 / (300 - 30) as f64;
-        let meter = create_progress_bar(bpm_progress, 20, '#', '.');
-        execute!(
-            writer,
-            cursor::MoveTo(12, BPM_PANEL_ROW + 2),
-            SetForegroundColor(if current_bpm > 150 {
-                Color::Red
-            } else if current_bpm > 100 {
-                Color::Yellow
-            } else {
-                Color::Green
-            }),
-            Print(&meter),
-            ResetColor,
-        )?;
-
-        cache.last_bpm = current_bpm;
+    if let Some(rect) = layout.controls {
+        draw_controls(frame, rect, layout.control_columns, screen.bindings);
     }
 
-    if current_sound != cache.last_sound || cache.first_render {
-        execute!(writer, cursor::MoveTo(45, BPM_PANEL_ROW))?;
-        draw_box_border(writer, 45, BPM_PANEL_ROW, 25, 4)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(47, BPM_PANEL_ROW + 1),
-            SetForegroundColor(Color::Magenta),
-            SetAttribute(Attribute::Bold),
-            Print("🔊 Sound: "),
-            ResetColor,
-        )?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(47, BPM_PANEL_ROW + 2),
-            SetForegroundColor(Color::White),
-            Print(&format!(
-                "{} {}",
-                current_sound.icon(),
-                current_sound.name()
-            )),
-            ResetColor,
-        )?;
-
-        cache.last_sound = current_sound;
+    if let Some(rect) = layout.sounds {
+        let sounds_display: String = SoundType::all().map(sound_entry).collect();
+        let lines = vec![
+            Line::from("🎵 ═══ AVAILABLE SOUNDS ═══ 🎵").cyan().bold().centered(),
+            Line::from(sounds_display).white(),
+        ];
+        frame.render_widget(Paragraph::new(lines), rect);
     }
 
-    if current_status != cache.last_status
-        || current_tick_count != cache.last_tick_count
-        || cache.first_render
-    {
-        execute!(writer, cursor::MoveTo(10, STATUS_PANEL_ROW))?;
-        draw_box_border(writer, 10, STATUS_PANEL_ROW, 35, 4)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(12, STATUS_PANEL_ROW + 1),
-            SetAttribute(Attribute::Bold),
-        )?;
-
-        if current_status {
-            let beats_per_measure = (current_tick_count % 4) + 1;
-            execute!(
-                writer,
-                SetForegroundColor(Color::Green),
-                Print(&format!(
-                    "▶️  PLAYING • Beat #{} • {}/4",
-                    current_tick_count, beats_per_measure
-                )),
-                ResetColor,
-            )?;
-
-            execute!(writer, cursor::MoveTo(12, STATUS_PANEL_ROW + 2))?;
-            for i in 1..=4 {
-                if i <= beats_per_measure {
-                    execute!(
-                        writer,
-                        SetForegroundColor(Color::Green),
-                        Print("* "),
-                        ResetColor
-                    )?;
-                } else {
-                    execute!(
-                        writer,
-                        SetForegroundColor(Color::DarkGrey),
-                        Print("o "),
-                        ResetColor
-                    )?;
-                }
-            }
+    if let Some(rect) = layout.presets {
+        let presets_display: String = if screen.presets.is_empty() {
+            format!("  None yet, {} saves the current setup", screen.bindings.label(&[Action::SavePreset(0)]))
         } else {
-            execute!(
-                writer,
-                SetForegroundColor(Color::Red),
-                Print("⏹️  STOPPED"),
-                ResetColor,
-            )?;
-        }
+            screen
+                .presets
+                .iter()
+                .take(9)
+                .enumerate()
+                .map(|(i, preset)| format!("  {} {}", i + 1, preset.name))
+                .collect()
+        };
+        let lines = vec![
+            Line::from("🎼 ═══ PRESETS ═══ 🎼").green().bold().centered(),
+            Line::from(presets_display).white(),
+        ];
+        frame.render_widget(Paragraph::new(lines), rect);
+    }
 
-        cache.last_status = current_status;
-        cache.last_tick_count = current_tick_count;
+    if let Some(rect) = layout.footer {
+        // Problems take the place of the tip, newest first
+        let footer = match (screen.problems.back(), screen.problems.len()) {
+            (None, _) => Line::from(format!(
+                "💡 Pro tip: {} switches practice modes • {} for quick BPM presets • {} for volume",
+                screen.bindings.label(&[Action::NextMode]),
+                screen.bindings.label(&TEMPO_ACTIONS),
+                screen.bindings.label(&[Action::VolumeUp, Action::VolumeDown])
+            ))
+            .dark_gray(),
+            (Some(only), 1) => Line::from(format!("⚠️  {}", only)).yellow(),
+            (Some(newest), count) => Line::from(format!("⚠️  {} (and {} more)", newest, count - 1)).yellow(),
+        };
+        frame.render_widget(Paragraph::new(footer.centered()), rect);
     }
+    Some(layout)
+}
 
-    if current_random_mode != cache.last_random_mode
-        || current_remaining_ticks != cache.last_remaining_ticks
-        || cache.first_render
-    {
-        execute!(writer, cursor::MoveTo(50, STATUS_PANEL_ROW))?;
-        draw_box_border(writer, 50, STATUS_PANEL_ROW, 30, 4)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(52, STATUS_PANEL_ROW + 1),
-            SetAttribute(Attribute::Bold),
-        )?;
-
-        if current_random_mode {
-            execute!(
-                writer,
-                SetForegroundColor(Color::Yellow),
-                Print("🎲 RANDOM MODE"),
-                ResetColor,
-            )?;
-
-            execute!(
-                writer,
-                cursor::MoveTo(52, STATUS_PANEL_ROW + 2),
-                SetForegroundColor(Color::White),
-                Print(&format!("Next change: {} ticks", current_remaining_ticks)),
-                ResetColor,
-            )?;
-        } else {
-            execute!(
-                writer,
-                SetForegroundColor(Color::DarkGrey),
-                Print("🎯 FIXED BPM"),
-                ResetColor,
-            )?;
-        }
+fn sound_entry(sound: SoundType) -> String {
+    format!("  {} {}", sound.icon(), sound.name())
+}
 
-        cache.last_random_mode = current_random_mode;
-        cache.last_remaining_ticks = current_remaining_ticks;
+// The sound whose entry in the sound list is at that cell, gap in front included
+pub fn sound_at(layout: &ScreenLayout, column: u16, row: u16) -> Option<SoundType> {
+    let rect = layout.sounds?;
+    // The title takes the first row
+    if !rect.contains(Position { x: column, y: row }) || row != rect.y + 1 {
+        return None;
     }
+    let mut end = rect.x as usize;
+    SoundType::all().find(|sound| {
+        end += Span::raw(sound_entry(*sound)).width();
+        (column as usize) < end
+    })
+}
 
-    if current_volume != cache.last_volume || cache.first_render {
-        execute!(writer, cursor::MoveTo(10, VOLUME_PANEL_ROW))?;
-        draw_box_border(writer, 10, VOLUME_PANEL_ROW, 25, 4)?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(12, VOLUME_PANEL_ROW + 1),
-            SetForegroundColor(Color::Cyan),
-            SetAttribute(Attribute::Bold),
-            Print(&format!("🔉 Volume: {}%", current_volume)),
-            ResetColor,
-        )?;
-
-        let volume_progress = current_volume as f64 / 100.0;
-        let volume_bar = create_progress_bar(volume_progress, 15, '#', '.');
-        execute!(
-            writer,
-            cursor::MoveTo(12, VOLUME_PANEL_ROW + 2),
-            SetForegroundColor(Color::Cyan),
-            Print(&volume_bar),
-            ResetColor,
-        )?;
-
-        cache.last_volume = current_volume;
+pub fn volume_bar(layout: &ScreenLayout) -> Option<Rect> {
+    let content = layout.panel_content(Panel::Volume)?;
+    // Below the volume line
+    (content.height > 1).then(|| Rect::new(content.x, content.y + 1, VOLUME_BAR_WIDTH.min(content.width), 1))
+}
+
+// The volume that fills the bar up to and including the column; a narrow
+// panel cuts the bar short, so it goes by the width actually drawn
+pub fn bar_volume(bar: Rect, column: u16) -> u32 {
+    if column < bar.x || bar.width == 0 {
+        return 0;
     }
+    let filled = (column - bar.x + 1).min(bar.width) as u32;
+    // Rounded up, so the bar shows the cell as filled
+    (filled * 100).div_ceil(bar.width as u32)
+}
+
+fn draw_header(frame: &mut Frame, layout: &ScreenLayout) {
+    let title = Line::from(vec![
+        Span::styled("🎵 ═══ ", Style::new().fg(Color::Magenta)),
+        Span::styled("CLI METRONOME STUDIO", Style::new().fg(Color::Blue)),
+        Span::styled(" ═══ 🎵", Style::new().fg(Color::Magenta)),
+    ]);
+    let title = if title.width() <= layout.title.width as usize {
+        title
+    } else {
+        Line::from("CLI METRONOME STUDIO").blue()
+    };
+    frame.render_widget(Paragraph::new(title.bold().centered()), layout.title);
 
-    if cache.first_render {
-        execute!(
-            writer,
-            cursor::MoveTo(25, CONTROLS_TITLE_ROW),
-            SetForegroundColor(Color::Yellow),
-            SetAttribute(Attribute::Bold),
-            Print("🎹 ═══ CONTROL PANEL ═══ 🎹"),
-            ResetColor,
-        )?;
-
-        let controls = [
-            ("⏯️  SPACE/ENTER", "Start/Stop metronome", Color::Green),
-            ("🎲 R", "Toggle random BPM mode", Color::Yellow),
-            ("⬆️⬇️ ↑/↓", "Adjust BPM by ±5", Color::Cyan),
-            ("⬅️➡️ ←/→", "Adjust BPM by ±1", Color::Cyan),
-            ("➕➖ +/-", "Adjust random count ±10", Color::Magenta),
-            ("🔊 S/N", "Next sound", Color::Blue),
-            ("🔉 A/P", "Previous sound", Color::Blue),
-            ("🧪 T", "Test current sound", Color::White),
-            ("🔊 V/C", "Volume up/down", Color::Cyan),
-            ("⚡ F1-F4", "BPM presets (60/120/180/200)", Color::Red),
-            ("❌ Q/ESC", "Quit application", Color::Red),
+    if let Some(rect) = layout.subtitle {
+        let lines = vec![
+            Line::from("♪ Professional Rhythm Training Tool ♪").dark_gray().centered(),
+            Line::from("=".repeat(rect.width as usize)).cyan(),
         ];
+        frame.render_widget(Paragraph::new(lines), rect);
+    }
+}
+
+fn bpm_panel(snapshot: &MetronomeSnapshot) -> Vec<Line<'static>> {
+    let current_bpm = snapshot.bpm;
+    let bpm_progress = current_bpm.saturating_sub(30) as f64 / (300 - 30) as f64;
+    let meter = create_progress_bar(bpm_progress.min(1.0), 20, '#', '.');
+    let meter_color = if current_bpm > 150 {
+        Color::Red
+    } else if current_bpm > 100 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+    vec![
+        Line::from(vec![
+            Span::styled("⚡ BPM: ", Style::new().fg(Color::Yellow)),
+            Span::styled(format!("{:3}", current_bpm), Style::new().fg(Color::White)),
+        ])
+        .bold(),
+        Line::from(meter).fg(meter_color),
+    ]
+}
+
+fn sound_panel(snapshot: &MetronomeSnapshot) -> Vec<Line<'static>> {
+    let current_sound = SoundType::from_key(snapshot.sound_type).unwrap_or_default();
+    vec![
+        Line::from("🔊 Sound: ").magenta().bold(),
+        Line::from(format!("{} {}", current_sound.icon(), current_sound.name())).white(),
+    ]
+}
+
+fn status_panel(snapshot: &MetronomeSnapshot, beat: Option<&TimelineEvent>) -> Vec<Line<'static>> {
+    if !snapshot.is_running {
+        return vec![Line::from("⏹️  STOPPED").red().bold()];
+    }
+    let Some(beat) = beat else {
+        return vec![Line::from("▶️  PLAYING").green().bold()];
+    };
+
+    let beats_per_bar = snapshot.beats_per_bar.max(1);
+    let current_beat = beat.beat % beats_per_bar + 1;
+    let beats: Vec<Span> = (1..=beats_per_bar)
+        .map(|i| if i <= current_beat { "* ".green() } else { "o ".dark_gray() })
+        .collect();
+    vec![
+        Line::from(format!("▶️  PLAYING • Bar {} • {}/{}", beat.bar + 1, current_beat, beats_per_bar))
+            .green()
+            .bold(),
+        Line::from(beats),
+    ]
+}
+
+// The current mode and how far along it is; the settings get the mode section
+fn mode_panel(snapshot: &MetronomeSnapshot) -> Vec<Line<'static>> {
+    let (icon, name) = snapshot.modes.get(snapshot.mode).copied().unwrap_or(("🎯", "Standard"));
+    if snapshot.mode == 0 {
+        return vec![Line::from(format!("{} {} MODE", icon, name.to_uppercase())).dark_gray().bold()];
+    }
+
+    let panel = &snapshot.panel;
+    let detail = match (&panel.progress, panel.live.first()) {
+        (Some(progress), _) => progress.label.clone(),
+        (None, Some(live)) if snapshot.is_running => live.clone(),
+        // The status leads with the mode's name, which is already on the first line
+        _ => panel.status.split_once(" - ").map_or("", |(_, rest)| rest).to_string(),
+    };
+    vec![
+        Line::from(format!("{} {} MODE", icon, name.to_uppercase())).yellow().bold(),
+        Line::from(detail).white(),
+    ]
+}
+
+fn volume_panel(snapshot: &MetronomeSnapshot, bar_width: u16) -> Vec<Line<'static>> {
+    let current_volume = snapshot.volume;
+    let volume_progress = current_volume as f64 / 100.0;
+    vec![
+        Line::from(format!("🔉 Volume: {}%", current_volume)).cyan().bold(),
+        Line::from(create_progress_bar(volume_progress, bar_width as usize, '#', '.')).cyan(),
+    ]
+}
+
+// The mode list, one line per setting (and per practice section), then the
+// mode's progress, live status and notes. Also returns the selected line.
+fn mode_lines(screen: &Screen, width: u16) -> (Vec<Line<'static>>, usize) {
+    let snapshot = screen.snapshot;
+    let mut lines = vec![mode_tabs(snapshot, width)];
+    let mut selected = 0;
 
-        for (i, (key, desc, color)) in controls.iter().enumerate() {
-            execute!(
-                writer,
-                cursor::MoveTo(15, CONTROLS_START_ROW + i as u16),
-                SetForegroundColor(*color),
-                Print(&format!("{:15}", key)),
-                SetForegroundColor(Color::White),
-                Print(" - "),
-                SetForegroundColor(Color::DarkGrey),
-                Print(desc),
-                ResetColor,
-            )?;
+    let selected_style = Style::new().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let style_for = |field: Field| {
+        if screen.field == Some(field) { selected_style } else { Style::new().fg(Color::White) }
+    };
+    for (i, setting) in snapshot.settings.iter().enumerate() {
+        if screen.field.is_some_and(|field| field.setting() == i) {
+            selected = lines.len();
+        }
+        let label = Span::styled(format!("  {}: ", setting.label), Style::new().fg(Color::Cyan));
+        match &setting.value {
+            SettingValue::Sections(sections) if !sections.is_empty() => {
+                lines.push(Line::from(label));
+                for (section, (bpm, beats)) in sections.iter().enumerate() {
+                    if screen.field == Some(Field::SectionBpm(i, section))
+                        || screen.field == Some(Field::SectionBeats(i, section))
+                    {
+                        selected = lines.len();
+                    }
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("    {}. ", section + 1), Style::new().fg(Color::DarkGray)),
+                        Span::styled(format!("{} BPM", bpm), style_for(Field::SectionBpm(i, section))),
+                        " × ".dark_gray(),
+                        Span::styled(format!("{} beats", beats), style_for(Field::SectionBeats(i, section))),
+                    ]));
+                }
+            }
+            SettingValue::Pattern(pattern) => {
+                let mut spans = vec![label];
+                for (step, accent) in pattern.iter().enumerate() {
+                    let symbol = if *accent { "●" } else { "○" };
+                    spans.push(Span::styled(symbol, style_for(Field::Step(i, step))));
+                    spans.push(" ".into());
+                }
+                lines.push(Line::from(spans));
+            }
+            _ => lines.push(Line::from(vec![label, Span::styled(setting_value(setting), style_for(Field::Value(i)))])),
         }
+    }
 
-        execute!(
-            writer,
-            cursor::MoveTo(20, SOUNDS_SECTION_ROW),
-            SetForegroundColor(Color::Cyan),
-            SetAttribute(Attribute::Bold),
-            Print("🎵 ═══ AVAILABLE SOUNDS ═══ 🎵"),
-            ResetColor,
-        )?;
-
-        let sounds_display = format!(
-            "  {} Beep  {} Kick  {} Click  {} Cowbell  {} Hi-hat  {} Square  {} Triangle  {} Woodblock",
-            SoundType::Beep.icon(),
-            SoundType::Kick.icon(),
-            SoundType::Click.icon(),
-            SoundType::Cowbell.icon(),
-            SoundType::Hihat.icon(),
-            SoundType::Square.icon(),
-            SoundType::Triangle.icon(),
-            SoundType::Woodblock.icon()
-        );
+    let panel = &snapshot.panel;
+    if let Some(progress) = &panel.progress {
+        let bar = create_progress_bar(progress.fraction.clamp(0.0, 1.0) as f64, 20, '#', '.');
+        lines.push(Line::from(format!("  {} {}", bar, progress.label)).green());
+    }
+    if snapshot.is_running {
+        lines.extend(panel.live.iter().map(|live| Line::from(format!("  {}", live)).white()));
+    }
+    lines.extend(panel.notes.iter().map(|note| Line::from(format!("  {}", note)).dark_gray()));
+    (lines, selected)
+}
+
+// Every mode by name with the current one marked, or only the current one when they do not fit
+fn mode_tabs(snapshot: &MetronomeSnapshot, width: u16) -> Line<'static> {
+    let spans: Vec<Span> = snapshot
+        .modes
+        .iter()
+        .enumerate()
+        .map(|(i, (icon, name))| {
+            let tab = format!(" {} {} ", icon, name);
+            if i == snapshot.mode { tab.black().on_yellow().bold() } else { tab.dark_gray() }
+        })
+        .collect();
+    let tabs = Line::from(spans);
+    if tabs.width() <= width as usize {
+        return tabs.centered();
+    }
+    let (icon, name) = snapshot.modes.get(snapshot.mode).copied().unwrap_or_default();
+    Line::from(format!("◀ {} {} ▶ ({}/{})", icon, name, snapshot.mode + 1, snapshot.modes.len()))
+        .yellow()
+        .bold()
+        .centered()
+}
+
+fn setting_value(setting: &Setting) -> String {
+    match (&setting.kind, &setting.value) {
+        (SettingKind::Number { suffix, .. }, SettingValue::Number(value)) => format!("{}{}", value, suffix),
+        (SettingKind::Choice(options), SettingValue::Number(value)) => options
+            .iter()
+            .map(|(option, name)| if option == value { format!("[{}]", name) } else { name.to_string() })
+            .collect::<Vec<_>>()
+            .join(" "),
+        (SettingKind::Toggle, SettingValue::Toggle(value)) => if *value { "[x] on" } else { "[ ] off" }.to_string(),
+        (SettingKind::Sections { .. }, SettingValue::Sections(_)) => "none".to_string(),
+        (_, value) => format!("{:?}", value),
+    }
+}
 
-        execute!(
-            writer,
-            cursor::MoveTo(5, SOUNDS_SECTION_ROW + 1),
-            SetForegroundColor(Color::White),
-            Print(&sounds_display),
-            ResetColor,
-        )?;
-
-        execute!(
-            writer,
-            cursor::MoveTo(15, FOOTER_ROW),
-            SetForegroundColor(Color::DarkGrey),
-            Print(
-                "💡 Pro tip: Use random mode for practice • F-keys for quick BPM presets • V/C for volume"
-            ),
-            ResetColor,
-        )?;
+// Fills the columns top to bottom; when not every key fits, the last line says how many are missing
+fn draw_controls(frame: &mut Frame, rect: Rect, columns: u16, bindings: &KeyBindings) {
+    let [title_area, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(rect);
+    frame.render_widget(
+        Paragraph::new(Line::from("🎹 ═══ CONTROL PANEL ═══ 🎹").yellow().bold().centered()),
+        title_area,
+    );
+
+    let rows = body.height as usize;
+    let capacity = rows * columns as usize;
+    let shown = if capacity >= CONTROLS.len() { CONTROLS.len() } else { capacity.saturating_sub(1) };
+    let mut lines: Vec<Line> = CONTROLS[..shown]
+        .iter()
+        .map(|(icon, actions, desc, color)| {
+            let desc = match actions.first() {
+                Some(Action::Tempo(_)) => format!(
+                    "{} ({})",
+                    desc,
+                    TEMPO_PRESETS.map(|bpm| bpm.to_string()).join("/")
+                ),
+                _ => desc.to_string(),
+            };
+            Line::from(vec![
+                Span::styled(format!("{:15}", format!("{} {}", icon, bindings.label(actions))), Style::new().fg(*color)),
+                " - ".white(),
+                Span::styled(desc, Style::new().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+    if shown < CONTROLS.len() {
+        lines.push(Line::from(format!("… {} more, enlarge the terminal to see every key", CONTROLS.len() - shown)).dark_gray());
     }
 
-    writer.flush()?;
-    Ok(())
+    let column_areas = Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns as usize]).split(body);
+    for (column, area) in column_areas.iter().enumerate() {
+        let column_lines: Vec<Line> = lines.iter().skip(column * rows).take(rows).cloned().collect();
+        let indented = Block::default().padding(Padding::left(2));
+        frame.render_widget(Paragraph::new(column_lines).block(indented), *area);
+    }
 }
 
-fn generate_enhanced_tick_animation(state: &Arc<AtomicState>) -> String {
-    const ANIMATION_WIDTH: usize = 70;
+fn generate_enhanced_tick_animation(snapshot: &MetronomeSnapshot, width: usize) -> String {
     const PULSE_SYMBOLS: [char; 4] = ['♪', '♫', '♬', '♭'];
 
-    let bpm = state.bpm.load(Ordering::Relaxed);
-    let is_running = state.is_running.load(Ordering::Relaxed);
-    let tick_count = state.tick_count.load(Ordering::Relaxed);
+    let bpm = snapshot.bpm.max(1);
+    let is_running = snapshot.is_running;
+    let tick_count = snapshot.tick_count;
 
     if !is_running {
-        let idle_pattern = "=".repeat(ANIMATION_WIDTH);
+        let idle_pattern = "=".repeat(width);
         return format!("⏸️  {}", idle_pattern);
     }
 
-    let elapsed = state.get_last_tick_elapsed();
-    let beat_duration = Duration::from_millis(60000 / bpm as u64);
+    let elapsed = snapshot.last_beat.elapsed();
+    // Subdivisions and polyrhythms click more often than once a beat
+    let beat_duration = Duration::from_millis(60000 / bpm as u64).mul_f32(snapshot.interval);
 
     let progress = if beat_duration.as_millis() > 0 {
         (elapsed.as_millis() as f64 / beat_duration.as_millis() as f64).min(1.0)
@@ -398,13 +496,13 @@ fn generate_enhanced_tick_animation(state: &Arc<AtomicState>) -> String {
         0.0
     };
 
-    let mut animation = vec!['-'; ANIMATION_WIDTH];
+    let mut animation = vec!['-'; width];
 
-    let beats_per_measure = 4;
-    let marker_spacing = ANIMATION_WIDTH / beats_per_measure;
+    let beats_per_measure = (snapshot.beats_per_bar.max(1) as usize).min(width.max(1));
+    let marker_spacing = width / beats_per_measure;
     for i in 0..beats_per_measure {
         let pos = i * marker_spacing;
-        if pos < ANIMATION_WIDTH {
+        if pos < width {
             let measure_num = (tick_count as usize / beats_per_measure) % 4;
             animation[pos] = match measure_num {
                 0 => '|',
@@ -416,8 +514,8 @@ fn generate_enhanced_tick_animation(state: &Arc<AtomicState>) -> String {
         }
     }
 
-    let tick_pos = (progress * (ANIMATION_WIDTH - 1) as f64) as usize;
-    if tick_pos < ANIMATION_WIDTH {
+    let tick_pos = (progress * (width - 1) as f64) as usize;
+    if tick_pos < width {
         let pulse_index = (tick_count as usize) % PULSE_SYMBOLS.len();
         animation[tick_pos] = PULSE_SYMBOLS[pulse_index];
     }
@@ -428,14 +526,14 @@ fn generate_enhanced_tick_animation(state: &Arc<AtomicState>) -> String {
         let intensity = ((1.0 - fade_progress) * 4.0) as usize;
 
         for i in 0..=intensity {
-            if tick_pos >= i && tick_pos + i < ANIMATION_WIDTH {
+            if tick_pos >= i && tick_pos + i < width {
                 if i == 0 {
                     animation[tick_pos] = '*';
                 } else if i <= 2 {
                     if tick_pos >= i {
                         animation[tick_pos - i] = 'o';
                     }
-                    if tick_pos + i < ANIMATION_WIDTH {
+                    if tick_pos + i < width {
                         animation[tick_pos + i] = 'o';
                     }
                 }
@@ -459,30 +557,3 @@ fn create_progress_bar(progress: f64, width: usize, filled_char: char, empty_cha
     }
     bar
 }
-
-fn draw_box_border(
-    writer: &mut BufWriter<Stdout>,
-    x: u16,
-    y: u16,
-    width: u16,
-    height: u16,
-) -> Result<(), Box<dyn std::error::Error>> {
-    execute!(writer, cursor::MoveTo(x, y), Print("+"))?;
-    for _ in 1..width - 1 {
-        execute!(writer, Print("-"))?;
-    }
-    execute!(writer, Print("+"))?;
-
-    for i in 1..height - 1 {
-        execute!(writer, cursor::MoveTo(x, y + i), Print("|"))?;
-        execute!(writer, cursor::MoveTo(x + width - 1, y + i), Print("|"))?;
-    }
-
-    execute!(writer, cursor::MoveTo(x, y + height - 1), Print("+"))?;
-    for _ in 1..width - 1 {
-        execute!(writer, Print("-"))?;
-    }
-    execute!(writer, Print("+"))?;
-
-    Ok(())
-}
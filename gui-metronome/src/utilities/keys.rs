@@ -0,0 +1,424 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const TEMPO_PRESETS: [u32; 4] = [60, 120, 180, 200];
+pub const PRESET_SLOTS: usize = 9;
+
+// Everything a key can do; the dispatcher in main matches on these
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    StartStop,
+    RandomMode,
+    BpmUp,
+    BpmDown,
+    BpmUpFine,
+    BpmDownFine,
+    NextMode,
+    PreviousMode,
+    // Move between the settings of the current mode
+    NextSetting,
+    PreviousSetting,
+    // Change the selected setting
+    SettingUp,
+    SettingDown,
+    // Practice mode's sections
+    AddSection,
+    RemoveSection,
+    NextSound,
+    PreviousSound,
+    TestSound,
+    VolumeUp,
+    VolumeDown,
+    // Index into TEMPO_PRESETS
+    Tempo(usize),
+    // Preset slots count from 0, the keys from 1
+    RecallPreset(usize),
+    SavePreset(usize),
+}
+
+impl Action {
+    const FIXED: [Action; 20] = [
+        Action::Quit,
+        Action::StartStop,
+        Action::RandomMode,
+        Action::BpmUp,
+        Action::BpmDown,
+        Action::BpmUpFine,
+        Action::BpmDownFine,
+        Action::NextMode,
+        Action::PreviousMode,
+        Action::NextSetting,
+        Action::PreviousSetting,
+        Action::SettingUp,
+        Action::SettingDown,
+        Action::AddSection,
+        Action::RemoveSection,
+        Action::NextSound,
+        Action::PreviousSound,
+        Action::TestSound,
+        Action::VolumeUp,
+        Action::VolumeDown,
+    ];
+
+    pub fn all() -> impl Iterator<Item = Action> {
+        Self::FIXED
+            .into_iter()
+            .chain((0..TEMPO_PRESETS.len()).map(Action::Tempo))
+            .chain((0..PRESET_SLOTS).map(Action::RecallPreset))
+            .chain((0..PRESET_SLOTS).map(Action::SavePreset))
+    }
+
+    // The name used in the `[keys]` table of config.toml
+    pub fn name(&self) -> String {
+        match self {
+            Action::Quit => "quit".to_string(),
+            Action::StartStop => "start_stop".to_string(),
+            Action::RandomMode => "random_mode".to_string(),
+            Action::BpmUp => "bpm_up".to_string(),
+            Action::BpmDown => "bpm_down".to_string(),
+            Action::BpmUpFine => "bpm_up_fine".to_string(),
+            Action::BpmDownFine => "bpm_down_fine".to_string(),
+            Action::NextMode => "next_mode".to_string(),
+            Action::PreviousMode => "previous_mode".to_string(),
+            Action::NextSetting => "next_setting".to_string(),
+            Action::PreviousSetting => "previous_setting".to_string(),
+            Action::SettingUp => "setting_up".to_string(),
+            Action::SettingDown => "setting_down".to_string(),
+            Action::AddSection => "add_section".to_string(),
+            Action::RemoveSection => "remove_section".to_string(),
+            Action::NextSound => "next_sound".to_string(),
+            Action::PreviousSound => "previous_sound".to_string(),
+            Action::TestSound => "test_sound".to_string(),
+            Action::VolumeUp => "volume_up".to_string(),
+            Action::VolumeDown => "volume_down".to_string(),
+            Action::Tempo(i) => format!("tempo_{}", i + 1),
+            Action::RecallPreset(i) => format!("preset_{}", i + 1),
+            Action::SavePreset(i) => format!("save_preset_{}", i + 1),
+        }
+    }
+
+    // In the same form as the config file
+    fn default_keys(&self) -> Vec<String> {
+        let keys: &[&str] = match self {
+            Action::Quit => &["q", "esc"],
+            Action::StartStop => &["space", "enter"],
+            Action::RandomMode => &["r"],
+            Action::BpmUp => &["up"],
+            Action::BpmDown => &["down"],
+            Action::BpmUpFine => &["right"],
+            Action::BpmDownFine => &["left"],
+            Action::NextMode => &["m"],
+            Action::PreviousMode => &["M"],
+            Action::NextSetting => &["tab"],
+            Action::PreviousSetting => &["backtab"],
+            Action::SettingUp => &["+", "="],
+            Action::SettingDown => &["-", "_"],
+            Action::AddSection => &["insert"],
+            Action::RemoveSection => &["delete"],
+            Action::NextSound => &["s", "n"],
+            Action::PreviousSound => &["a", "p"],
+            Action::TestSound => &["t"],
+            Action::VolumeUp => &["v"],
+            Action::VolumeDown => &["c"],
+            Action::Tempo(i) => return vec![format!("f{}", i + 1)],
+            Action::RecallPreset(i) => return vec![format!("{}", i + 1)],
+            Action::SavePreset(i) => return vec![format!("alt+{}", i + 1)],
+        };
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    // Only ALT and CTRL; shift is already part of the character
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    // "space", "f1", "q", "+", "alt+1", "ctrl+s"; names are case-insensitive, single characters are not,
+    // so "M" is shift+m
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec.trim();
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if lower.starts_with("alt+") && rest.len() > 4 {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[4..];
+            } else if lower.starts_with("ctrl+") && rest.len() > 5 {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[5..];
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "insert" => KeyCode::Insert,
+                "delete" => KeyCode::Delete,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("'{}' is not a key", spec)),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            modifiers: event.modifiers & (KeyModifiers::ALT | KeyModifiers::CONTROL),
+        }
+    }
+
+    // The next key along, so runs like 1..9 or F1..F4 can be shown as a range
+    fn follows(&self, previous: &KeyBinding) -> bool {
+        if self.modifiers != previous.modifiers {
+            return false;
+        }
+        match (self.code, previous.code) {
+            (KeyCode::Char(c), KeyCode::Char(p)) => c as u32 == p as u32 + 1,
+            (KeyCode::F(n), KeyCode::F(p)) => n == p + 1,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "CTRL+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "ALT+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "SPACE"),
+            KeyCode::Char(c) if c.is_uppercase() => write!(f, "SHIFT+{}", c),
+            KeyCode::Char(c) => write!(f, "{}", c.to_uppercase()),
+            KeyCode::Enter => write!(f, "ENTER"),
+            KeyCode::Esc => write!(f, "ESC"),
+            KeyCode::Tab => write!(f, "TAB"),
+            KeyCode::BackTab => write!(f, "SHIFT+TAB"),
+            KeyCode::Backspace => write!(f, "BACKSPACE"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Home => write!(f, "HOME"),
+            KeyCode::End => write!(f, "END"),
+            KeyCode::PageUp => write!(f, "PGUP"),
+            KeyCode::PageDown => write!(f, "PGDN"),
+            KeyCode::Insert => write!(f, "INS"),
+            KeyCode::Delete => write!(f, "DEL"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// The active key map: the defaults, with the actions named in the config's
+/// `[keys]` table replaced. Each key does at most one thing.
+pub struct KeyBindings {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl KeyBindings {
+    /// Builds the key map from the config, reporting unknown actions, keys
+    /// that cannot be read and keys bound to more than one action. On a
+    /// conflict the configured binding beats a default one, and otherwise the
+    /// action that comes first in `Action::all()` keeps the key.
+    pub fn from_config(keys: &BTreeMap<String, Vec<String>>) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut configured: Vec<(Action, Vec<KeyBinding>)> = Vec::new();
+        for (name, specs) in keys {
+            let Some(action) = Action::all().find(|action| action.name() == *name) else {
+                errors.push(format!("keys.{}: no such action", name));
+                continue;
+            };
+            let mut bindings = Vec::new();
+            for spec in specs {
+                match KeyBinding::parse(spec) {
+                    Ok(binding) => bindings.push(binding),
+                    Err(message) => errors.push(format!("keys.{}: {}", name, message)),
+                }
+            }
+            configured.push((action, bindings));
+        }
+        configured.sort_by_key(|(action, _)| Action::all().position(|other| other == *action));
+
+        let defaults: Vec<(Action, Vec<KeyBinding>)> = Action::all()
+            .filter(|action| !configured.iter().any(|(configured, _)| configured == action))
+            .map(|action| {
+                let keys = action.default_keys().iter().filter_map(|spec| KeyBinding::parse(spec).ok()).collect();
+                (action, keys)
+            })
+            .collect();
+
+        let mut bindings: Vec<(KeyBinding, Action)> = Vec::new();
+        for (action, keys) in configured.into_iter().chain(defaults) {
+            for key in keys {
+                match bindings.iter().find(|(bound, _)| *bound == key) {
+                    Some((_, owner)) => errors.push(format!(
+                        "keys: {} is bound to both {} and {}; it stays with {}",
+                        key,
+                        owner.name(),
+                        action.name(),
+                        owner.name()
+                    )),
+                    None => bindings.push((key, action)),
+                }
+            }
+        }
+        (Self { bindings }, errors)
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        let pressed = KeyBinding::from_event(event);
+        self.bindings.iter().find(|(key, _)| *key == pressed).map(|(_, action)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = KeyBinding> + '_ {
+        self.bindings.iter().filter(move |(_, bound)| *bound == action).map(|(key, _)| *key)
+    }
+
+    /// The keys for a help line. One action lists all its keys ("SPACE/ENTER");
+    /// several list their first key each, as a range when they run on ("F1-F4").
+    pub fn label(&self, actions: &[Action]) -> String {
+        let keys: Vec<KeyBinding> = match actions {
+            [action] => self.keys(*action).collect(),
+            _ => actions.iter().filter_map(|action| self.keys(*action).next()).collect(),
+        };
+        if keys.is_empty() {
+            return "(unbound)".to_string();
+        }
+
+        let is_run = actions.len() > 2 && keys.len() == actions.len() && keys.windows(2).all(|pair| pair[1].follows(&pair[0]));
+        if is_run {
+            let last = KeyBinding { modifiers: KeyModifiers::NONE, ..keys[keys.len() - 1] };
+            return format!("{}-{}", keys[0], last);
+        }
+        keys.iter().map(|key| key.to_string()).collect::<Vec<_>>().join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding { code, modifiers }
+    }
+
+    fn config(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(name, keys)| (name.to_string(), keys.iter().map(|key| key.to_string()).collect()))
+            .collect()
+    }
+
+    fn pressed(bindings: &KeyBindings, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        bindings.action(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn names_ignore_case_but_characters_do_not() {
+        assert_eq!(KeyBinding::parse("Space"), Ok(key(KeyCode::Char(' '), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("PAGEDOWN"), Ok(key(KeyCode::PageDown, KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("m"), Ok(key(KeyCode::Char('m'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("M"), Ok(key(KeyCode::Char('M'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("+"), Ok(key(KeyCode::Char('+'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn modifiers_stack_in_any_order() {
+        let both = KeyModifiers::ALT | KeyModifiers::CONTROL;
+        assert_eq!(KeyBinding::parse("alt+1"), Ok(key(KeyCode::Char('1'), KeyModifiers::ALT)));
+        assert_eq!(KeyBinding::parse("Ctrl+s"), Ok(key(KeyCode::Char('s'), KeyModifiers::CONTROL)));
+        assert_eq!(KeyBinding::parse("ctrl+alt+f2"), Ok(key(KeyCode::F(2), both)));
+        assert_eq!(KeyBinding::parse("alt+ctrl+f2"), Ok(key(KeyCode::F(2), both)));
+        // A lone "+" after a modifier is the key itself
+        assert_eq!(KeyBinding::parse("alt++"), Ok(key(KeyCode::Char('+'), KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn function_keys_run_from_f1_to_f12() {
+        assert_eq!(KeyBinding::parse("f1"), Ok(key(KeyCode::F(1), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("F12"), Ok(key(KeyCode::F(12), KeyModifiers::NONE)));
+        assert!(KeyBinding::parse("f0").is_err());
+        assert!(KeyBinding::parse("f13").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for spec in ["", "alt+", "spacebar", "shift+a", "ctrl+"] {
+            assert!(KeyBinding::parse(spec).is_err(), "{:?} was accepted", spec);
+        }
+    }
+
+    #[test]
+    fn defaults_bind_every_key_once() {
+        let (bindings, errors) = KeyBindings::from_config(&BTreeMap::new());
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(pressed(&bindings, KeyCode::Char(' '), KeyModifiers::NONE), Some(Action::StartStop));
+        assert_eq!(pressed(&bindings, KeyCode::Char('M'), KeyModifiers::SHIFT), Some(Action::PreviousMode));
+        assert_eq!(pressed(&bindings, KeyCode::Char('3'), KeyModifiers::ALT), Some(Action::SavePreset(2)));
+        assert_eq!(bindings.label(&[Action::Tempo(0), Action::Tempo(1), Action::Tempo(2), Action::Tempo(3)]), "F1-F4");
+    }
+
+    #[test]
+    fn configured_keys_replace_the_defaults() {
+        let (bindings, errors) = KeyBindings::from_config(&config(&[("start_stop", &["x"])]));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(pressed(&bindings, KeyCode::Char('x'), KeyModifiers::NONE), Some(Action::StartStop));
+        assert_eq!(pressed(&bindings, KeyCode::Char(' '), KeyModifiers::NONE), None);
+        assert_eq!(pressed(&bindings, KeyCode::Enter, KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn a_configured_key_wins_over_a_default() {
+        let (bindings, errors) = KeyBindings::from_config(&config(&[("test_sound", &["q"])]));
+        assert_eq!(pressed(&bindings, KeyCode::Char('q'), KeyModifiers::NONE), Some(Action::TestSound));
+        // Quit keeps its other default
+        assert_eq!(pressed(&bindings, KeyCode::Esc, KeyModifiers::NONE), Some(Action::Quit));
+        assert_eq!(errors, vec!["keys: Q is bound to both test_sound and quit; it stays with test_sound"]);
+    }
+
+    #[test]
+    fn between_configured_keys_the_earlier_action_wins() {
+        // The table is read in name order, but ownership follows Action::all()
+        let (bindings, errors) = KeyBindings::from_config(&config(&[("volume_up", &["x"]), ("bpm_up", &["x"])]));
+        assert_eq!(pressed(&bindings, KeyCode::Char('x'), KeyModifiers::NONE), Some(Action::BpmUp));
+        assert_eq!(bindings.label(&[Action::VolumeUp]), "(unbound)");
+        assert_eq!(errors, vec!["keys: X is bound to both bpm_up and volume_up; it stays with bpm_up"]);
+    }
+
+    #[test]
+    fn bad_entries_are_reported_and_the_rest_kept() {
+        let (bindings, errors) = KeyBindings::from_config(&config(&[("jump", &["j"]), ("bpm_up", &["k", "hyper+k"])]));
+        assert_eq!(pressed(&bindings, KeyCode::Char('k'), KeyModifiers::NONE), Some(Action::BpmUp));
+        assert_eq!(pressed(&bindings, KeyCode::Char('j'), KeyModifiers::NONE), None);
+        assert_eq!(errors, vec!["keys.bpm_up: 'hyper+k' is not a key", "keys.jump: no such action"]);
+    }
+}
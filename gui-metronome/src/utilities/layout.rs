@@ -0,0 +1,152 @@
+use ratatui::layout::{Margin, Position, Rect};
+
+pub const MIN_WIDTH: u16 = 30;
+pub const MIN_HEIGHT: u16 = 6;
+const MAX_WIDTH: u16 = 110;
+// Panels sit side by side from this width, and the controls from twice this
+const TWO_COLUMN_WIDTH: u16 = 60;
+const CONTROL_COLUMN_WIDTH: u16 = 52;
+const PANEL_MAX_WIDTH: u16 = 40;
+const BORDERED_PANEL_HEIGHT: u16 = 4;
+const COMPACT_PANEL_HEIGHT: u16 = 2;
+const ANIMATION_HEIGHT: u16 = 3;
+// A cut down control panel still gets its title and this many keys
+const MIN_CONTROL_ROWS: u16 = 3;
+// A cut down mode panel still shows the mode and the selected setting
+const MIN_MODE_ROWS: u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Panel {
+    Bpm,
+    Sound,
+    Status,
+    Mode,
+    Volume,
+}
+
+/// Where everything goes at the current terminal size. When rows run short
+/// the panels lose their borders, then sections are left out from the least
+/// useful up: subtitle, sounds, footer, presets, the control panel (which
+/// first shrinks), the animation, the mode settings (which shrink too) and
+/// finally the lower panels.
+#[derive(Clone, Debug, Default)]
+pub struct ScreenLayout {
+    pub title: Rect,
+    pub subtitle: Option<Rect>,
+    pub animation: Option<Rect>,
+    pub panels: Vec<(Panel, Rect)>,
+    pub bordered: bool,
+    pub mode: Option<Rect>,
+    pub controls: Option<Rect>,
+    pub control_columns: u16,
+    pub sounds: Option<Rect>,
+    pub presets: Option<Rect>,
+    pub footer: Option<Rect>,
+}
+
+impl ScreenLayout {
+    // None when the terminal cannot even fit the tempo and status
+    pub fn new(area: Rect, control_count: u16, mode_rows: u16) -> Option<Self> {
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            return None;
+        }
+        let width = area.width.saturating_sub(2).min(MAX_WIDTH);
+        let x = area.x + (area.width - width) / 2;
+
+        // Rows of panels, most important first
+        let panel_rows: &[&[Panel]] = if width >= TWO_COLUMN_WIDTH {
+            &[&[Panel::Bpm, Panel::Sound], &[Panel::Status, Panel::Mode], &[Panel::Volume]]
+        } else {
+            &[&[Panel::Bpm], &[Panel::Status], &[Panel::Sound], &[Panel::Mode], &[Panel::Volume]]
+        };
+
+        let mut spare = area.height - 1;
+        let bordered = spare >= panel_rows.len() as u16 * BORDERED_PANEL_HEIGHT;
+        let panel_height = if bordered { BORDERED_PANEL_HEIGHT } else { COMPACT_PANEL_HEIGHT };
+        let panel_rows = &panel_rows[..(spare / panel_height).min(panel_rows.len() as u16) as usize];
+        spare -= panel_rows.len() as u16 * panel_height;
+
+        let mode = take(&mut spare, mode_rows).or_else(|| take(&mut spare, MIN_MODE_ROWS.min(mode_rows)));
+        let animation = take(&mut spare, ANIMATION_HEIGHT);
+        let control_columns = if width >= 2 * CONTROL_COLUMN_WIDTH { 2 } else { 1 };
+        let controls = take(&mut spare, 1 + control_count.div_ceil(control_columns))
+            .or_else(|| (spare > MIN_CONTROL_ROWS).then(|| std::mem::take(&mut spare)));
+        let presets = take(&mut spare, 2);
+        let footer = take(&mut spare, 1);
+        let sounds = take(&mut spare, 2);
+        let subtitle = take(&mut spare, 2);
+
+        // Top to bottom, with a blank row between sections while rows are left over
+        let mut y = area.y;
+        let mut place = |height: Option<u16>| {
+            let height = height?;
+            if y > area.y && spare > 0 {
+                y += 1;
+                spare -= 1;
+            }
+            let rect = Rect::new(x, y, width, height);
+            y += height;
+            Some(rect)
+        };
+        let title = place(Some(1)).unwrap_or_default();
+        let subtitle = place(subtitle);
+        let animation = place(animation);
+        let panel_area = place(Some(panel_rows.len() as u16 * panel_height)).unwrap_or_default();
+        let mode = place(mode);
+        let controls = place(controls);
+        let sounds = place(sounds);
+        let presets = place(presets);
+        let footer = place(footer);
+
+        let columns = panel_rows.first().map_or(1, |row| row.len() as u16);
+        let panel_width = ((width - 2 * (columns - 1)) / columns).min(PANEL_MAX_WIDTH);
+        let grid_x = x + (width - (panel_width * columns + 2 * (columns - 1))) / 2;
+        let mut panels = Vec::new();
+        for (row, row_panels) in panel_rows.iter().enumerate() {
+            for (column, panel) in row_panels.iter().enumerate() {
+                let rect = Rect::new(
+                    grid_x + column as u16 * (panel_width + 2),
+                    panel_area.y + row as u16 * panel_height,
+                    panel_width,
+                    panel_height,
+                );
+                panels.push((*panel, rect));
+            }
+        }
+
+        Some(Self {
+            title,
+            subtitle,
+            animation,
+            panels,
+            bordered,
+            mode,
+            controls,
+            control_columns,
+            sounds,
+            presets,
+            footer,
+        })
+    }
+
+    // For mouse clicks, which come in screen cells
+    pub fn panel_at(&self, column: u16, row: u16) -> Option<Panel> {
+        let position = Position { x: column, y: row };
+        self.panels.iter().find(|(_, rect)| rect.contains(position)).map(|(panel, _)| *panel)
+    }
+
+    // Inside the border and padding, where the panel's lines start
+    pub fn panel_content(&self, panel: Panel) -> Option<Rect> {
+        let (_, rect) = self.panels.iter().find(|(shown, _)| *shown == panel)?;
+        let margin = if self.bordered { Margin::new(2, 1) } else { Margin::new(1, 0) };
+        Some(rect.inner(&margin))
+    }
+}
+
+fn take(spare: &mut u16, rows: u16) -> Option<u16> {
+    if *spare < rows {
+        return None;
+    }
+    *spare -= rows;
+    Some(rows)
+}
@@ -1,5 +1,7 @@
-pub mod sound;
 pub mod sound_type;
 pub mod display;
-pub mod state;
 pub mod cache;
+pub mod profiles;
+pub mod keys;
+pub mod layout;
+pub mod settings;
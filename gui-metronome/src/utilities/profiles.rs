@@ -0,0 +1,128 @@
+use crossterm::{
+    cursor,
+    event::{Event, KeyCode, KeyEventKind, read},
+    execute,
+    terminal::{Clear, ClearType, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::Paragraph,
+};
+use std::io::{self, Stdout};
+
+use metronome_engine::utilities::profile::list_profiles;
+
+const NEW_NAME_LABEL: &str = "New profile name: ";
+
+// Asks who is practicing before anything loads. None when the user quits.
+pub fn choose_profile() -> io::Result<Option<String>> {
+    enable_raw_mode()?;
+    let choice = Terminal::new(CrosstermBackend::new(io::stdout())).and_then(|mut terminal| {
+        terminal.clear()?;
+        run_picker(&mut terminal)
+    });
+    execute!(io::stdout(), Clear(ClearType::All), cursor::MoveTo(0, 0), cursor::Show)?;
+    disable_raw_mode()?;
+    choice
+}
+
+fn run_picker(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<Option<String>> {
+    let profiles = list_profiles();
+    let mut selected = 0;
+    // Some while a new profile's name is being typed
+    let mut new_name: Option<String> = None;
+
+    loop {
+        // Drawing fits the frame to the terminal, so a resize only needs another pass
+        terminal.draw(|frame| draw_picker(frame, &profiles, selected, new_name.as_deref()))?;
+
+        let Event::Key(key_event) = read()? else {
+            continue;
+        };
+        if key_event.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        if let Some(name) = new_name.as_mut() {
+            match key_event.code {
+                KeyCode::Enter if !name.trim().is_empty() => return Ok(Some(name.trim().to_string())),
+                KeyCode::Esc => new_name = None,
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) if !matches!(c, '/' | '\\' | '.') => name.push(c),
+                _ => {}
+            }
+            continue;
+        }
+
+        match key_event.code {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(profiles.len() - 1),
+            KeyCode::Enter | KeyCode::Char(' ') => return Ok(Some(profiles[selected].clone())),
+            KeyCode::Char(digit @ '1'..='9') => {
+                if let Some(profile) = profiles.get(digit as usize - '1' as usize) {
+                    return Ok(Some(profile.clone()));
+                }
+            }
+            KeyCode::Char('n') => new_name = Some(String::new()),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn draw_picker(frame: &mut Frame, profiles: &[String], selected: usize, new_name: Option<&str>) {
+    let entries: Vec<Line> = profiles
+        .iter()
+        .enumerate()
+        .map(|(i, profile)| {
+            let entry = Line::from(format!("{} {}  {}", if i == selected { "▶" } else { " " }, i + 1, profile));
+            if i == selected { entry.green() } else { entry.white() }
+        })
+        .collect();
+    let prompt = match new_name {
+        Some(name) => vec![
+            Line::from(format!("{}{}", NEW_NAME_LABEL, name)).yellow(),
+            Line::default(),
+            Line::from("ENTER - Create and open • ESC - Cancel").dark_gray(),
+        ],
+        None => vec![Line::from("↑/↓ + ENTER or 1-9 - Open • N - New profile • Q - Quit").dark_gray()],
+    };
+
+    let area = frame.size();
+    let [_, title, _, list, _, hint, _] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(2),
+        Constraint::Max(entries.len() as u16),
+        Constraint::Length(1),
+        Constraint::Length(prompt.len() as u16),
+        Constraint::Min(0),
+    ])
+    .areas(area);
+
+    let title_line = Line::from(vec!["👤 ═══ ".magenta(), "WHO IS PRACTICING?".blue(), " ═══ 👤".magenta()]);
+    frame.render_widget(Paragraph::new(title_line.bold().centered()), title);
+
+    // The list and prompt share a column in the middle, so the entries line up
+    let width = entries.iter().chain(&prompt).map(Line::width).max().unwrap_or(0) as u16;
+    let column = |rect: Rect| {
+        let width = width.min(rect.width);
+        Rect { x: rect.x + (rect.width - width) / 2, width, ..rect }
+    };
+    // Scrolled so the selected profile stays in view on a short terminal
+    let scroll = (selected as u16 + 1).saturating_sub(list.height);
+    frame.render_widget(Paragraph::new(entries).scroll((scroll, 0)), column(list));
+
+    let hint = column(hint);
+    if let Some(name) = new_name {
+        let x = hint.x + (NEW_NAME_LABEL.chars().count() + name.chars().count()) as u16;
+        frame.set_cursor(x.min(hint.right().saturating_sub(1)), hint.y);
+    }
+    frame.render_widget(Paragraph::new(prompt), hint);
+}
@@ -0,0 +1,126 @@
+use metronome_engine::utilities::mode::{Setting, SettingKind, SettingValue};
+
+// A new practice section, when there is none to copy
+const NEW_SECTION: (u32, u32) = (120, 32);
+// Section lengths move a bar of 4/4 at a time
+const SECTION_BEATS_STEP: u32 = 4;
+
+// One selectable entry of the mode panel; the first number is the setting's index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    // A number, choice or toggle, or a section list with nothing in it
+    Value(usize),
+    // One click of an accent pattern
+    Step(usize, usize),
+    SectionBpm(usize, usize),
+    SectionBeats(usize, usize),
+}
+
+impl Field {
+    pub fn setting(&self) -> usize {
+        match *self {
+            Field::Value(setting)
+            | Field::Step(setting, _)
+            | Field::SectionBpm(setting, _)
+            | Field::SectionBeats(setting, _) => setting,
+        }
+    }
+}
+
+// Every field of the mode's settings, in the order they are drawn
+pub fn fields(settings: &[Setting]) -> Vec<Field> {
+    let mut fields = Vec::new();
+    for (i, setting) in settings.iter().enumerate() {
+        match &setting.value {
+            SettingValue::Pattern(pattern) => fields.extend((0..pattern.len()).map(|step| Field::Step(i, step))),
+            SettingValue::Sections(sections) if !sections.is_empty() => {
+                for section in 0..sections.len() {
+                    fields.push(Field::SectionBpm(i, section));
+                    fields.push(Field::SectionBeats(i, section));
+                }
+            }
+            _ => fields.push(Field::Value(i)),
+        }
+    }
+    fields
+}
+
+// Bigger ranges move in bigger steps, so crossing them takes a few dozen presses at most
+fn number_step(min: u32, max: u32) -> u32 {
+    match max.saturating_sub(min) {
+        0..=99 => 1,
+        100..=399 => 5,
+        _ => 10,
+    }
+}
+
+fn step_number(value: u32, (min, max): (u32, u32), step: u32, up: bool) -> u32 {
+    let value = if up { value.saturating_add(step) } else { value.saturating_sub(step) };
+    value.clamp(min, max)
+}
+
+/// The setting's value after moving the field up or down: numbers step
+/// through their range, choices cycle, toggles and accents flip. None when
+/// the value stays the same.
+pub fn step(setting: &Setting, field: Field, up: bool) -> Option<SettingValue> {
+    let value = match (&setting.kind, &setting.value, field) {
+        (SettingKind::Number { min, max, .. }, SettingValue::Number(value), Field::Value(_)) => {
+            SettingValue::Number(step_number(*value, (*min, *max), number_step(*min, *max), up))
+        }
+        (SettingKind::Choice(options), SettingValue::Number(value), Field::Value(_)) => {
+            let current = options.iter().position(|(option, _)| option == value).unwrap_or(0);
+            let next = if up { current + 1 } else { current + options.len() - 1 };
+            SettingValue::Number(options.get(next % options.len().max(1))?.0)
+        }
+        (SettingKind::Toggle, SettingValue::Toggle(value), Field::Value(_)) => SettingValue::Toggle(!value),
+        (SettingKind::Pattern, SettingValue::Pattern(pattern), Field::Step(_, step)) => {
+            let mut pattern = pattern.clone();
+            *pattern.get_mut(step)? ^= true;
+            SettingValue::Pattern(pattern)
+        }
+        (SettingKind::Sections { bpm, .. }, SettingValue::Sections(sections), Field::SectionBpm(_, section)) => {
+            let mut sections = sections.clone();
+            let (value, _) = sections.get_mut(section)?;
+            *value = step_number(*value, *bpm, number_step(bpm.0, bpm.1), up);
+            SettingValue::Sections(sections)
+        }
+        (SettingKind::Sections { beats, .. }, SettingValue::Sections(sections), Field::SectionBeats(_, section)) => {
+            let mut sections = sections.clone();
+            let (_, value) = sections.get_mut(section)?;
+            *value = step_number(*value, *beats, SECTION_BEATS_STEP, up);
+            SettingValue::Sections(sections)
+        }
+        _ => return None,
+    };
+    (value != setting.value).then_some(value)
+}
+
+// Adds a copy of the selected section after it, or a first one to an empty list
+pub fn add_section(setting: &Setting, field: Field) -> Option<SettingValue> {
+    let SettingValue::Sections(sections) = &setting.value else {
+        return None;
+    };
+    let mut sections = sections.clone();
+    match field {
+        Field::SectionBpm(_, section) | Field::SectionBeats(_, section) => {
+            sections.insert(section + 1, *sections.get(section)?);
+        }
+        _ => sections.push(NEW_SECTION),
+    }
+    Some(SettingValue::Sections(sections))
+}
+
+// Removes the selected section, but never the last one, so the mode always has something to practice
+pub fn remove_section(setting: &Setting, field: Field) -> Option<SettingValue> {
+    let (SettingValue::Sections(sections), Field::SectionBpm(_, section) | Field::SectionBeats(_, section)) =
+        (&setting.value, field)
+    else {
+        return None;
+    };
+    if section >= sections.len() || sections.len() == 1 {
+        return None;
+    }
+    let mut sections = sections.clone();
+    sections.remove(section);
+    Some(SettingValue::Sections(sections))
+}
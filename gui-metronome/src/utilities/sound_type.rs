@@ -1,7 +1,21 @@
-use crate::utilities::sound::{
-    create_beep_sound, create_click_sound, create_cowbell_sound, create_hihat_sound,
-    create_kick_sound, create_square_sound, create_triangle_sound, create_wood_block_sound,
-};
+use std::sync::OnceLock;
+
+use metronome_engine::utilities::loudness::match_loudness;
+use metronome_engine::{CUSTOM_SOUND_START, builtin_sound};
+
+use metronome_engine::utilities::sample::CustomSound;
+
+static CUSTOM_SOUNDS: OnceLock<Vec<CustomSound>> = OnceLock::new();
+
+// Must be called before the sound cache is built; later calls are ignored.
+// The engine has to be given the same sounds in the same order.
+pub fn register_custom_sounds(sounds: Vec<CustomSound>) {
+    let _ = CUSTOM_SOUNDS.set(sounds);
+}
+
+fn custom_sounds() -> &'static [CustomSound] {
+    CUSTOM_SOUNDS.get().map(Vec::as_slice).unwrap_or(&[])
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SoundType {
@@ -13,6 +27,7 @@ pub enum SoundType {
     Square,
     Triangle,
     Woodblock,
+    Custom(u16),
 }
 
 impl Default for SoundType {
@@ -33,14 +48,63 @@ impl SoundType {
         SoundType::Woodblock,
     ];
 
+    // Built-in sounds followed by every registered custom sound
+    pub fn all() -> impl Iterator<Item = SoundType> {
+        Self::ALL
+            .into_iter()
+            .chain((0..custom_sounds().len()).map(|i| SoundType::Custom(i as u16)))
+    }
+
+    pub fn count() -> usize {
+        Self::ALL.len() + custom_sounds().len()
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            SoundType::Custom(i) => Self::ALL.len() + *i as usize,
+            _ => Self::ALL.iter().position(|s| s == self).unwrap(),
+        }
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        let index = index.min(Self::count() - 1);
+        if index < Self::ALL.len() {
+            Self::ALL[index]
+        } else {
+            SoundType::Custom((index - Self::ALL.len()) as u16)
+        }
+    }
+
     pub fn next(&self) -> Self {
-        let current_idx = Self::ALL.iter().position(|&s| s == *self).unwrap();
-        Self::ALL[(current_idx + 1) % Self::ALL.len()]
+        Self::from_index((self.index() + 1) % Self::count())
     }
 
     pub fn prev(&self) -> Self {
-        let current_idx = Self::ALL.iter().position(|&s| s == *self).unwrap();
-        Self::ALL[(current_idx + Self::ALL.len() - 1) % Self::ALL.len()]
+        Self::from_index((self.index() + Self::count() - 1) % Self::count())
+    }
+
+    // Key of the sound in the engine's sound cache, which orders the built-in sounds differently
+    pub fn key(&self) -> u32 {
+        match self {
+            SoundType::Beep => 0,
+            SoundType::Kick => 1,
+            SoundType::Click => 2,
+            SoundType::Cowbell => 3,
+            SoundType::Hihat => 4,
+            SoundType::Woodblock => 5,
+            SoundType::Triangle => 6,
+            SoundType::Square => 7,
+            SoundType::Custom(i) => CUSTOM_SOUND_START + *i as u32,
+        }
+    }
+
+    pub fn from_key(key: u32) -> Option<Self> {
+        Self::all().find(|sound| sound.key() == key)
+    }
+
+    // Sound names in configs and presets are shared with the other frontends
+    pub fn find(name: &str) -> Option<Self> {
+        Self::all().find(|sound| sound.name().eq_ignore_ascii_case(name))
     }
 
     pub fn name(&self) -> &'static str {
@@ -53,6 +117,7 @@ impl SoundType {
             SoundType::Square => "Square",
             SoundType::Triangle => "Triangle",
             SoundType::Woodblock => "Woodblock",
+            SoundType::Custom(i) => &custom_sounds()[*i as usize].name,
         }
     }
 
@@ -66,19 +131,19 @@ impl SoundType {
             SoundType::Square => "⬜",
             SoundType::Triangle => "🔺",
             SoundType::Woodblock => "🪵",
+            SoundType::Custom(_) => "🎙️",
         }
     }
 
+    // At the level the engine plays it
     pub fn create_sound(&self) -> Vec<f32> {
         match self {
-            SoundType::Beep => create_beep_sound(),
-            SoundType::Kick => create_kick_sound(),
-            SoundType::Click => create_click_sound(),
-            SoundType::Cowbell => create_cowbell_sound(),
-            SoundType::Hihat => create_hihat_sound(),
-            SoundType::Square => create_square_sound(),
-            SoundType::Triangle => create_triangle_sound(),
-            SoundType::Woodblock => create_wood_block_sound(),
+            SoundType::Custom(i) => {
+                let mut samples = custom_sounds()[*i as usize].samples.clone();
+                match_loudness(&mut samples);
+                samples
+            }
+            _ => builtin_sound(self.key()).unwrap_or_default(),
         }
     }
 }
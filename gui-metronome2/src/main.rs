@@ -1,264 +1,271 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use eframe::egui;
-use rand::Rng;
-use rodio::{OutputStream, OutputStreamHandle, Sink, buffer::SamplesBuffer};
-use std::collections::HashMap;
 use std::f32::consts::PI;
-use std::sync::{
-    Arc, Mutex, RwLock,
-    atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
-    mpsc::{self, Receiver, Sender},
-};
-use std::thread;
+use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 
-mod utilities;
-use crate::utilities::sound::{
-    create_beep_sound, create_click_sound, create_cowbell_sound, create_hihat_sound,
-    create_kick_sound, create_square_sound, create_triangle_sound, create_wood_block_sound,
-    create_celebration_sound
+use metronome_engine::utilities::config::{Config, WindowGeometry, config_path, load_config_or_default, save_config};
+use metronome_engine::utilities::history::{
+    PracticeStats, TEMPO_RANGE_WIDTH, history_path, load_history, practice_stats, record_history,
 };
+use metronome_engine::utilities::hooks::{hooks_path, load_hooks, run_hooks};
+use metronome_engine::utilities::kit::{builtin_kits, kits_manifest_path, load_kits};
+use metronome_engine::utilities::loudness::match_loudness;
+use metronome_engine::utilities::mode::{Setting, SettingKind, SettingValue};
+use metronome_engine::utilities::profile::{
+    DEFAULT_PROFILE, active_profile, list_profiles, profile_from_args, select_profile,
+};
+use metronome_engine::utilities::preset::{
+    Preset, delete_preset, export_preset, import_preset, load_presets, presets_dir, save_preset,
+};
+use metronome_engine::utilities::script::{load_script_modes, scripts_dir};
+use metronome_engine::utilities::sink::{SinkConfig, SinkSettings, load_sinks, sinks_path};
+use metronome_engine::utilities::timeline::TimelineEvent;
+use metronome_engine::{CUSTOM_SOUND_START, Metronome, MetronomeCommand, MetronomeEvent};
 
-#[derive(Clone, Copy, PartialEq, Debug)]
-enum MetronomeMode {
-    Standard,
-    Random,
-    Practice,
-    Polyrhythm,
-    Ritardando,
-    Subdivision,
-    Countdown,
-}
-
-// Commands sent to the metronome thread
-#[derive(Debug, Clone)]
-enum MetronomeCommand {
-    Start,
-    Stop,
-    ChangeBpm(u32),
-    ChangeVolume(u32),
-    ChangeSoundType(u32),
-    ChangeMode(MetronomeMode),
-    UpdateRandomSettings { count: u32 },
-    UpdatePracticeSettings { sections: Vec<(u32, u32)> },
-    UpdatePolyrhythmSettings { primary: u32, secondary: u32, accent_primary: bool, accent_secondary: bool },
-    UpdateRitardandoSettings { start_bpm: u32, target_bpm: u32, duration: u32 },
-    UpdateSubdivisionSettings { subdivisions: u32, pattern: Vec<bool> },
-    UpdateCountdownSettings { duration_seconds: u32, enable_random_bpm: bool },
-    Reset,
-}
-
-// Events sent back from the metronome thread
-#[derive(Debug, Clone)]
-enum MetronomeEvent {
-    Beat { tick_count: u32, is_accent: bool },
-    ModeChanged { mode: MetronomeMode },
-    BpmChanged { bpm: u32 },
-    CountdownFinished,
-    Error { message: String },
-}
+mod utilities;
+use metronome_engine::utilities::sample::{custom_sounds_dir, load_custom_sounds, load_sample};
+use crate::utilities::synth::{ClickSynthParams, FilterKind, Oscillator, render_click, save_wav};
+
+// Changes are written to the config file at most this often, and on exit
+const CONFIG_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+// How far back the stats panel charts practice time
+const STATS_DAYS: u32 = 7;
+const STATS_WEEKS: u32 = 6;
+
+const BUILTIN_SOUNDS: [(&str, &str); 8] = [
+    ("🔔", "Beep"),
+    ("🥁", "Kick"),
+    ("🖱️", "Click"),
+    ("🔔", "Cowbell"),
+    ("🎺", "Hi-hat"),
+    ("🪵", "Woodblock"),
+    ("🔺", "Triangle"),
+    ("⬜", "Square"),
+];
 
 struct MetronomeApp {
-    // Communication with metronome thread
-    command_sender: Sender<MetronomeCommand>,
+    // The engine is changed only by sending commands and read back through snapshots
+    metronome: Metronome,
     event_receiver: Receiver<MetronomeEvent>,
     
-    // Shared state (read-only from UI thread)
-    shared_state: Arc<SharedMetronomeState>,
-    
     // UI-only state
     animation_progress: f32,
     beat_progress: f32,
     last_beat_time: Instant,
+    // The timeline event behind the latest click
+    last_event: Option<TimelineEvent>,
     celebration_animation: f32,
     celebration_time: Instant,
-    
-    // Audio resources
-    _stream: OutputStream,
-    #[allow(dead_code)]
-    stream_handle: OutputStreamHandle,
-}
-
-// Thread-safe shared state
-struct SharedMetronomeState {
-    // Core state
-    bpm: AtomicU32,
-    is_running: AtomicBool,
-    volume: AtomicU32,
-    sound_type: AtomicU32,
-    tick_count: AtomicU32,
-    
-    // Current mode (atomic for simple reads)
-    mode: AtomicUsize, // We'll cast MetronomeMode to/from usize
-    
-    // Mode-specific state (protected by RwLock for complex data)
-    random_state: RwLock<RandomState>,
-    practice_state: RwLock<PracticeState>,
-    polyrhythm_state: RwLock<PolyrhythmState>,
-    ritardando_state: RwLock<RitardandoState>,
-    subdivision_state: RwLock<SubdivisionState>,
-    countdown_state: RwLock<CountdownState>,
-    
-    // Beat timing
-    last_beat: RwLock<Instant>,
-}
-
-#[derive(Clone, Debug)]
-struct RandomState {
-    count: u32,
-    remaining_ticks: u32,
-}
-
-#[derive(Clone, Debug)]
-struct PracticeState {
-    sections: Vec<(u32, u32)>, // (BPM, beats)
-    current_section: u32,
-    section_remaining: u32,
+    custom_sound_names: Vec<String>,
+    kit_names: Vec<String>,
+    designer: SoundDesignerState,
+    presets: PresetsState,
+    // Read from the history when the stats panel is open and a session ended since
+    stats: Option<PracticeStats>,
+    // As last saved, with the window as last seen
+    config: Config,
+    config_saved_at: Instant,
 }
 
-#[derive(Clone, Debug)]
-struct PolyrhythmState {
-    primary: u32,
-    secondary: u32,
-    accent_primary: bool,
-    accent_secondary: bool,
+// UI-only state for the sound designer panel
+struct SoundDesignerState {
+    params: ClickSynthParams,
+    preview: Vec<f32>,
+    name: String,
+    status: Option<String>,
 }
 
-#[derive(Clone, Debug)]
-struct RitardandoState {
-    start_bpm: u32,
-    target_bpm: u32,
-    duration: u32,
-    remaining: u32,
+// UI-only state for the presets panel
+struct PresetsState {
+    list: Vec<Preset>,
+    name: String,
+    // File to import from or export to
+    path: String,
+    status: Option<String>,
 }
 
-#[derive(Clone, Debug)]
-struct SubdivisionState {
-    subdivisions: u32,
-    accent_pattern: Vec<bool>,
-}
+impl MetronomeApp {
+    fn new(config: Config) -> Self {
+        let mut builder = Metronome::builder();
+
+        let mut custom_sound_names = Vec::new();
+        if let Some(dir) = custom_sounds_dir() {
+            let (custom_sounds, errors) = load_custom_sounds(&dir);
+            for error in errors {
+                eprintln!("Skipping custom sound {}", error);
+            }
+            for custom_sound in custom_sounds {
+                builder = builder.custom_sound(custom_sound.samples);
+                custom_sound_names.push(custom_sound.name);
+            }
+        }
 
-#[derive(Clone, Debug)]
-struct CountdownState {
-    duration_seconds: u32,
-    remaining_seconds: f32,
-    enable_random_bpm: bool,
-    original_bpm: u32,
-    next_bpm_change: f32,
-}
+        let resolve = |name: &str| resolve_sound(&custom_sound_names, name);
 
-impl Default for MetronomeApp {
-    fn default() -> Self {
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-        
-        // Create communication channels
-        let (command_sender, command_receiver) = mpsc::channel();
-        let (event_sender, event_receiver) = mpsc::channel();
-        
-        // Initialize shared state
-        let shared_state = Arc::new(SharedMetronomeState {
-            bpm: AtomicU32::new(120),
-            is_running: AtomicBool::new(false),
-            volume: AtomicU32::new(80),
-            sound_type: AtomicU32::new(0),
-            tick_count: AtomicU32::new(0),
-            mode: AtomicUsize::new(MetronomeMode::Standard as usize),
-            random_state: RwLock::new(RandomState {
-                count: 100,
-                remaining_ticks: 100,
-            }),
-            practice_state: RwLock::new(PracticeState {
-                sections: vec![(60, 32), (120, 32), (180, 32)],
-                current_section: 0,
-                section_remaining: 0,
-            }),
-            polyrhythm_state: RwLock::new(PolyrhythmState {
-                primary: 4,
-                secondary: 3,
-                accent_primary: true,
-                accent_secondary: true,
-            }),
-            ritardando_state: RwLock::new(RitardandoState {
-                start_bpm: 120,
-                target_bpm: 180,
-                duration: 64,
-                remaining: 0,
-            }),
-            subdivision_state: RwLock::new(SubdivisionState {
-                subdivisions: 1,
-                accent_pattern: vec![true, false, false, false],
-            }),
-            countdown_state: RwLock::new(CountdownState {
-                duration_seconds: 60,
-                remaining_seconds: 60.0,
-                enable_random_bpm: false,
-                original_bpm: 120,
-                next_bpm_change: 5.0,
-            }),
-            last_beat: RwLock::new(Instant::now()),
-        });
+        let mut kits = builtin_kits();
+        if let Some(path) = kits_manifest_path() {
+            match load_kits(&path, resolve) {
+                Ok(user_kits) => kits.extend(user_kits),
+                Err(message) => eprintln!("Failed to load sound kits: {}", message),
+            }
+        }
+        let kit_names = kits.iter().map(|kit| kit.name.clone()).collect();
 
-        // Create sound cache including celebration sound
-        let mut sound_cache = HashMap::new();
-        for i in 0..9 { // Increased to include celebration sound
-            let sound_data = match i {
-                0 => create_beep_sound(),
-                1 => create_kick_sound(),
-                2 => create_click_sound(),
-                3 => create_cowbell_sound(),
-                4 => create_hihat_sound(),
-                5 => create_wood_block_sound(),
-                6 => create_triangle_sound(),
-                7 => create_square_sound(),
-                8 => create_celebration_sound(), // New celebration sound
-                _ => create_beep_sound(),
-            };
-            sound_cache.insert(i, sound_data);
+        if let Some(dir) = scripts_dir() {
+            let (script_modes, errors) = load_script_modes(&dir);
+            for error in errors {
+                eprintln!("Skipping script mode {}", error);
+            }
+            for mode in script_modes {
+                builder = builder.mode(mode);
+            }
         }
 
-        // Start metronome thread
-        let shared_state_clone = Arc::clone(&shared_state);
-        let sink = Arc::new(Mutex::new(Sink::try_new(&stream_handle).unwrap()));
-        
-        thread::spawn(move || {
-            metronome_thread(shared_state_clone, sink, sound_cache, command_receiver, event_sender);
-        });
+        let sink_settings = match sinks_path().map(|path| load_sinks(&path)) {
+            Some(Ok(settings)) => settings,
+            Some(Err(message)) => {
+                eprintln!("Failed to load beat sinks: {}", message);
+                SinkSettings::default()
+            },
+            None => SinkSettings::default(),
+        };
+        let mut sink_configs = sink_settings.sinks;
+        // Every --sink SPEC on the command line adds to the ones in sinks.toml
+        let args: Vec<String> = std::env::args().collect();
+        for spec in args.windows(2).filter(|pair| pair[0] == "--sink").map(|pair| &pair[1]) {
+            match SinkConfig::from_spec(spec) {
+                Ok(config) => sink_configs.push(config),
+                Err(message) => eprintln!("Ignoring --sink {}: {}", spec, message),
+            }
+        }
+        for config in sink_configs.iter().filter(|config| config.enabled) {
+            match config.open() {
+                Ok(sink) => builder = builder.sink(sink),
+                Err(message) => eprintln!("Failed to open beat sink: {}", message),
+            }
+        }
+        builder = builder
+            .audio_clicks(sink_settings.audio.enabled)
+            .audio_latency(Duration::from_millis(sink_settings.audio.latency_ms));
+
+        let metronome = builder.kits(kits).config(&config, resolve).build().unwrap();
+        if let Some(path) = hooks_path() {
+            match load_hooks(&path) {
+                Ok(hooks) => run_hooks(&metronome, hooks, |message| eprintln!("{}", message)),
+                Err(message) => eprintln!("Failed to load hooks: {}", message),
+            }
+        }
+        // Subscribed first, so a session is written before the app hears it ended
+        if let Some(path) = history_path() {
+            record_history(&metronome, path, |message| eprintln!("{}", message));
+        }
+        let event_receiver = metronome.subscribe();
+
+        let mut presets = Vec::new();
+        if let Some(dir) = presets_dir() {
+            let errors;
+            (presets, errors) = load_presets(&dir);
+            for error in errors {
+                eprintln!("Skipping preset {}", error);
+            }
+        }
 
         Self {
-            command_sender,
+            metronome,
             event_receiver,
-            shared_state,
             animation_progress: 0.0,
             beat_progress: 0.0,
             last_beat_time: Instant::now(),
+            last_event: None,
             celebration_animation: 0.0,
             celebration_time: Instant::now(),
-            _stream,
-            stream_handle,
+            custom_sound_names,
+            kit_names,
+            designer: SoundDesignerState {
+                preview: render_click(&ClickSynthParams::default()),
+                params: ClickSynthParams::default(),
+                name: String::new(),
+                status: None,
+            },
+            presets: PresetsState {
+                list: presets,
+                name: String::new(),
+                path: String::new(),
+                status: None,
+            },
+            stats: None,
+            config,
+            config_saved_at: Instant::now(),
         }
     }
-}
 
-impl SharedMetronomeState {
-    fn get_mode(&self) -> MetronomeMode {
-        let mode_val = self.mode.load(Ordering::Relaxed);
-        match mode_val {
-            0 => MetronomeMode::Standard,
-            1 => MetronomeMode::Random,
-            2 => MetronomeMode::Practice,
-            3 => MetronomeMode::Polyrhythm,
-            4 => MetronomeMode::Ritardando,
-            5 => MetronomeMode::Subdivision,
-            6 => MetronomeMode::Countdown,
-            _ => MetronomeMode::Standard,
+    fn sound_name(&self, key: u32) -> String {
+        match BUILTIN_SOUNDS.get(key as usize) {
+            Some((_, name)) => name.to_string(),
+            None => key
+                .checked_sub(CUSTOM_SOUND_START)
+                .and_then(|i| self.custom_sound_names.get(i as usize))
+                .cloned()
+                .unwrap_or_default(),
         }
     }
-    
-    fn set_mode(&self, mode: MetronomeMode) {
-        self.mode.store(mode as usize, Ordering::Relaxed);
+
+    // Saves the config when the engine state or the window changed since the last save
+    fn save_config(&mut self, force: bool) {
+        if !force && self.config_saved_at.elapsed() < CONFIG_SAVE_INTERVAL {
+            return;
+        }
+        let snapshot = self.metronome.snapshot();
+        let mut config = self.config.clone();
+        let kit = snapshot.kit.checked_sub(1).and_then(|i| self.kit_names.get(i)).cloned().unwrap_or_default();
+        config.capture(&snapshot, &self.sound_name(snapshot.sound_type), &kit);
+        if config == self.config && !force {
+            return;
+        }
+
+        self.config_saved_at = Instant::now();
+        if let Some(path) = config_path()
+            && let Err(message) = save_config(&path, &config)
+        {
+            eprintln!("Failed to save settings: {}", message);
+        }
+        self.config = config;
+    }
+
+    // Sends the whole preset to the engine at once and makes it the saved config,
+    // so the settings of modes other than the current one are kept too
+    fn load_preset(&mut self, preset: &Preset) {
+        self.metronome.send(MetronomeCommand::LoadConfig {
+            config: Box::new(preset.config.clone()),
+            sound_type: resolve_sound(&self.custom_sound_names, &preset.config.sound),
+        });
+
+        let mut config = self.config.clone();
+        preset.apply_to(&mut config);
+        self.config_saved_at = Instant::now();
+        if let Some(path) = config_path()
+            && let Err(message) = save_config(&path, &config)
+        {
+            eprintln!("Failed to save settings: {}", message);
+        }
+        self.config = config;
     }
 }
 
+fn resolve_sound(custom_sound_names: &[String], name: &str) -> Option<u32> {
+    BUILTIN_SOUNDS
+        .iter()
+        .position(|(_, builtin)| builtin.eq_ignore_ascii_case(name))
+        .map(|i| i as u32)
+        .or_else(|| {
+            custom_sound_names
+                .iter()
+                .position(|custom| custom == name)
+                .map(|i| CUSTOM_SOUND_START + i as u32)
+        })
+}
+
 struct Theme {
     primary: egui::Color32,
     secondary: egui::Color32,
@@ -291,356 +298,57 @@ impl Theme {
             countdown: egui::Color32::from_rgb(255, 87, 34),
         }
     }
-}
-
-fn metronome_thread(
-    state: Arc<SharedMetronomeState>,
-    sink: Arc<Mutex<Sink>>,
-    sound_cache: HashMap<u32, Vec<f32>>,
-    command_receiver: Receiver<MetronomeCommand>,
-    event_sender: Sender<MetronomeEvent>,
-) {
-    let mut last_tick = Instant::now();
-    let mut subdivision_tick = 0u32;
-    let mut countdown_start_time = Instant::now();
-    
-    // Local state for the metronome thread
-    let mut local_random_state = state.random_state.read().unwrap().clone();
-    let mut local_practice_state = state.practice_state.read().unwrap().clone();
-    let mut local_polyrhythm_state = state.polyrhythm_state.read().unwrap().clone();
-    let mut local_ritardando_state = state.ritardando_state.read().unwrap().clone();
-    let mut local_subdivision_state = state.subdivision_state.read().unwrap().clone();
-    let mut local_countdown_state = state.countdown_state.read().unwrap().clone();
-
-    loop {
-        // Process commands (non-blocking)
-        while let Ok(command) = command_receiver.try_recv() {
-            match command {
-                MetronomeCommand::Start => {
-                    state.is_running.store(true, Ordering::Relaxed);
-                    state.tick_count.store(0, Ordering::Relaxed);
-                    last_tick = Instant::now();
-                    countdown_start_time = Instant::now();
-                    subdivision_tick = 0;
-                    
-                    // Reset mode-specific state
-                    let current_mode = state.get_mode();
-                    match current_mode {
-                        MetronomeMode::Random => {
-                            local_random_state.remaining_ticks = local_random_state.count;
-                        },
-                        MetronomeMode::Practice => {
-                            local_practice_state.current_section = 0;
-                            local_practice_state.section_remaining = 0;
-                        },
-                        MetronomeMode::Ritardando => {
-                            local_ritardando_state.remaining = local_ritardando_state.duration;
-                            state.bpm.store(local_ritardando_state.start_bpm, Ordering::Relaxed);
-                        },
-                        MetronomeMode::Countdown => {
-                            local_countdown_state.remaining_seconds = local_countdown_state.duration_seconds as f32;
-                            local_countdown_state.original_bpm = state.bpm.load(Ordering::Relaxed);
-                            local_countdown_state.next_bpm_change = 5.0; // Change BPM every 5 seconds
-                        },
-                        _ => {},
-                    }
-                },
-                MetronomeCommand::Stop => {
-                    state.is_running.store(false, Ordering::Relaxed);
-                },
-                MetronomeCommand::ChangeBpm(bpm) => {
-                    state.bpm.store(bpm, Ordering::Relaxed);
-                },
-                MetronomeCommand::ChangeVolume(volume) => {
-                    state.volume.store(volume, Ordering::Relaxed);
-                },
-                MetronomeCommand::ChangeSoundType(sound_type) => {
-                    state.sound_type.store(sound_type, Ordering::Relaxed);
-                },
-                MetronomeCommand::ChangeMode(mode) => {
-                    state.set_mode(mode);
-                    let _ = event_sender.send(MetronomeEvent::ModeChanged { mode });
-                },
-                MetronomeCommand::UpdateRandomSettings { count } => {
-                    local_random_state.count = count;
-                    local_random_state.remaining_ticks = count;
-                    *state.random_state.write().unwrap() = local_random_state.clone();
-                },
-                MetronomeCommand::UpdatePracticeSettings { sections } => {
-                    local_practice_state.sections = sections;
-                    *state.practice_state.write().unwrap() = local_practice_state.clone();
-                },
-                MetronomeCommand::UpdatePolyrhythmSettings { primary, secondary, accent_primary, accent_secondary } => {
-                    local_polyrhythm_state = PolyrhythmState {
-                        primary,
-                        secondary,
-                        accent_primary,
-                        accent_secondary,
-                    };
-                    *state.polyrhythm_state.write().unwrap() = local_polyrhythm_state.clone();
-                },
-                MetronomeCommand::UpdateRitardandoSettings { start_bpm, target_bpm, duration } => {
-                    local_ritardando_state.start_bpm = start_bpm;
-                    local_ritardando_state.target_bpm = target_bpm;
-                    local_ritardando_state.duration = duration.max(1);
-                    *state.ritardando_state.write().unwrap() = local_ritardando_state.clone();
-                },
-                MetronomeCommand::UpdateSubdivisionSettings { subdivisions, pattern } => {
-                    local_subdivision_state.subdivisions = subdivisions;
-                    local_subdivision_state.accent_pattern = pattern;
-                    *state.subdivision_state.write().unwrap() = local_subdivision_state.clone();
-                },
-                MetronomeCommand::UpdateCountdownSettings { duration_seconds, enable_random_bpm } => {
-                    local_countdown_state.duration_seconds = duration_seconds;
-                    local_countdown_state.enable_random_bpm = enable_random_bpm;
-                    *state.countdown_state.write().unwrap() = local_countdown_state.clone();
-                },
-                MetronomeCommand::Reset => {
-                    state.tick_count.store(0, Ordering::Relaxed);
-                    subdivision_tick = 0;
-                },
-            }
-        }
-
-        if state.is_running.load(Ordering::Relaxed) {
-            let current_mode = state.get_mode();
-            let mut effective_bpm = state.bpm.load(Ordering::Relaxed);
-            let mut should_tick = false;
-            let mut is_accent = false;
-            let mut use_alternate_sound = false;
-
-            // Handle countdown mode timing
-            if current_mode == MetronomeMode::Countdown {
-                let elapsed = countdown_start_time.elapsed().as_secs_f32();
-                local_countdown_state.remaining_seconds = (local_countdown_state.duration_seconds as f32 - elapsed).max(0.0);
-                
-                // Check if countdown finished
-                if local_countdown_state.remaining_seconds <= 0.0 {
-                    state.is_running.store(false, Ordering::Relaxed);
-                    
-                    // Play celebration sound
-                    let volume = state.volume.load(Ordering::Relaxed) as f32 / 100.0;
-                    if let Some(celebration_sound) = sound_cache.get(&8) {
-                        let volume_adjusted_sound: Vec<f32> = celebration_sound
-                            .iter()
-                            .map(|&sample| sample * volume * 1.5) // Louder for celebration
-                            .collect();
-                        
-                        let source = SamplesBuffer::new(1, 44100, volume_adjusted_sound);
-                        if let Ok(sink_guard) = sink.try_lock() {
-                            sink_guard.append(source);
-                        }
-                    }
-                    
-                    let _ = event_sender.send(MetronomeEvent::CountdownFinished);
-                    continue;
-                }
-                
-                // Handle random BPM changes during countdown
-                if local_countdown_state.enable_random_bpm {
-                    // Decrease the timer
-                    local_countdown_state.next_bpm_change -= 0.001; // Subtract 1ms each iteration
-                    
-                    if local_countdown_state.next_bpm_change <= 0.0 {
-                        let mut rng = rand::thread_rng();
-                        let new_bpm = rng.gen_range(80..=180);
-                        state.bpm.store(new_bpm, Ordering::Relaxed);
-                        local_countdown_state.next_bpm_change = rng.gen_range(3.0..=8.0); // Next change in 3-8 seconds
-                        let _ = event_sender.send(MetronomeEvent::BpmChanged { bpm: new_bpm });
-                    }
-                }
-                
-                // Update shared countdown state
-                if let Ok(mut shared_countdown) = state.countdown_state.try_write() {
-                    *shared_countdown = local_countdown_state.clone();
-                }
-            }
-
-            // Calculate beat interval based on mode
-            let beat_interval = match current_mode {
-                MetronomeMode::Subdivision => {
-                    let multiplier = match local_subdivision_state.subdivisions {
-                        1 => 1.0,  // Quarter notes
-                        2 => 2.0,  // Eighth notes
-                        3 => 3.0,  // Triplets
-                        4 => 4.0,  // Sixteenth notes
-                        _ => 1.0,
-                    };
-                    Duration::from_millis((60000.0 / (effective_bpm as f32 * multiplier)) as u64)
-                },
-                _ => Duration::from_millis(60000 / effective_bpm.max(1) as u64),
-            };
-
-            if last_tick.elapsed() >= beat_interval {
-                should_tick = true;
-                
-                match current_mode {
-                    MetronomeMode::Standard => {
-                        // Standard mode - just tick
-                    },
-                    
-                    MetronomeMode::Countdown => {
-                        // Countdown mode - accent every 10 seconds
-                        let seconds_elapsed = local_countdown_state.duration_seconds as f32 - local_countdown_state.remaining_seconds;
-                        if seconds_elapsed % 10.0 < 0.5 {
-                            is_accent = true;
-                        }
-                    },
-                    
-                    MetronomeMode::Random => {
-                        if local_random_state.remaining_ticks == 0 {
-                            local_random_state.remaining_ticks = local_random_state.count;
-                        }
-                        
-                        local_random_state.remaining_ticks = local_random_state.remaining_ticks.saturating_sub(1);
-                        
-                        if local_random_state.remaining_ticks == 0 {
-                            let mut rng = rand::thread_rng();
-                            let new_bpm = rng.gen_range(60..=200);
-                            state.bpm.store(new_bpm, Ordering::Relaxed);
-                            let _ = event_sender.send(MetronomeEvent::BpmChanged { bpm: new_bpm });
-                        }
-                        
-                        if let Ok(mut shared_random) = state.random_state.try_write() {
-                            *shared_random = local_random_state.clone();
-                        }
-                    },
-                    
-                    MetronomeMode::Practice => {
-                        if local_practice_state.section_remaining == 0 {
-                            let current_section = local_practice_state.current_section as usize;
-                            
-                            if current_section < local_practice_state.sections.len() {
-                                let (section_bpm, section_beats) = local_practice_state.sections[current_section];
-                                state.bpm.store(section_bpm, Ordering::Relaxed);
-                                local_practice_state.section_remaining = section_beats;
-                                
-                                let next_section = (current_section + 1) % local_practice_state.sections.len();
-                                local_practice_state.current_section = next_section as u32;
-                                
-                                let _ = event_sender.send(MetronomeEvent::BpmChanged { bpm: section_bpm });
-                            }
-                        }
-                        
-                        local_practice_state.section_remaining = local_practice_state.section_remaining.saturating_sub(1);
-                        
-                        if let Ok(mut shared_practice) = state.practice_state.try_write() {
-                            *shared_practice = local_practice_state.clone();
-                        }
-                    },
-                    
-                    MetronomeMode::Polyrhythm => {
-                        let tick_count = state.tick_count.load(Ordering::Relaxed);
-                        
-                        let primary_hit = local_polyrhythm_state.primary > 0 && (tick_count % local_polyrhythm_state.primary) == 0;
-                        let secondary_hit = local_polyrhythm_state.secondary > 0 && (tick_count % local_polyrhythm_state.secondary) == 0;
-                        
-                        if primary_hit && local_polyrhythm_state.accent_primary {
-                            is_accent = true;
-                        }
-                        if secondary_hit && local_polyrhythm_state.accent_secondary {
-                            use_alternate_sound = true;
-                        }
-                    },
-                    
-                    MetronomeMode::Ritardando => {
-                        if local_ritardando_state.remaining == 0 {
-                            local_ritardando_state.remaining = local_ritardando_state.duration;
-                        }
-                        
-                        let start_bpm = local_ritardando_state.start_bpm as f32;
-                        let target_bpm = local_ritardando_state.target_bpm as f32;
-                        let duration = local_ritardando_state.duration as f32;
-                        
-                        if duration > 0.0 {
-                            let progress = (duration - local_ritardando_state.remaining as f32) / duration;
-                            let current_bpm = start_bpm - (start_bpm - target_bpm) * progress;
-                            let current_bpm_u32 = (current_bpm as u32).max(1);
-                            state.bpm.store(current_bpm_u32, Ordering::Relaxed);
-                        } else {
-                            state.bpm.store(local_ritardando_state.target_bpm, Ordering::Relaxed);
-                        }
-                        
-                        local_ritardando_state.remaining = local_ritardando_state.remaining.saturating_sub(1);
-                        
-                        if let Ok(mut shared_ritardando) = state.ritardando_state.try_write() {
-                            *shared_ritardando = local_ritardando_state.clone();
-                        }
-                    },
-                    
-                    MetronomeMode::Subdivision => {
-                        if !local_subdivision_state.accent_pattern.is_empty() {
-                            let pattern_index = subdivision_tick as usize % local_subdivision_state.accent_pattern.len();
-                            is_accent = local_subdivision_state.accent_pattern[pattern_index];
-                        }
-                        
-                        subdivision_tick = subdivision_tick.wrapping_add(1);
-                    },
-                }
-
-                if should_tick {
-                    let new_tick_count = state.tick_count.fetch_add(1, Ordering::Relaxed) + 1;
-
-                    if let Ok(mut last_beat) = state.last_beat.try_write() {
-                        *last_beat = Instant::now();
-                    }
-
-                    let _ = event_sender.send(MetronomeEvent::Beat {
-                        tick_count: new_tick_count,
-                        is_accent,
-                    });
-
-                    // Play sound
-                    let volume = state.volume.load(Ordering::Relaxed) as f32 / 100.0;
-                    let mut sound_type = state.sound_type.load(Ordering::Relaxed);
-                    
-                    if use_alternate_sound {
-                        sound_type = (sound_type + 1) % 8;
-                    }
-                    
-                    let final_volume = if is_accent { 
-                        (volume * 1.5).min(1.0)
-                    } else { 
-                        volume 
-                    };
-
-                    if let Some(sound_data) = sound_cache.get(&sound_type) {
-                        let volume_adjusted_sound: Vec<f32> =
-                            sound_data.iter().map(|&sample| sample * final_volume).collect();
-
-                        let source = SamplesBuffer::new(1, 44100, volume_adjusted_sound);
 
-                        if let Ok(sink_guard) = sink.try_lock() {
-                            sink_guard.append(source);
-                        }
-                    }
-                }
-
-                last_tick = Instant::now();
-            }
-        } else {
-            last_tick = Instant::now();
-            subdivision_tick = 0;
+    fn mode_color(&self, mode_name: &str) -> Option<egui::Color32> {
+        match mode_name {
+            "Random" => Some(self.warning),
+            "Practice" => Some(self.practice),
+            "Polyrhythm" => Some(self.polyrhythm),
+            "Ritardando" => Some(self.error),
+            "Countdown" => Some(self.countdown),
+            _ => None,
         }
-
-        thread::sleep(Duration::from_millis(1));
     }
 }
 
 impl eframe::App for MetronomeApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_config(true);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ctx.input(|input| {
+            let viewport = input.viewport();
+            if let Some(inner) = viewport.inner_rect {
+                let position = viewport.outer_rect.map(|outer| outer.min);
+                self.config.window = Some(WindowGeometry {
+                    width: inner.width(),
+                    height: inner.height(),
+                    x: position.map(|position| position.x),
+                    y: position.map(|position| position.y),
+                });
+            }
+        });
+        self.save_config(false);
+
         // Process events from metronome thread
         while let Ok(event) = self.event_receiver.try_recv() {
             match event {
-                MetronomeEvent::Beat { is_accent, .. } => {
+                MetronomeEvent::Beat { event, .. } => {
                     self.last_beat_time = Instant::now();
+                    self.last_event = Some(event);
                 },
-                MetronomeEvent::CountdownFinished => {
+                MetronomeEvent::Finished { .. } => {
                     self.celebration_time = Instant::now();
                     self.celebration_animation = 1.0;
+                    self.stats = None;
                 },
+                MetronomeEvent::Bar { .. } => {},
                 MetronomeEvent::ModeChanged { .. } => {},
                 MetronomeEvent::BpmChanged { .. } => {},
+                MetronomeEvent::SectionChanged { .. } => {},
+                MetronomeEvent::Started { .. } => {},
+                MetronomeEvent::Stopped => self.stats = None,
                 MetronomeEvent::Error { message } => {
                     eprintln!("Metronome error: {}", message);
                 },
@@ -665,11 +373,14 @@ impl eframe::App for MetronomeApp {
         style.spacing.indent = 25.0;
         ctx.set_style(style);
 
-        let bpm = self.shared_state.bpm.load(Ordering::Relaxed);
-        let is_running = self.shared_state.is_running.load(Ordering::Relaxed);
-        let volume = self.shared_state.volume.load(Ordering::Relaxed);
-        let tick_count = self.shared_state.tick_count.load(Ordering::Relaxed);
-        let current_mode = self.shared_state.get_mode();
+        let snapshot = self.metronome.snapshot();
+        let bpm = snapshot.bpm;
+        let is_running = snapshot.is_running;
+        let volume = snapshot.volume;
+        let tick_count = snapshot.tick_count;
+        let (mode_icon, mode_name) = snapshot.modes[snapshot.mode];
+        // Modes without a colour of their own use the theme's primary gradient
+        let mode_color = theme.mode_color(mode_name);
 
         // Handle celebration animation
         if self.celebration_animation > 0.0 {
@@ -679,30 +390,15 @@ impl eframe::App for MetronomeApp {
         }
 
         if is_running {
-            if let Ok(last_beat) = self.shared_state.last_beat.try_read() {
-                let time_since_beat = last_beat.elapsed().as_millis() as f32;
-                let effective_bpm = match current_mode {
-                    MetronomeMode::Subdivision => {
-                        if let Ok(subdivision_state) = self.shared_state.subdivision_state.try_read() {
-                            let multiplier = match subdivision_state.subdivisions {
-                                1 => 1.0, 2 => 2.0, 3 => 3.0, 4 => 4.0, _ => 1.0,
-                            };
-                            bpm as f32 * multiplier
-                        } else {
-                            bpm as f32
-                        }
-                    },
-                    _ => bpm as f32,
-                };
-                let beat_interval_ms = 60000.0 / effective_bpm.max(1.0);
+            let time_since_beat = snapshot.last_beat.elapsed().as_millis() as f32;
+            let beat_interval_ms = 60000.0 * snapshot.interval / (bpm as f32).max(1.0);
 
-                self.beat_progress = (time_since_beat / beat_interval_ms).min(1.0);
+            self.beat_progress = (time_since_beat / beat_interval_ms).min(1.0);
 
-                if time_since_beat < 200.0 {
-                    self.animation_progress = 1.0 - (time_since_beat / 200.0);
-                } else {
-                    self.animation_progress = 0.0;
-                }
+            if time_since_beat < 200.0 {
+                self.animation_progress = 1.0 - (time_since_beat / 200.0);
+            } else {
+                self.animation_progress = 0.0;
             }
             ctx.request_repaint();
         } else {
@@ -720,7 +416,7 @@ impl eframe::App for MetronomeApp {
                 // Show celebration effects if active
                 if self.celebration_animation > 0.0 {
                     ui.heading(
-                        egui::RichText::new("🎉 COUNTDOWN COMPLETE! 🎉")
+                        egui::RichText::new(format!("🎉 {} COMPLETE! 🎉", mode_name.to_uppercase()))
                             .size(40.0)
                             .color(egui::Color32::from_rgb(255, 215, 0))
                             .strong(),
@@ -761,28 +457,11 @@ impl eframe::App for MetronomeApp {
                     );
                     ui.add_space(10.0);
 
-                    let modes = [
-                        (MetronomeMode::Standard, "🎵", "Standard"),
-                        (MetronomeMode::Random, "🎲", "Random"),
-                        (MetronomeMode::Practice, "🎯", "Practice"),
-                        (MetronomeMode::Polyrhythm, "🔄", "Polyrhythm"),
-                        (MetronomeMode::Ritardando, "🐌", "Ritardando"),
-                        (MetronomeMode::Subdivision, "🎼", "Subdivision"),
-                        (MetronomeMode::Countdown, "⏱️", "Countdown"),
-                    ];
-
                     ui.horizontal_wrapped(|ui| {
-                        for (mode, icon, name) in modes.iter() {
-                            let selected = *mode == current_mode;
+                        for (mode, (icon, name)) in snapshot.modes.iter().enumerate() {
+                            let selected = mode == snapshot.mode;
                             let button_color = if selected {
-                                match mode {
-                                    MetronomeMode::Random => theme.warning,
-                                    MetronomeMode::Practice => theme.practice,
-                                    MetronomeMode::Polyrhythm => theme.polyrhythm,
-                                    MetronomeMode::Ritardando => theme.error,
-                                    MetronomeMode::Countdown => theme.countdown,
-                                    _ => theme.primary,
-                                }
+                                mode_color.unwrap_or(theme.primary)
                             } else {
                                 theme.surface
                             };
@@ -798,7 +477,7 @@ impl eframe::App for MetronomeApp {
                                 )
                                 .clicked()
                             {
-                                let _ = self.command_sender.send(MetronomeCommand::ChangeMode(*mode));
+                                self.metronome.send(MetronomeCommand::ChangeMode(mode));
                             }
                         }
                     });
@@ -807,15 +486,7 @@ impl eframe::App for MetronomeApp {
             ui.add_space(20.0);
 
             // Mode-specific controls
-            match current_mode {
-                MetronomeMode::Random => self.draw_random_controls(ui, &theme),
-                MetronomeMode::Practice => self.draw_practice_controls(ui, &theme),
-                MetronomeMode::Polyrhythm => self.draw_polyrhythm_controls(ui, &theme),
-                MetronomeMode::Ritardando => self.draw_ritardando_controls(ui, &theme),
-                MetronomeMode::Subdivision => self.draw_subdivision_controls(ui, &theme),
-                MetronomeMode::Countdown => self.draw_countdown_controls(ui, &theme),
-                _ => {},
-            }
+            self.draw_mode_controls(ui, &theme);
 
             ui.add_space(20.0);
 
@@ -823,8 +494,13 @@ impl eframe::App for MetronomeApp {
             ui.vertical_centered(|ui| {
                 let base_size = 120.0;
                 let max_size = base_size + 40.0;
+                // Accented clicks pulse further than plain ones
+                let pulse_depth = match &self.last_event {
+                    Some(event) if !event.is_accent() => 25.0,
+                    _ => 40.0,
+                };
                 let pulse_size = if self.animation_progress > 0.0 {
-                    base_size + self.animation_progress * 40.0
+                    base_size + self.animation_progress * pulse_depth
                 } else {
                     base_size
                 };
@@ -843,29 +519,17 @@ impl eframe::App for MetronomeApp {
                         } else {
                             0.3 + self.animation_progress * 0.7
                         };
-                        match current_mode {
-                            MetronomeMode::Random => theme.warning,
-                            MetronomeMode::Practice => theme.practice,
-                            MetronomeMode::Polyrhythm => theme.polyrhythm,
-                            MetronomeMode::Countdown => if self.celebration_animation > 0.0 {
-                                egui::Color32::from_rgb(255, 215, 0) // Gold for celebration
-                            } else {
-                                theme.countdown
-                            },
-                            _ => egui::Color32::from_rgb(
+                        if self.celebration_animation > 0.0 {
+                            egui::Color32::from_rgb(255, 215, 0) // Gold for celebration
+                        } else {
+                            mode_color.unwrap_or(egui::Color32::from_rgb(
                                 (138.0 + (255.0 - 138.0) * intensity) as u8,
                                 (43.0 + (255.0 - 43.0) * intensity) as u8,
                                 (226.0 + (255.0 - 226.0) * intensity) as u8,
-                            ),
+                            ))
                         }
                     } else {
-                        match current_mode {
-                            MetronomeMode::Random => theme.warning,
-                            MetronomeMode::Practice => theme.practice,
-                            MetronomeMode::Polyrhythm => theme.polyrhythm,
-                            MetronomeMode::Countdown => theme.countdown,
-                            _ => theme.primary,
-                        }
+                        mode_color.unwrap_or(theme.primary)
                     }
                 } else {
                     egui::Color32::from_gray(80)
@@ -927,14 +591,10 @@ impl eframe::App for MetronomeApp {
                 } else {
                     40.0
                 };
-                let symbol = match current_mode {
-                    MetronomeMode::Random => "🎲",
-                    MetronomeMode::Practice => "🎯",
-                    MetronomeMode::Polyrhythm => "🔄",
-                    MetronomeMode::Ritardando => "🐌",
-                    MetronomeMode::Subdivision => "🎼",
-                    MetronomeMode::Countdown => if self.celebration_animation > 0.0 { "🎉" } else { "⏱️" },
-                    _ => "♪",
+                let symbol = if self.celebration_animation > 0.0 {
+                    "🎉"
+                } else {
+                    mode_icon
                 };
                 ui.painter().text(
                     rect.center(),
@@ -955,121 +615,97 @@ impl eframe::App for MetronomeApp {
 
             ui.add_space(20.0);
 
-            // Beat progress bar or countdown progress
+            // Beat progress bar
             ui.vertical_centered(|ui| {
-                if current_mode == MetronomeMode::Countdown {
-                    self.draw_countdown_progress(ui, &theme);
-                } else {
-                    ui.label(
-                        egui::RichText::new("Beat Progress")
-                            .size(14.0)
-                            .color(theme.accent),
-                    );
-                    ui.add_space(5.0);
+                ui.label(
+                    egui::RichText::new("Beat Progress")
+                        .size(14.0)
+                        .color(theme.accent),
+                );
+                ui.add_space(5.0);
 
-                    let slider_width = 400.0;
-                    let slider_height = 12.0;
-                    let slider_rect = ui
-                        .allocate_space([slider_width, slider_height + 20.0].into())
-                        .1;
+                let slider_width = 400.0;
+                let slider_height = 12.0;
+                let slider_rect = ui
+                    .allocate_space([slider_width, slider_height + 20.0].into())
+                    .1;
 
-                    let track_rect = egui::Rect::from_center_size(
-                        slider_rect.center(),
-                        egui::Vec2::new(slider_width, slider_height),
-                    );
-                    ui.painter().rect_filled(
-                        track_rect,
-                        egui::Rounding::same(slider_height / 2.0),
-                        egui::Color32::from_gray(40),
-                    );
+                let track_rect = egui::Rect::from_center_size(
+                    slider_rect.center(),
+                    egui::Vec2::new(slider_width, slider_height),
+                );
+                ui.painter().rect_filled(
+                    track_rect,
+                    egui::Rounding::same(slider_height / 2.0),
+                    egui::Color32::from_gray(40),
+                );
 
-                    let progress_width = slider_width * self.beat_progress;
-                    let progress_rect = egui::Rect::from_min_size(
-                        track_rect.min,
-                        egui::Vec2::new(progress_width, slider_height),
-                    );
+                let progress_width = slider_width * self.beat_progress;
+                let progress_rect = egui::Rect::from_min_size(
+                    track_rect.min,
+                    egui::Vec2::new(progress_width, slider_height),
+                );
 
-                    let progress_color = if is_running {
-                        if self.animation_progress > 0.5 {
-                            egui::Color32::from_rgb(255, 255, 255)
-                        } else {
-                            match current_mode {
-                                MetronomeMode::Random => theme.warning,
-                                MetronomeMode::Practice => theme.practice,
-                                MetronomeMode::Polyrhythm => theme.polyrhythm,
-                                MetronomeMode::Countdown => theme.countdown,
-                                _ => theme.primary,
-                            }
-                        }
+                let progress_color = if is_running {
+                    if self.animation_progress > 0.5 {
+                        egui::Color32::from_rgb(255, 255, 255)
                     } else {
-                        egui::Color32::from_gray(60)
-                    };
+                        mode_color.unwrap_or(theme.primary)
+                    }
+                } else {
+                    egui::Color32::from_gray(60)
+                };
 
-                    ui.painter().rect_filled(
-                        progress_rect,
-                        egui::Rounding::same(slider_height / 2.0),
-                        progress_color,
-                    );
+                ui.painter().rect_filled(
+                    progress_rect,
+                    egui::Rounding::same(slider_height / 2.0),
+                    progress_color,
+                );
 
-                    // Subdivision marks for subdivision mode
-                    if current_mode == MetronomeMode::Subdivision {
-                        if let Ok(subdivision_state) = self.shared_state.subdivision_state.try_read() {
-                            let subdivisions = subdivision_state.subdivisions;
-                            for i in 1..subdivisions {
-                                let tick_x = track_rect.min.x + (slider_width * i as f32) / subdivisions as f32;
-                                let tick_top = track_rect.min.y - 3.0;
-                                let tick_bottom = track_rect.max.y + 3.0;
-
-                                ui.painter().line_segment(
-                                    [
-                                        egui::pos2(tick_x, tick_top),
-                                        egui::pos2(tick_x, tick_bottom),
-                                    ],
-                                    egui::Stroke::new(1.0, egui::Color32::from_gray(100)),
-                                );
-                            }
-                        }
-                    } else {
-                        let num_subdivisions = 4;
-                        for i in 1..num_subdivisions {
-                            let tick_x =
-                                track_rect.min.x + (slider_width * i as f32) / num_subdivisions as f32;
-                            let tick_top = track_rect.min.y - 3.0;
-                            let tick_bottom = track_rect.max.y + 3.0;
-
-                            ui.painter().line_segment(
-                                [
-                                    egui::pos2(tick_x, tick_top),
-                                    egui::pos2(tick_x, tick_bottom),
-                                ],
-                                egui::Stroke::new(1.0, egui::Color32::from_gray(100)),
-                            );
-                        }
+                // Modes that click faster than the beat mark every click instead of the quarters
+                if snapshot.interval < 1.0 {
+                    let subdivisions = (1.0 / snapshot.interval).round() as u32;
+                    for i in 1..subdivisions {
+                        let tick_x = track_rect.min.x + (slider_width * i as f32) / subdivisions as f32;
+                        let tick_top = track_rect.min.y - 3.0;
+                        let tick_bottom = track_rect.max.y + 3.0;
+
+                        ui.painter().line_segment(
+                            [
+                                egui::pos2(tick_x, tick_top),
+                                egui::pos2(tick_x, tick_bottom),
+                            ],
+                            egui::Stroke::new(1.0, egui::Color32::from_gray(100)),
+                        );
                     }
-
-                    if is_running {
-                        let effective_bpm = match current_mode {
-                            MetronomeMode::Subdivision => {
-                                if let Ok(subdivision_state) = self.shared_state.subdivision_state.try_read() {
-                                    let multiplier = match subdivision_state.subdivisions {
-                                        1 => 1.0, 2 => 2.0, 3 => 3.0, 4 => 4.0, _ => 1.0,
-                                    };
-                                    bpm as f32 * multiplier
-                                } else {
-                                    bpm as f32
-                                }
-                            },
-                            _ => bpm as f32,
-                        };
-                        let time_to_next_beat = (60000.0 / effective_bpm.max(1.0)) * (1.0 - self.beat_progress);
-                        ui.add_space(15.0);
-                        ui.label(
-                            egui::RichText::new(format!("Next beat in: {:.1}ms", time_to_next_beat))
-                                .size(12.0)
-                                .color(theme.accent),
+                } else {
+                    let num_subdivisions = 4;
+                    for i in 1..num_subdivisions {
+                        let tick_x =
+                            track_rect.min.x + (slider_width * i as f32) / num_subdivisions as f32;
+                        let tick_top = track_rect.min.y - 3.0;
+                        let tick_bottom = track_rect.max.y + 3.0;
+
+                        ui.painter().line_segment(
+                            [
+                                egui::pos2(tick_x, tick_top),
+                                egui::pos2(tick_x, tick_bottom),
+                            ],
+                            egui::Stroke::new(1.0, egui::Color32::from_gray(100)),
                         );
                     }
                 }
+
+                if is_running {
+                    let beat_interval_ms = 60000.0 * snapshot.interval / (bpm as f32).max(1.0);
+                    let time_to_next_beat = beat_interval_ms * (1.0 - self.beat_progress);
+                    ui.add_space(15.0);
+                    ui.label(
+                        egui::RichText::new(format!("Next beat in: {:.1}ms", time_to_next_beat))
+                            .size(12.0)
+                            .color(theme.accent),
+                    );
+                }
             });
 
             ui.add_space(30.0);
@@ -1092,7 +728,7 @@ impl eframe::App for MetronomeApp {
                             .show_value(false)
                             .handle_shape(egui::style::HandleShape::Circle);
                         if ui.add_sized([250.0, 25.0], slider).changed() {
-                            let _ = self.command_sender.send(MetronomeCommand::ChangeBpm(bpm_value as u32));
+                            self.metronome.send(MetronomeCommand::ChangeBpm(bpm_value as u32));
                         }
                         ui.add_space(10.0);
                         ui.label(
@@ -1117,7 +753,7 @@ impl eframe::App for MetronomeApp {
                             .show_value(false)
                             .handle_shape(egui::style::HandleShape::Circle);
                         if ui.add_sized([250.0, 25.0], slider).changed() {
-                            let _ = self.command_sender.send(MetronomeCommand::ChangeVolume(volume_value as u32));
+                            self.metronome.send(MetronomeCommand::ChangeVolume(volume_value as u32));
                         }
                         ui.add_space(10.0);
                         ui.label(
@@ -1154,9 +790,10 @@ impl eframe::App for MetronomeApp {
                     .clicked()
                 {
                     if is_running {
-                        let _ = self.command_sender.send(MetronomeCommand::Stop);
+                        self.metronome.send(MetronomeCommand::Stop);
                     } else {
-                        let _ = self.command_sender.send(MetronomeCommand::Start);
+                        self.last_event = None;
+                        self.metronome.send(MetronomeCommand::Start);
                     }
                 }
             });
@@ -1176,21 +813,21 @@ impl eframe::App for MetronomeApp {
                     );
                     ui.add_space(10.0);
 
-                    let sounds = [
-                        ("🔔", "Beep"),
-                        ("🥁", "Kick"),
-                        ("🖱️", "Click"),
-                        ("🔔", "Cowbell"),
-                        ("🎺", "Hi-hat"),
-                        ("🪵", "Woodblock"),
-                        ("🔺", "Triangle"),
-                        ("⬜", "Square"),
-                    ];
-                    let current_sound = self.shared_state.sound_type.load(Ordering::Relaxed) as usize;
+                    let custom_sounds = self
+                        .custom_sound_names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| (CUSTOM_SOUND_START + i as u32, "🎙️", name.as_str()));
+                    let all_sounds = BUILTIN_SOUNDS
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (icon, name))| (i as u32, *icon, *name))
+                        .chain(custom_sounds);
+                    let current_sound = snapshot.sound_type;
 
                     ui.horizontal_wrapped(|ui| {
-                        for (i, (icon, name)) in sounds.iter().enumerate() {
-                            let selected = i == current_sound;
+                        for (sound_key, icon, name) in all_sounds {
+                            let selected = sound_key == current_sound;
                             let button_color = if selected {
                                 theme.primary
                             } else {
@@ -1215,16 +852,68 @@ impl eframe::App for MetronomeApp {
                                 )
                                 .clicked()
                             {
-                                let _ = self.command_sender.send(MetronomeCommand::ChangeSoundType(i as u32));
+                                self.metronome.send(MetronomeCommand::ChangeSoundType(sound_key));
+                            }
+                        }
+                    });
+
+                    ui.add_space(15.0);
+                    ui.label(
+                        egui::RichText::new("🥁 Sound Kit:")
+                            .size(16.0)
+                            .color(theme.accent),
+                    );
+                    ui.add_space(10.0);
+
+                    let current_kit = snapshot.kit;
+                    let kit_options = std::iter::once("Single Sound")
+                        .chain(self.kit_names.iter().map(String::as_str));
+
+                    ui.horizontal_wrapped(|ui| {
+                        for (kit, name) in kit_options.enumerate() {
+                            let button_color = if kit == current_kit {
+                                theme.primary
+                            } else {
+                                theme.surface
+                            };
+
+                            if ui
+                                .add_sized(
+                                    [100.0, 30.0],
+                                    egui::Button::new(egui::RichText::new(name).size(11.0))
+                                        .fill(button_color)
+                                        .rounding(egui::Rounding::same(8.0)),
+                                )
+                                .clicked()
+                            {
+                                self.metronome.send(MetronomeCommand::ChangeKit(kit));
                             }
                         }
                     });
+
+                    ui.add_space(5.0);
+                    let mut count_in = snapshot.count_in;
+                    if ui.checkbox(&mut count_in, "🔢 Count-in bar before starting").changed() {
+                        self.metronome.send(MetronomeCommand::SetCountIn(count_in));
+                    }
                 });
 
             ui.add_space(20.0);
 
+            self.draw_sound_designer(ui, &theme);
+
+            ui.add_space(20.0);
+
+            self.draw_presets(ui, &theme);
+
+            ui.add_space(20.0);
+
+            self.draw_stats(ui, &theme);
+
+            ui.add_space(20.0);
+
             // Status display
-            let mode_info = self.get_mode_info(current_mode);
+            let mode_info = &snapshot.panel.status;
 
             egui::Frame::none()
                 .fill(theme.surface)
@@ -1239,7 +928,18 @@ impl eframe::App for MetronomeApp {
                         };
                         let status_icon = if is_running { "🟢" } else { "🔴" };
                         let status_text = if is_running {
-                            format!("PLAYING - Beat #{} - {}", tick_count, mode_info)
+                            let position = match &self.last_event {
+                                Some(event) if event.click > 0 => format!(
+                                    "Bar {}.{}.{} at {:.1}s - {}",
+                                    event.bar + 1, event.beat + 1, event.click + 1, event.time, event.section
+                                ),
+                                Some(event) => format!(
+                                    "Bar {}.{} at {:.1}s - {}",
+                                    event.bar + 1, event.beat + 1, event.time, event.section
+                                ),
+                                None => "Waiting for the first beat".to_string(),
+                            };
+                            format!("PLAYING - Beat #{} - {} - {}", tick_count, position, mode_info)
                         } else {
                             format!("STOPPED - {}", mode_info)
                         };
@@ -1260,584 +960,727 @@ impl eframe::App for MetronomeApp {
 }
 
 impl MetronomeApp {
-    fn get_mode_info(&self, current_mode: MetronomeMode) -> String {
-        match current_mode {
-            MetronomeMode::Random => {
-                if let Ok(random_state) = self.shared_state.random_state.try_read() {
-                    format!("Random Mode - Next change in {} beats", random_state.remaining_ticks)
-                } else {
-                    "Random Mode".to_string()
-                }
-            },
-            MetronomeMode::Practice => {
-                if let Ok(practice_state) = self.shared_state.practice_state.try_read() {
-                    format!("Practice Mode - Section {} - {} beats remaining", 
-                           practice_state.current_section + 1, 
-                           practice_state.section_remaining)
-                } else {
-                    "Practice Mode".to_string()
-                }
-            },
-            MetronomeMode::Polyrhythm => {
-                if let Ok(poly_state) = self.shared_state.polyrhythm_state.try_read() {
-                    format!("Polyrhythm Mode - {}:{}", poly_state.primary, poly_state.secondary)
-                } else {
-                    "Polyrhythm Mode".to_string()
-                }
-            },
-            MetronomeMode::Ritardando => {
-                if let Ok(ritardando_state) = self.shared_state.ritardando_state.try_read() {
-                    format!("Ritardando - {} beats to {}BPM", 
-                           ritardando_state.remaining, 
-                           ritardando_state.target_bpm)
-                } else {
-                    "Ritardando Mode".to_string()
+    fn draw_mode_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
+        let snapshot = self.metronome.snapshot();
+        let panel = &snapshot.panel;
+        if snapshot.settings.is_empty() && panel.progress.is_none() && panel.notes.is_empty() {
+            return;
+        }
+        let (icon, name) = snapshot.modes[snapshot.mode];
+        let color = theme.mode_color(name).unwrap_or(theme.primary);
+
+        egui::Frame::none()
+            .fill(color.gamma_multiply(0.2))
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(15.0))
+            .stroke(egui::Stroke::new(2.0, color))
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(format!("{} {} Mode Settings", icon, name))
+                        .size(16.0)
+                        .color(color)
+                        .strong(),
+                );
+                ui.add_space(10.0);
+
+                for setting in snapshot.settings.iter() {
+                    if let Some(value) = draw_setting(ui, theme, setting) {
+                        self.metronome.send(MetronomeCommand::UpdateModeSetting {
+                            key: setting.key,
+                            value,
+                        });
+                    }
                 }
-            },
-            MetronomeMode::Subdivision => {
-                if let Ok(subdivision_state) = self.shared_state.subdivision_state.try_read() {
-                    let sub_name = match subdivision_state.subdivisions {
-                        1 => "Quarter notes",
-                        2 => "Eighth notes", 
-                        3 => "Triplets",
-                        4 => "Sixteenth notes",
-                        _ => "Custom",
-                    };
-                    format!("Subdivision Mode - {}", sub_name)
-                } else {
-                    "Subdivision Mode".to_string()
+
+                if let Some(progress) = &panel.progress {
+                    ui.add_space(10.0);
+                    let bar_height = 20.0;
+                    let track_rect = ui.allocate_space([400.0, bar_height].into()).1;
+                    ui.painter().rect_filled(
+                        track_rect,
+                        egui::Rounding::same(bar_height / 2.0),
+                        egui::Color32::from_gray(40),
+                    );
+
+                    let fill_rect = egui::Rect::from_min_size(
+                        track_rect.min,
+                        egui::Vec2::new(track_rect.width() * progress.fraction.clamp(0.0, 1.0), bar_height),
+                    );
+                    ui.painter().rect_filled(fill_rect, egui::Rounding::same(bar_height / 2.0), color);
+
+                    ui.painter().text(
+                        track_rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        &progress.label,
+                        egui::FontId::proportional(14.0),
+                        egui::Color32::WHITE,
+                    );
                 }
-            },
-            MetronomeMode::Countdown => {
-                if let Ok(countdown_state) = self.shared_state.countdown_state.try_read() {
-                    let minutes = (countdown_state.remaining_seconds / 60.0) as u32;
-                    let seconds = (countdown_state.remaining_seconds % 60.0) as u32;
-                    format!("Countdown Mode - {}:{:02} remaining", minutes, seconds)
-                } else {
-                    "Countdown Mode".to_string()
+
+                if snapshot.is_running {
+                    for line in &panel.live {
+                        ui.add_space(10.0);
+                        ui.label(egui::RichText::new(line).color(color));
+                    }
                 }
-            },
-            MetronomeMode::Standard => "Standard Mode".to_string(),
-        }
-    }
 
-    fn draw_countdown_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(countdown_state) = self.shared_state.countdown_state.try_read() {
-            let mut duration_seconds = countdown_state.duration_seconds;
-            let mut enable_random_bpm = countdown_state.enable_random_bpm;
-            let mut changed = false;
-            
-            egui::Frame::none()
-                .fill(theme.countdown.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.countdown))
+                for note in &panel.notes {
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(*note).size(12.0).color(color));
+                }
+            });
+    }
+    
+    fn draw_sound_designer(&mut self, ui: &mut egui::Ui, theme: &Theme) {
+        let snapshot = self.metronome.snapshot();
+        egui::Frame::none()
+            .fill(theme.surface)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(15.0))
+            .show(ui, |ui| {
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("🎛️ Sound Designer")
+                        .size(16.0)
+                        .color(theme.accent),
+                )
                 .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("⏱️ Countdown Mode Settings")
-                            .size(16.0)
-                            .color(theme.countdown)
-                            .strong(),
-                    );
-                    ui.add_space(10.0);
-                    
+                    let params = &mut self.designer.params;
+                    let mut changed = false;
+
                     ui.horizontal(|ui| {
-                        ui.label("Duration:");
-                        let mut duration_minutes = duration_seconds as f32 / 60.0;
-                        if ui.add(egui::Slider::new(&mut duration_minutes, 0.5..=30.0)
-                            .suffix(" min")).changed() {
-                            duration_seconds = (duration_minutes * 60.0) as u32;
-                            changed = true;
+                        ui.label("Oscillator:");
+                        for oscillator in Oscillator::ALL {
+                            let button_color = if params.oscillator == oscillator {
+                                theme.primary
+                            } else {
+                                theme.surface
+                            };
+                            if ui
+                                .add_sized([70.0, 25.0], egui::Button::new(oscillator.name()).fill(button_color))
+                                .clicked()
+                            {
+                                params.oscillator = oscillator;
+                                changed = true;
+                            }
                         }
                     });
-                    
-                    ui.add_space(10.0);
-                    
-                    if ui.checkbox(&mut enable_random_bpm, "🎲 Randomize BPM during countdown").changed() {
-                        changed = true;
-                    }
-                    
-                    if enable_random_bpm {
-                        ui.add_space(5.0);
-                        ui.label(
-                            egui::RichText::new("💡 BPM will randomly change every 3-8 seconds")
-                                .size(12.0)
-                                .color(theme.countdown),
-                        );
+
+                    let sliders = [
+                        ("Frequency:", &mut params.base_frequency, 40.0..=8000.0, " Hz"),
+                        ("Pitch sweep:", &mut params.pitch_sweep, -48.0..=24.0, " st"),
+                        ("Attack:", &mut params.attack_ms, 0.0..=50.0, " ms"),
+                        ("Decay:", &mut params.decay_ms, 1.0..=500.0, " ms"),
+                        ("Length:", &mut params.length_ms, 5.0..=1000.0, " ms"),
+                    ];
+                    for (label, value, range, suffix) in sliders {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            if ui.add(egui::Slider::new(value, range).suffix(suffix)).changed() {
+                                changed = true;
+                            }
+                        });
                     }
-                    
-                    ui.add_space(10.0);
-                    ui.label(
-                        egui::RichText::new("🎉 A celebration sound will play when countdown completes!")
-                            .size(12.0)
-                            .color(theme.countdown),
-                    );
-                });
-                
-            if changed {
-                let _ = self.command_sender.send(MetronomeCommand::UpdateCountdownSettings {
-                    duration_seconds,
-                    enable_random_bpm,
-                });
-            }
-        }
-    }
 
-    fn draw_countdown_progress(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(countdown_state) = self.shared_state.countdown_state.try_read() {
-            ui.label(
-                egui::RichText::new("⏱️ Countdown Progress")
-                    .size(14.0)
-                    .color(theme.countdown),
-            );
-            ui.add_space(5.0);
-
-            let slider_width = 400.0;
-            let slider_height = 20.0;
-            let slider_rect = ui
-                .allocate_space([slider_width, slider_height + 20.0].into())
-                .1;
-
-            let track_rect = egui::Rect::from_center_size(
-                slider_rect.center(),
-                egui::Vec2::new(slider_width, slider_height),
-            );
-            
-            // Background
-            ui.painter().rect_filled(
-                track_rect,
-                egui::Rounding::same(slider_height / 2.0),
-                egui::Color32::from_gray(40),
-            );
-
-            // Progress fill
-            let progress = if countdown_state.duration_seconds > 0 {
-                1.0 - (countdown_state.remaining_seconds / countdown_state.duration_seconds as f32)
-            } else {
-                0.0
-            };
-            
-            let progress_width = slider_width * progress;
-            let progress_rect = egui::Rect::from_min_size(
-                track_rect.min,
-                egui::Vec2::new(progress_width, slider_height),
-            );
-
-            let progress_color = if countdown_state.remaining_seconds <= 10.0 {
-                theme.error // Red when less than 10 seconds
-            } else if countdown_state.remaining_seconds <= 30.0 {
-                theme.warning // Yellow when less than 30 seconds
-            } else {
-                theme.countdown // Orange otherwise
-            };
-
-            ui.painter().rect_filled(
-                progress_rect,
-                egui::Rounding::same(slider_height / 2.0),
-                progress_color,
-            );
-
-            // Time display
-            let minutes = (countdown_state.remaining_seconds / 60.0) as u32;
-            let seconds = (countdown_state.remaining_seconds % 60.0) as u32;
-            
-            ui.painter().text(
-                track_rect.center(),
-                egui::Align2::CENTER_CENTER,
-                format!("{}:{:02}", minutes, seconds),
-                egui::FontId::proportional(14.0),
-                egui::Color32::WHITE,
-            );
-
-            ui.add_space(15.0);
-            
-            if countdown_state.enable_random_bpm {
-                ui.label(
-                    egui::RichText::new("🎲 Random BPM mode active")
-                        .size(12.0)
-                        .color(theme.countdown),
-                );
-            }
-        }
-    }
+                    ui.horizontal(|ui| {
+                        ui.label("Noise mix:");
+                        if ui.add(egui::Slider::new(&mut params.noise_mix, 0.0..=1.0)).changed() {
+                            changed = true;
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Filter:");
+                        for (kind, name) in [(FilterKind::LowPass, "Low-pass"), (FilterKind::HighPass, "High-pass")] {
+                            if ui.radio_value(&mut params.filter, kind, name).changed() {
+                                changed = true;
+                            }
+                        }
+                        if ui
+                            .add(egui::Slider::new(&mut params.filter_cutoff, 20.0..=20000.0)
+                                .logarithmic(true)
+                                .suffix(" Hz"))
+                            .changed()
+                        {
+                            changed = true;
+                        }
+                    });
+
+                    if changed {
+                        self.designer.preview = render_click(&self.designer.params);
+                    }
 
-    fn draw_random_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(random_state) = self.shared_state.random_state.try_read() {
-            let is_running = self.shared_state.is_running.load(Ordering::Relaxed);
-            
-            egui::Frame::none()
-                .fill(theme.warning.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.warning))
-                .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("🎲 Random Mode Settings")
-                            .size(16.0)
-                            .color(theme.warning)
-                            .strong(),
-                    );
                     ui.add_space(10.0);
-                    
+                    draw_waveform(ui, &self.designer.preview, theme);
+                    ui.add_space(10.0);
+
                     ui.horizontal(|ui| {
-                        ui.label("Change every:");
-                        let mut random_count_value = random_state.count as f32;
-                        let slider = egui::Slider::new(&mut random_count_value, 10.0..=500.0)
-                            .suffix(" beats");
-                        if ui.add_sized([200.0, 20.0], slider).changed() {
-                            let _ = self.command_sender.send(MetronomeCommand::UpdateRandomSettings {
-                                count: random_count_value as u32,
+                        if ui.button("🔈 Audition").clicked() {
+                            let volume = snapshot.volume as f32 / 100.0;
+                            let mut samples = self.designer.preview.clone();
+                            match_loudness(&mut samples);
+                            self.metronome.play(&samples.into(), volume);
+                        }
+
+                        ui.add(egui::TextEdit::singleline(&mut self.designer.name)
+                            .hint_text("Sound name")
+                            .desired_width(150.0));
+
+                        if ui.button("💾 Save as custom sound").clicked() {
+                            self.designer.status = Some(match self.save_designed_sound() {
+                                Ok(()) => format!("Saved '{}'", self.designer.name.trim()),
+                                Err(e) => format!("Could not save: {}", e),
                             });
                         }
                     });
-                    
-                    if is_running {
-                        ui.add_space(10.0);
-                        let progress = if random_state.count > 0 {
-                            (random_state.count - random_state.remaining_ticks) as f32 / random_state.count as f32
-                        } else {
-                            0.0
-                        };
-                        
-                        ui.horizontal(|ui| {
-                            ui.label(format!("Next change in: {} beats", random_state.remaining_ticks));
-                            let progress_bar_width = 150.0;
-                            let progress_rect = ui.allocate_space([progress_bar_width, 8.0].into()).1;
-                            
-                            ui.painter().rect_filled(
-                                progress_rect,
-                                egui::Rounding::same(4.0),
-                                egui::Color32::from_gray(40),
-                            );
-                            
-                            let fill_width = progress_rect.width() * progress;
-                            let fill_rect = egui::Rect::from_min_size(
-                                progress_rect.min,
-                                egui::Vec2::new(fill_width, progress_rect.height()),
-                            );
-                            
-                            ui.painter().rect_filled(
-                                fill_rect,
-                                egui::Rounding::same(4.0),
-                                theme.warning,
-                            );
-                        });
+
+                    if let Some(status) = &self.designer.status {
+                        ui.label(egui::RichText::new(status).size(12.0).color(theme.accent));
                     }
-                    
-                    ui.add_space(5.0);
-                    ui.label(
-                        egui::RichText::new("🎯 BPM will randomly change between 60-200")
-                            .size(12.0)
-                            .color(theme.warning),
-                    );
                 });
-        }
+            });
     }
-    
-    fn draw_practice_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(mut practice_state) = self.shared_state.practice_state.try_write() {
-            egui::Frame::none()
-                .fill(theme.practice.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.practice))
+
+    fn draw_presets(&mut self, ui: &mut egui::Ui, theme: &Theme) {
+        egui::Frame::none()
+            .fill(theme.surface)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(15.0))
+            .show(ui, |ui| {
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("🎼 Presets")
+                        .size(16.0)
+                        .color(theme.accent),
+                )
                 .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("🎯 Practice Mode Settings")
-                            .size(16.0)
-                            .color(theme.practice)
-                            .strong(),
-                    );
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.presets.name)
+                            .hint_text("Preset name")
+                            .desired_width(150.0));
+
+                        if ui.button("💾 Save current setup").clicked() {
+                            self.presets.status = Some(match self.save_current_preset() {
+                                Ok(name) => format!("Saved '{}'", name),
+                                Err(e) => format!("Could not save: {}", e),
+                            });
+                        }
+                    });
+
                     ui.add_space(10.0);
-                    
-                    ui.label("Practice sections (BPM, Beats):");
-                    
-                    let mut to_remove = None;
-                    let mut sections_changed = false;
-                    
-                    for (i, (bpm, beats)) in practice_state.sections.iter_mut().enumerate() {
+                    if self.presets.list.is_empty() {
+                        ui.label(egui::RichText::new("No presets yet").size(12.0).color(theme.on_surface));
+                    }
+
+                    let mut load = None;
+                    let mut export = None;
+                    let mut delete = None;
+                    for (i, preset) in self.presets.list.iter().enumerate() {
                         ui.horizontal(|ui| {
-                            ui.label(format!("Section {}:", i + 1));
-                            
-                            let mut bpm_f = *bpm as f32;
-                            if ui.add(egui::Slider::new(&mut bpm_f, 30.0..=300.0)
-                                .suffix(" BPM")).changed() {
-                                *bpm = bpm_f as u32;
-                                sections_changed = true;
+                            ui.label(egui::RichText::new(&preset.name).strong());
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} · {} BPM · {}/4 · {}",
+                                    preset.config.mode, preset.config.bpm, preset.config.beats_per_bar, preset.config.sound
+                                ))
+                                .size(12.0)
+                                .color(theme.on_surface),
+                            );
+                            if ui.button("▶ Load").clicked() {
+                                load = Some(i);
                             }
-                            
-                            let mut beats_f = *beats as f32;
-                            if ui.add(egui::Slider::new(&mut beats_f, 4.0..=128.0)
-                                .suffix(" beats")).changed() {
-                                *beats = beats_f as u32;
-                                sections_changed = true;
+                            if ui.button("📤 Export").clicked() {
+                                export = Some(i);
                             }
-                            
-                            if ui.button("❌").clicked() {
-                                to_remove = Some(i);
+                            if ui.button("🗑").on_hover_text("Delete").clicked() {
+                                delete = Some(i);
                             }
                         });
                     }
-                    
-                    if let Some(index) = to_remove {
-                        practice_state.sections.remove(index);
-                        sections_changed = true;
-                    }
-                    
+
                     ui.add_space(10.0);
-                    if ui.button("➕ Add Section").clicked() {
-                        practice_state.sections.push((120, 32));
-                        sections_changed = true;
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.presets.path)
+                            .hint_text("File to import or export, e.g. ~/lesson.toml")
+                            .desired_width(250.0));
+
+                        if ui.button("📥 Import").clicked() {
+                            self.presets.status = Some(match self.import_preset() {
+                                Ok(name) => format!("Imported '{}'", name),
+                                Err(e) => format!("Could not import: {}", e),
+                            });
+                        }
+                    });
+
+                    if let Some(i) = load {
+                        let preset = self.presets.list[i].clone();
+                        self.load_preset(&preset);
+                        self.presets.status = Some(format!("Loaded '{}'", preset.name));
                     }
-                    
-                    if sections_changed {
-                        let _ = self.command_sender.send(MetronomeCommand::UpdatePracticeSettings {
-                            sections: practice_state.sections.clone(),
+                    if let Some(i) = export {
+                        let preset = &self.presets.list[i];
+                        let path = expand_home(self.presets.path.trim());
+                        self.presets.status = Some(if self.presets.path.trim().is_empty() {
+                            "Enter a file to export to".to_string()
+                        } else {
+                            match export_preset(preset, &path) {
+                                Ok(()) => format!("Exported '{}' to {}", preset.name, path.display()),
+                                Err(message) => format!("Could not export: {}", message),
+                            }
                         });
                     }
-                    
-                    let is_running = self.shared_state.is_running.load(Ordering::Relaxed);
-                    if is_running {
-                        ui.add_space(10.0);
-                        ui.label(
-                            egui::RichText::new(format!(
-                                "Current: Section {} - {} beats remaining", 
-                                practice_state.current_section + 1, 
-                                practice_state.section_remaining
-                            ))
-                            .color(theme.practice),
-                        );
+                    if let Some(i) = delete {
+                        let name = self.presets.list[i].name.clone();
+                        self.presets.status = Some(match presets_dir().map(|dir| delete_preset(&dir, &name)) {
+                            Some(Ok(())) => {
+                                self.presets.list.remove(i);
+                                format!("Deleted '{}'", name)
+                            },
+                            Some(Err(message)) => format!("Could not delete: {}", message),
+                            None => "No config directory available".to_string(),
+                        });
+                    }
+
+                    if let Some(status) = &self.presets.status {
+                        ui.label(egui::RichText::new(status).size(12.0).color(theme.accent));
                     }
                 });
-        }
+            });
     }
-    
-    fn draw_polyrhythm_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(poly_state) = self.shared_state.polyrhythm_state.try_read() {
-            let mut primary = poly_state.primary;
-            let mut secondary = poly_state.secondary;
-            let mut accent_primary = poly_state.accent_primary;
-            let mut accent_secondary = poly_state.accent_secondary;
-            let mut changed = false;
-            
-            egui::Frame::none()
-                .fill(theme.polyrhythm.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.polyrhythm))
+
+    fn draw_stats(&mut self, ui: &mut egui::Ui, theme: &Theme) {
+        egui::Frame::none()
+            .fill(theme.surface)
+            .rounding(egui::Rounding::same(12.0))
+            .inner_margin(egui::Margin::same(15.0))
+            .show(ui, |ui| {
+                egui::CollapsingHeader::new(
+                    egui::RichText::new("📊 Practice Stats")
+                        .size(16.0)
+                        .color(theme.accent),
+                )
                 .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("🔄 Polyrhythm Mode Settings")
-                            .size(16.0)
-                            .color(theme.polyrhythm)
-                            .strong(),
-                    );
-                    ui.add_space(10.0);
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("Primary rhythm:");
-                        let mut primary_f = primary as f32;
-                        if ui.add(egui::Slider::new(&mut primary_f, 2.0..=16.0)).changed() {
-                            primary = primary_f as u32;
-                            changed = true;
-                        }
-                        
-                        if ui.checkbox(&mut accent_primary, "Accent").changed() {
-                            changed = true;
+                    let stats = self.stats.get_or_insert_with(|| {
+                        let Some(path) = history_path() else {
+                            return PracticeStats::default();
+                        };
+                        let (records, errors) = load_history(&path);
+                        for error in errors {
+                            eprintln!("Skipping session {}", error);
                         }
+                        practice_stats(&records, chrono::Local::now().date_naive(), STATS_DAYS, STATS_WEEKS)
                     });
-                    
+
+                    if stats.sessions == 0 {
+                        ui.label(egui::RichText::new("No sessions yet; every start to stop is recorded").size(12.0));
+                        return;
+                    }
+
                     ui.horizontal(|ui| {
-                        ui.label("Secondary rhythm:");
-                        let mut secondary_f = secondary as f32;
-                        if ui.add(egui::Slider::new(&mut secondary_f, 2.0..=16.0)).changed() {
-                            secondary = secondary_f as u32;
-                            changed = true;
-                        }
-                        
-                        if ui.checkbox(&mut accent_secondary, "Accent").changed() {
-                            changed = true;
+                        for (label, value) in [
+                            ("Sessions", stats.sessions.to_string()),
+                            ("Total", format_minutes(stats.total_seconds)),
+                            ("Streak", format!("{} days", stats.current_streak)),
+                            ("Longest", format!("{} days", stats.longest_streak)),
+                        ] {
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new(label).size(12.0));
+                                ui.label(egui::RichText::new(value).size(18.0).strong().color(theme.primary));
+                            });
+                            ui.add_space(20.0);
                         }
                     });
-                    
-                    ui.add_space(5.0);
-                    ui.label(
-                        egui::RichText::new("💡 Creates overlapping rhythmic patterns")
-                            .size(12.0)
-                            .color(theme.polyrhythm),
-                    );
-                });
-                
-            if changed {
-                let _ = self.command_sender.send(MetronomeCommand::UpdatePolyrhythmSettings {
-                    primary,
-                    secondary,
-                    accent_primary,
-                    accent_secondary,
+
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new(format!("Last {} days", STATS_DAYS)).strong());
+                    let days: Vec<_> = stats
+                        .per_day
+                        .iter()
+                        .map(|(day, seconds)| (day.format("%a %d").to_string(), *seconds))
+                        .collect();
+                    draw_stat_bars(ui, &days, theme.primary);
+
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new("Weeks").strong());
+                    let weeks: Vec<_> = stats
+                        .per_week
+                        .iter()
+                        .map(|(week, seconds)| (format!("From {}", week.format("%d %b")), *seconds))
+                        .collect();
+                    draw_stat_bars(ui, &weeks, theme.secondary);
+
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new("Time per tempo").strong());
+                    let tempos: Vec<_> = stats
+                        .per_tempo
+                        .iter()
+                        .map(|(range, seconds)| (format!("{}-{} BPM", range, range + TEMPO_RANGE_WIDTH - 1), *seconds))
+                        .collect();
+                    draw_stat_bars(ui, &tempos, theme.accent);
                 });
-            }
+            });
+    }
+
+    // Captures every mode's settings, not only the current mode's, through the config
+    fn save_current_preset(&mut self) -> Result<String, String> {
+        let dir = presets_dir().ok_or("no config directory available")?;
+        self.save_config(true);
+        let preset = save_preset(&dir, &self.presets.name, &self.config)?;
+        let name = preset.name.clone();
+        self.add_preset(preset);
+        Ok(name)
+    }
+
+    fn import_preset(&mut self) -> Result<String, String> {
+        let dir = presets_dir().ok_or("no config directory available")?;
+        if self.presets.path.trim().is_empty() {
+            return Err("enter a file to import".to_string());
         }
+        let preset = import_preset(&dir, &expand_home(self.presets.path.trim()))?;
+        let name = preset.name.clone();
+        self.add_preset(preset);
+        Ok(name)
     }
-    
-    fn draw_ritardando_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(ritardando_state) = self.shared_state.ritardando_state.try_read() {
-            let mut start_bpm = ritardando_state.start_bpm;
-            let mut target_bpm = ritardando_state.target_bpm;
-            let mut duration = ritardando_state.duration;
-            let mut changed = false;
-            
-            egui::Frame::none()
-                .fill(theme.error.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.error))
-                .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("🐌 Ritardando Mode Settings")
-                            .size(16.0)
-                            .color(theme.error)
-                            .strong(),
-                    );
-                    ui.add_space(10.0);
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("Start BPM:");
-                        let mut start_bpm_f = start_bpm as f32;
-                        if ui.add(egui::Slider::new(&mut start_bpm_f, 60.0..=300.0)).changed() {
-                            start_bpm = start_bpm_f as u32;
-                            changed = true;
-                        }
-                    });
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("Target BPM:");
-                        let mut target_bpm_f = target_bpm as f32;
-                        if ui.add(egui::Slider::new(&mut target_bpm_f, 30.0..=250.0)).changed() {
-                            target_bpm = target_bpm_f as u32;
-                            changed = true;
-                        }
-                    });
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("Duration:");
-                        let mut duration_f = duration as f32;
-                        if ui.add(egui::Slider::new(&mut duration_f, 1.0..=256.0).suffix(" beats")).changed() {
-                            duration = (duration_f as u32).max(1);
-                            changed = true;
-                        }
-                    });
-                    
-                    if self.shared_state.is_running.load(Ordering::Relaxed) {
-                        ui.add_space(10.0);
-                        ui.label(
-                            egui::RichText::new(format!("Slowing down... {} beats remaining", ritardando_state.remaining))
-                                .color(theme.error),
-                        );
+
+    // Replaces a preset with the same name, keeping the list sorted
+    fn add_preset(&mut self, preset: Preset) {
+        self.presets.list.retain(|existing| existing.name != preset.name);
+        let index = self.presets.list.partition_point(|existing| existing.name < preset.name);
+        self.presets.list.insert(index, preset);
+    }
+
+    // Writes the design into the custom sounds folder, then loads it back so the
+    // running engine plays exactly what the next launch will
+    fn save_designed_sound(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let name = self.designer.name.trim().to_string();
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            return Err("enter a name without '/', '\\' or '.'".into());
+        }
+
+        let dir = custom_sounds_dir().ok_or("no config directory available")?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.wav", name));
+        save_wav(&path, &self.designer.preview)?;
+        let mut samples = load_sample(&path)?;
+        match_loudness(&mut samples);
+
+        let index = match self.custom_sound_names.iter().position(|existing| *existing == name) {
+            Some(index) => index,
+            None => {
+                self.custom_sound_names.push(name);
+                self.custom_sound_names.len() - 1
+            }
+        };
+        self.metronome.send(MetronomeCommand::AddCustomSound {
+            key: CUSTOM_SOUND_START + index as u32,
+            samples,
+        });
+        Ok(())
+    }
+}
+
+// Paths typed into the app may start with ~ like in a shell
+fn expand_home(path: &str) -> std::path::PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+// Draws one mode setting and returns its new value when the user changed it
+fn draw_setting(ui: &mut egui::Ui, theme: &Theme, setting: &Setting) -> Option<SettingValue> {
+    match (&setting.kind, &setting.value) {
+        (SettingKind::Number { min, max, suffix }, SettingValue::Number(value)) => {
+            let mut value = *value;
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", setting.label));
+                let slider = egui::Slider::new(&mut value, *min..=*max).suffix(*suffix);
+                ui.add(slider).changed()
+            })
+            .inner
+            .then_some(SettingValue::Number(value))
+        },
+        (SettingKind::Choice(options), SettingValue::Number(value)) => {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", setting.label));
+                let mut choice = None;
+                for (option, name) in options.iter() {
+                    let button_color = if option == value { theme.primary } else { theme.surface };
+                    if ui.add_sized([80.0, 25.0], egui::Button::new(*name).fill(button_color)).clicked() {
+                        choice = Some(SettingValue::Number(*option));
+                    }
+                }
+                choice
+            })
+            .inner
+        },
+        (SettingKind::Toggle, SettingValue::Toggle(value)) => {
+            let mut value = *value;
+            ui.checkbox(&mut value, setting.label)
+                .changed()
+                .then_some(SettingValue::Toggle(value))
+        },
+        (SettingKind::Pattern, SettingValue::Pattern(pattern)) => {
+            ui.add_space(10.0);
+            ui.label(format!("{}:", setting.label));
+            let toggled = ui.horizontal(|ui| {
+                let mut toggled = None;
+                for (i, accent) in pattern.iter().enumerate() {
+                    let button_text = if *accent { "💥" } else { "○" };
+                    let button_color = if *accent { theme.accent } else { theme.surface };
+                    if ui.add_sized([40.0, 30.0], egui::Button::new(button_text).fill(button_color)).clicked() {
+                        toggled = Some(i);
+                    }
+                }
+                toggled
+            })
+            .inner;
+            toggled.map(|i| {
+                let mut pattern = pattern.clone();
+                pattern[i] = !pattern[i];
+                SettingValue::Pattern(pattern)
+            })
+        },
+        (SettingKind::Sections { bpm: bpm_range, beats: beats_range }, SettingValue::Sections(sections)) => {
+            ui.label(format!("{}:", setting.label));
+
+            let mut sections = sections.clone();
+            let mut to_remove = None;
+            let mut sections_changed = false;
+            // The last section stays, so the mode always has something to practice
+            let removable = sections.len() > 1;
+
+            for (i, (bpm, beats)) in sections.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Section {}:", i + 1));
+
+                    if ui.add(egui::Slider::new(bpm, bpm_range.0..=bpm_range.1)
+                        .suffix(" BPM")).changed() {
+                        sections_changed = true;
+                    }
+
+                    if ui.add(egui::Slider::new(beats, beats_range.0..=beats_range.1)
+                        .suffix(" beats")).changed() {
+                        sections_changed = true;
+                    }
+
+                    if removable && ui.button("❌").clicked() {
+                        to_remove = Some(i);
                     }
                 });
-                
-            if changed {
-                let _ = self.command_sender.send(MetronomeCommand::UpdateRitardandoSettings {
-                    start_bpm,
-                    target_bpm,
-                    duration,
-                });
+            }
+
+            if let Some(index) = to_remove {
+                sections.remove(index);
+                sections_changed = true;
+            }
+
+            ui.add_space(10.0);
+            if ui.button("➕ Add Section").clicked() {
+                sections.push((120, 32));
+                sections_changed = true;
+            }
+
+            sections_changed.then_some(SettingValue::Sections(sections))
+        },
+        _ => None,
+    }
+}
+
+// Draws one min/max line per pixel column so long sounds stay readable
+// One labelled bar per row, scaled to the longest
+fn draw_stat_bars(ui: &mut egui::Ui, rows: &[(String, f64)], color: egui::Color32) {
+    let longest = rows.iter().map(|(_, seconds)| *seconds).fold(0.0, f64::max);
+    for (label, seconds) in rows {
+        ui.horizontal(|ui| {
+            ui.add_sized([90.0, 16.0], egui::Label::new(egui::RichText::new(label).size(12.0)));
+            let (rect, _) = ui.allocate_exact_size([240.0, 12.0].into(), egui::Sense::hover());
+            ui.painter().rect_filled(rect, egui::Rounding::same(3.0), egui::Color32::from_gray(24));
+            if longest > 0.0 {
+                let mut filled = rect;
+                filled.set_width(rect.width() * (*seconds / longest) as f32);
+                ui.painter().rect_filled(filled, egui::Rounding::same(3.0), color);
+            }
+            ui.label(egui::RichText::new(format_minutes(*seconds)).size(12.0));
+        });
+    }
+}
+
+fn format_minutes(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{} min", minutes)
+    }
+}
+
+fn draw_waveform(ui: &mut egui::Ui, samples: &[f32], theme: &Theme) {
+    let width = ui.available_width().min(500.0);
+    let height = 80.0;
+    let (rect, _) = ui.allocate_exact_size([width, height].into(), egui::Sense::hover());
+
+    ui.painter().rect_filled(rect, egui::Rounding::same(6.0), egui::Color32::from_gray(24));
+    ui.painter().line_segment(
+        [rect.left_center(), rect.right_center()],
+        egui::Stroke::new(1.0, egui::Color32::from_gray(60)),
+    );
+
+    if samples.is_empty() {
+        return;
+    }
+
+    let columns = width as usize;
+    let samples_per_column = samples.len().div_ceil(columns).max(1);
+    let stroke = egui::Stroke::new(1.0, theme.primary);
+
+    for (column, chunk) in samples.chunks(samples_per_column).enumerate() {
+        let (min, max) = chunk
+            .iter()
+            .fold((0.0f32, 0.0f32), |(min, max), &s| (min.min(s), max.max(s)));
+        let x = rect.left() + column as f32;
+        let to_y = |value: f32| rect.center().y - value.clamp(-1.0, 1.0) * height / 2.0;
+        ui.painter()
+            .line_segment([egui::pos2(x, to_y(max)), egui::pos2(x, to_y(min))], stroke);
+    }
+}
+
+// Opens on the profile picker unless --profile named one, then runs the chosen profile's metronome
+enum StudioApp {
+    Profiles {
+        profiles: Vec<String>,
+        new_name: String,
+        status: Option<String>,
+    },
+    Metronome(Box<MetronomeApp>),
+}
+
+impl StudioApp {
+    fn open(name: &str, ctx: &egui::Context) -> Result<Self, String> {
+        select_profile(name)?;
+        let config = load_settings();
+        if let Some(window) = config.window {
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize([window.width, window.height].into()));
+            if let (Some(x), Some(y)) = (window.x, window.y) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition([x, y].into()));
             }
         }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(window_title()));
+        Ok(StudioApp::Metronome(Box::new(MetronomeApp::new(config))))
     }
-    
-    fn draw_subdivision_controls(&mut self, ui: &mut egui::Ui, theme: &Theme) {
-        if let Ok(subdivision_state) = self.shared_state.subdivision_state.try_read() {
-            let mut subdivisions = subdivision_state.subdivisions;
-            let mut pattern = subdivision_state.accent_pattern.clone();
-            let mut changed = false;
-            
-            egui::Frame::none()
-                .fill(theme.primary.gamma_multiply(0.2))
-                .rounding(egui::Rounding::same(12.0))
-                .inner_margin(egui::Margin::same(15.0))
-                .stroke(egui::Stroke::new(2.0, theme.primary))
-                .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new("🎼 Subdivision Mode Settings")
-                            .size(16.0)
-                            .color(theme.primary)
-                            .strong(),
-                    );
-                    ui.add_space(10.0);
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("Subdivision:");
-                        
-                        let subdivision_options = [(1, "Quarter"), (2, "Eighth"), (3, "Triplet"), (4, "Sixteenth")];
-                        for (value, name) in subdivision_options.iter() {
-                            let selected = subdivisions == *value;
-                            let button_color = if selected { theme.primary } else { theme.surface };
-                            
-                            if ui.add_sized([80.0, 25.0], 
-                                egui::Button::new(*name).fill(button_color)).clicked() {
-                                subdivisions = *value;
-                                changed = true;
-                            }
-                        }
-                    });
-                    
-                    ui.add_space(10.0);
-                    ui.label("Accent Pattern:");
-                    
-                    // Resize pattern if needed
-                    if pattern.len() != subdivisions as usize {
-                        pattern.resize(subdivisions as usize, false);
-                        if subdivisions > 0 {
-                            pattern[0] = true; // Always accent the first beat
+}
+
+impl eframe::App for StudioApp {
+    fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+        if let StudioApp::Metronome(app) = self {
+            app.on_exit(gl);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let (profiles, new_name, status) = match self {
+            StudioApp::Metronome(app) => return app.update(ctx, frame),
+            StudioApp::Profiles { profiles, new_name, status } => (profiles, new_name, status),
+        };
+
+        let theme = Theme::dark();
+        let mut chosen = None;
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(theme.background).inner_margin(egui::Margin::same(40.0)))
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new("👤 Who is practicing?").size(28.0).color(theme.primary));
+                    ui.add_space(20.0);
+
+                    for profile in profiles.iter() {
+                        let button = egui::Button::new(egui::RichText::new(profile).size(18.0)).fill(theme.surface);
+                        if ui.add_sized([260.0, 40.0], button).clicked() {
+                            chosen = Some(profile.clone());
                         }
-                        changed = true;
+                        ui.add_space(6.0);
                     }
-                    
+
+                    ui.add_space(20.0);
                     ui.horizontal(|ui| {
-                        for (i, accent) in pattern.iter_mut().enumerate() {
-                            let button_text = if *accent { "💥" } else { "○" };
-                            let button_color = if *accent { theme.accent } else { theme.surface };
-                            
-                            if ui.add_sized([40.0, 30.0], 
-                                egui::Button::new(button_text).fill(button_color)).clicked() {
-                                *accent = !*accent;
-                                changed = true;
-                            }
+                        ui.add_space((ui.available_width() - 340.0).max(0.0) / 2.0);
+                        let edit = ui.add(egui::TextEdit::singleline(new_name)
+                            .hint_text("New profile")
+                            .desired_width(200.0));
+                        let submitted = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                        if (ui.button("➕ Create").clicked() || submitted) && !new_name.trim().is_empty() {
+                            chosen = Some(new_name.trim().to_string());
                         }
                     });
-                    
-                    ui.add_space(5.0);
-                    ui.label(
-                        egui::RichText::new("💡 Click beats to toggle accents")
-                            .size(12.0)
-                            .color(theme.primary),
-                    );
-                });
-                
-            if changed {
-                let _ = self.command_sender.send(MetronomeCommand::UpdateSubdivisionSettings {
-                    subdivisions,
-                    pattern,
+
+                    if let Some(status) = status {
+                        ui.label(egui::RichText::new(status.as_str()).size(12.0).color(theme.error));
+                    }
                 });
+            });
+
+        if let Some(name) = chosen {
+            match StudioApp::open(&name, ctx) {
+                Ok(app) => *self = app,
+                Err(message) => *status = Some(message),
             }
         }
     }
 }
 
+fn load_settings() -> Config {
+    let (config, error) = load_config_or_default();
+    if let Some(message) = error {
+        eprintln!("{}", message);
+    }
+    config
+}
+
+// Names the profile in use, unless it is the only one
+fn window_title() -> String {
+    match active_profile().as_str() {
+        DEFAULT_PROFILE => "Metronome Studio Pro".to_string(),
+        profile => format!("Metronome Studio Pro · {}", profile),
+    }
+}
+
 fn main() -> Result<(), eframe::Error> {
+    // Without --profile the window opens on the picker, before any profile's
+    // config is loaded, so it starts at the default size
+    let args: Vec<String> = std::env::args().collect();
+    let profile = profile_from_args(&args);
+    let profiles = list_profiles();
+    if let Some(name) = &profile
+        && let Err(message) = select_profile(name)
+    {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+    let pick = profile.is_none();
+    let config = if pick { Config::default() } else { load_settings() };
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([900.0, 1000.0])
+        .with_min_inner_size([600.0, 700.0])
+        .with_title(window_title())
+        .with_resizable(true);
+    if let Some(window) = config.window {
+        viewport = viewport.with_inner_size([window.width, window.height]);
+        if let (Some(x), Some(y)) = (window.x, window.y) {
+            viewport = viewport.with_position([x, y]);
+        }
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([900.0, 1000.0])
-            .with_min_inner_size([600.0, 700.0])
-            .with_title("Metronome Studio Pro")
-            .with_resizable(true),
+        viewport,
         ..Default::default()
     };
 
     eframe::run_native(
         "Metronome Studio Pro",
         options,
-        Box::new(|_cc| Ok(Box::new(MetronomeApp::default()))),
+        Box::new(move |_cc| {
+            Ok(Box::new(if pick {
+                StudioApp::Profiles { profiles, new_name: String::new(), status: None }
+            } else {
+                StudioApp::Metronome(Box::new(MetronomeApp::new(config)))
+            }))
+        }),
     )
 }
\ No newline at end of file
@@ -1 +1 @@
-pub mod sound;
+pub mod synth;
@@ -0,0 +1,149 @@
+use rand::Rng;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::path::Path;
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Oscillator {
+    Sine,
+    Square,
+    Triangle,
+    Saw,
+}
+
+impl Oscillator {
+    pub const ALL: [Oscillator; 4] = [
+        Oscillator::Sine,
+        Oscillator::Square,
+        Oscillator::Triangle,
+        Oscillator::Saw,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Oscillator::Sine => "Sine",
+            Oscillator::Square => "Square",
+            Oscillator::Triangle => "Triangle",
+            Oscillator::Saw => "Saw",
+        }
+    }
+
+    // `phase` is in cycles, 0.0..1.0
+    fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Oscillator::Sine => (phase * 2.0 * PI).sin(),
+            Oscillator::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Oscillator::Triangle => {
+                if phase < 0.5 {
+                    4.0 * phase - 1.0
+                } else {
+                    3.0 - 4.0 * phase
+                }
+            }
+            Oscillator::Saw => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClickSynthParams {
+    pub oscillator: Oscillator,
+    pub base_frequency: f32,
+    // Semitones the pitch glides by, reached over the decay time; negative falls
+    pub pitch_sweep: f32,
+    // 0.0 is pure oscillator, 1.0 is pure white noise
+    pub noise_mix: f32,
+    pub filter: FilterKind,
+    pub filter_cutoff: f32,
+    pub attack_ms: f32,
+    pub decay_ms: f32,
+    pub length_ms: f32,
+}
+
+impl Default for ClickSynthParams {
+    fn default() -> Self {
+        Self {
+            oscillator: Oscillator::Sine,
+            base_frequency: 1200.0,
+            pitch_sweep: 0.0,
+            noise_mix: 0.0,
+            filter: FilterKind::LowPass,
+            filter_cutoff: 8000.0,
+            attack_ms: 1.0,
+            decay_ms: 60.0,
+            length_ms: 80.0,
+        }
+    }
+}
+
+pub fn render_click(params: &ClickSynthParams) -> Vec<f32> {
+    let samples = (SAMPLE_RATE * params.length_ms / 1000.0) as usize;
+    let attack = params.attack_ms / 1000.0;
+    let decay = (params.decay_ms / 1000.0).max(0.001);
+
+    // One-pole filter coefficient for the chosen cutoff
+    let cutoff = params.filter_cutoff.clamp(20.0, SAMPLE_RATE / 2.0);
+    let alpha = 1.0 - (-2.0 * PI * cutoff / SAMPLE_RATE).exp();
+
+    let mut rng = rand::thread_rng();
+    let mut phase = 0.0f32;
+    let mut low_pass = 0.0f32;
+
+    let mut wave: Vec<f32> = Vec::with_capacity(samples);
+    for i in 0..samples {
+        let t = i as f32 / SAMPLE_RATE;
+
+        let envelope = if t < attack {
+            t / attack
+        } else {
+            (-(t - attack) / decay).exp()
+        };
+
+        let sweep = params.pitch_sweep * (1.0 - (-t / decay).exp());
+        let frequency = params.base_frequency * 2.0f32.powf(sweep / 12.0);
+        phase = (phase + frequency / SAMPLE_RATE) % 1.0;
+
+        let tone = params.oscillator.sample(phase);
+        let noise: f32 = rng.gen_range(-1.0..1.0);
+        let raw = tone * (1.0 - params.noise_mix) + noise * params.noise_mix;
+
+        low_pass += alpha * (raw - low_pass);
+        let filtered = match params.filter {
+            FilterKind::LowPass => low_pass,
+            FilterKind::HighPass => raw - low_pass,
+        };
+
+        wave.push(filtered * envelope * 0.5);
+    }
+    wave
+}
+
+pub fn save_wav(path: &Path, samples: &[f32]) -> Result<(), Box<dyn Error>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use metronome_engine::utilities::kit::builtin_kits;
+
+// Parameter ids; they are saved in the host's projects, so never renumber them
+pub const SOUND: u32 = 0;
+pub const VOLUME: u32 = 1;
+pub const SUBDIVISION: u32 = 2;
+pub const KIT: u32 = 3;
+// One toggle per click within the beat, up to sixteenths
+pub const ACCENT: u32 = 4;
+pub const MAX_SUBDIVISIONS: usize = 4;
+
+const SOUND_NAMES: [&str; 8] = ["Beep", "Kick", "Click", "Cowbell", "Hi-Hat", "Woodblock", "Triangle", "Square"];
+const SUBDIVISION_NAMES: [&str; 4] = ["Quarter", "Eighth", "Triplet", "Sixteenth"];
+const ACCENT_NAMES: [&str; 4] = ["Accent 1", "Accent 2", "Accent 3", "Accent 4"];
+
+pub enum Display {
+    Names(Vec<String>),
+    Percent,
+    Toggle,
+}
+
+pub struct Param {
+    pub id: u32,
+    pub name: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub display: Display,
+}
+
+/// The plugin's parameters. Values are atomics so the host can read them on
+/// the main thread while the audio thread applies automation.
+pub struct Params {
+    params: Vec<Param>,
+    values: Vec<AtomicU64>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        let names = |names: &[&str]| Display::Names(names.iter().map(|name| name.to_string()).collect());
+        let mut kits = vec!["None".to_string()];
+        kits.extend(builtin_kits().into_iter().map(|kit| kit.name));
+
+        let mut params = vec![
+            Param { id: SOUND, name: "Sound", min: 0.0, max: 7.0, default: 0.0, display: names(&SOUND_NAMES) },
+            Param { id: VOLUME, name: "Volume", min: 0.0, max: 100.0, default: 80.0, display: Display::Percent },
+            Param { id: SUBDIVISION, name: "Subdivision", min: 1.0, max: 4.0, default: 1.0, display: names(&SUBDIVISION_NAMES) },
+            Param { id: KIT, name: "Kit", min: 0.0, max: (kits.len() - 1) as f64, default: 0.0, display: Display::Names(kits) },
+        ];
+        // The first click of each beat starts accented, like the subdivision mode
+        for (i, name) in ACCENT_NAMES.iter().enumerate() {
+            let default = if i == 0 { 1.0 } else { 0.0 };
+            params.push(Param { id: ACCENT + i as u32, name, min: 0.0, max: 1.0, default, display: Display::Toggle });
+        }
+
+        let values = params.iter().map(|param| AtomicU64::new(param.default.to_bits())).collect();
+        Self { params, values }
+    }
+
+    pub fn count(&self) -> usize {
+        self.params.len()
+    }
+
+    pub fn info(&self, index: usize) -> Option<&Param> {
+        self.params.get(index)
+    }
+
+    // Ids double as indexes
+    fn find(&self, id: u32) -> Option<&Param> {
+        self.params.get(id as usize)
+    }
+
+    pub fn get(&self, id: u32) -> Option<f64> {
+        self.values.get(id as usize).map(|value| f64::from_bits(value.load(Ordering::Relaxed)))
+    }
+
+    // Every parameter is stepped, so values are rounded into range
+    pub fn set(&self, id: u32, value: f64) -> bool {
+        let Some(param) = self.find(id) else { return false };
+        let value = value.round().clamp(param.min, param.max);
+        self.values[id as usize].store(value.to_bits(), Ordering::Relaxed);
+        true
+    }
+
+    pub fn value_to_text(&self, id: u32, value: f64) -> Option<String> {
+        let param = self.find(id)?;
+        let value = value.round().clamp(param.min, param.max);
+        let text = match &param.display {
+            Display::Names(names) => names.get((value - param.min) as usize)?.clone(),
+            Display::Percent => format!("{}%", value),
+            Display::Toggle => if value >= 0.5 { "On" } else { "Off" }.to_string(),
+        };
+        Some(text)
+    }
+
+    pub fn text_to_value(&self, id: u32, text: &str) -> Option<f64> {
+        let param = self.find(id)?;
+        let text = text.trim();
+        let value = match &param.display {
+            Display::Names(names) => match names.iter().position(|name| name.eq_ignore_ascii_case(text)) {
+                Some(index) => param.min + index as f64,
+                None => text.parse().ok()?,
+            },
+            Display::Percent => text.trim_end_matches('%').trim().parse().ok()?,
+            Display::Toggle => match text.to_ascii_lowercase().as_str() {
+                "on" => 1.0,
+                "off" => 0.0,
+                _ => text.parse().ok()?,
+            },
+        };
+        Some(value.round().clamp(param.min, param.max))
+    }
+
+    fn value(&self, id: u32) -> u32 {
+        self.get(id).unwrap_or(0.0) as u32
+    }
+
+    pub fn sound(&self) -> u32 {
+        self.value(SOUND)
+    }
+
+    pub fn volume(&self) -> u32 {
+        self.value(VOLUME)
+    }
+
+    pub fn kit(&self) -> usize {
+        self.value(KIT) as usize
+    }
+
+    // The accent of every click toggle and how many of them play per beat;
+    // a fixed array, so the audio thread reads them without allocating
+    pub fn accents(&self) -> ([bool; MAX_SUBDIVISIONS], usize) {
+        let subdivisions = (self.value(SUBDIVISION) as usize).clamp(1, MAX_SUBDIVISIONS);
+        let accents = std::array::from_fn(|click| self.value(ACCENT + click as u32) == 1);
+        (accents, subdivisions)
+    }
+
+    // Saved as (id, value) pairs so parameters added later load with their defaults
+    pub fn save(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for param in &self.params {
+            bytes.extend_from_slice(&param.id.to_le_bytes());
+            bytes.extend_from_slice(&self.get(param.id).unwrap_or(param.default).to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn load(&self, bytes: &[u8]) -> bool {
+        if !bytes.len().is_multiple_of(12) {
+            return false;
+        }
+        for entry in bytes.chunks_exact(12) {
+            let id = u32::from_le_bytes(entry[..4].try_into().unwrap());
+            let value = f64::from_le_bytes(entry[4..].try_into().unwrap());
+            self.set(id, value);
+        }
+        true
+    }
+}
@@ -0,0 +1,427 @@
+use std::ffi::{CStr, c_char, c_void};
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap_sys::events::{
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE, CLAP_EVENT_TRANSPORT, CLAP_TRANSPORT_HAS_BEATS_TIMELINE,
+    CLAP_TRANSPORT_HAS_TEMPO, CLAP_TRANSPORT_HAS_TIME_SIGNATURE, CLAP_TRANSPORT_IS_PLAYING, clap_event_header,
+    clap_event_param_value, clap_event_transport, clap_input_events, clap_output_events,
+};
+use clap_sys::ext::audio_ports::{
+    CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS, CLAP_PORT_STEREO, clap_audio_port_info, clap_plugin_audio_ports,
+};
+use clap_sys::ext::params::{
+    CLAP_EXT_PARAMS, CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_ENUM, CLAP_PARAM_IS_STEPPED, clap_param_info,
+    clap_plugin_params,
+};
+use clap_sys::ext::state::{CLAP_EXT_STATE, clap_plugin_state};
+use clap_sys::fixedpoint::CLAP_BEATTIME_FACTOR;
+use clap_sys::host::clap_host;
+use clap_sys::id::{CLAP_INVALID_ID, clap_id};
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{CLAP_PROCESS_CONTINUE, CLAP_PROCESS_ERROR, clap_process, clap_process_status};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use metronome_engine::{Metronome, Renderer, Transport};
+use metronome_engine::utilities::timeline::Clicks;
+
+use crate::DESCRIPTOR;
+use crate::params::{Display, MAX_SUBDIVISIONS, Params};
+
+// What plays while the host has no transport or is stopped
+const STOPPED: Transport = Transport {
+    playing: false,
+    tempo: 120.0,
+    position: 0.0,
+    bar_start: 0.0,
+    bar_number: 0,
+    beats_per_bar: 4,
+    beat_unit: 4,
+};
+
+/// One plugin instance. The host holds a pointer to `raw`, whose
+/// `plugin_data` points back at the instance.
+struct MetronomePlugin {
+    raw: clap_plugin,
+    params: Params,
+    // Set when parameters change outside `process`, which then applies them
+    params_changed: AtomicBool,
+    // Built in `activate` and only touched by the audio thread while active
+    renderer: Mutex<Option<Renderer>>,
+}
+
+pub fn create(_host: *const clap_host) -> *const clap_plugin {
+    let plugin = Box::new(MetronomePlugin {
+        raw: clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: ptr::null_mut(),
+            init: Some(init),
+            destroy: Some(destroy),
+            activate: Some(activate),
+            deactivate: Some(deactivate),
+            start_processing: Some(start_processing),
+            stop_processing: Some(stop_processing),
+            reset: Some(reset),
+            process: Some(process),
+            get_extension: Some(get_extension),
+            on_main_thread: Some(on_main_thread),
+        },
+        params: Params::new(),
+        params_changed: AtomicBool::new(false),
+        renderer: Mutex::new(None),
+    });
+    let plugin = Box::into_raw(plugin);
+    unsafe {
+        (*plugin).raw.plugin_data = plugin.cast();
+        &(*plugin).raw
+    }
+}
+
+unsafe fn instance<'a>(plugin: *const clap_plugin) -> &'a MetronomePlugin {
+    unsafe { &*((*plugin).plugin_data as *const MetronomePlugin) }
+}
+
+fn apply_params(params: &Params, renderer: &mut Renderer) {
+    renderer.set_sound(params.sound());
+    renderer.set_volume(params.volume());
+    renderer.set_kit(params.kit());
+    let (accents, subdivisions) = params.accents();
+    renderer.set_accents(&accents[..subdivisions]);
+}
+
+fn host_transport(transport: &clap_event_transport) -> Transport {
+    let has = |flag| transport.flags & flag != 0;
+    let beats = |time: i64| time as f64 / CLAP_BEATTIME_FACTOR as f64;
+    let (beats_per_bar, beat_unit) = if has(CLAP_TRANSPORT_HAS_TIME_SIGNATURE) {
+        (transport.tsig_num as u32, transport.tsig_denom as u32)
+    } else {
+        (4, 4)
+    };
+
+    Transport {
+        playing: has(CLAP_TRANSPORT_IS_PLAYING) && has(CLAP_TRANSPORT_HAS_BEATS_TIMELINE) && has(CLAP_TRANSPORT_HAS_TEMPO),
+        tempo: transport.tempo,
+        position: beats(transport.song_pos_beats),
+        bar_start: beats(transport.bar_start),
+        bar_number: transport.bar_number,
+        beats_per_bar,
+        beat_unit,
+    }
+}
+
+unsafe fn input_events<'a>(events: *const clap_input_events) -> impl Iterator<Item = &'a clap_event_header> {
+    let (size, get) = match unsafe { events.as_ref() } {
+        Some(list) => (list.size, list.get),
+        None => (None, None),
+    };
+    let count = size.map_or(0, |size| unsafe { size(events) });
+    (0..count).filter_map(move |index| unsafe { get.and_then(|get| get(events, index).as_ref()) })
+}
+
+// Applies a parameter change, returning false for any other event
+unsafe fn apply_param_event(params: &Params, event: &clap_event_header) -> bool {
+    if event.space_id != CLAP_CORE_EVENT_SPACE_ID || event.type_ != CLAP_EVENT_PARAM_VALUE {
+        return false;
+    }
+    let event = unsafe { &*(event as *const clap_event_header as *const clap_event_param_value) };
+    params.set(event.param_id, event.value)
+}
+
+unsafe extern "C" fn init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn destroy(plugin: *const clap_plugin) {
+    drop(unsafe { Box::from_raw((*plugin).plugin_data as *mut MetronomePlugin) });
+}
+
+unsafe extern "C" fn activate(
+    plugin: *const clap_plugin,
+    sample_rate: f64,
+    _min_frames_count: u32,
+    _max_frames_count: u32,
+) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    let mut renderer = Metronome::builder().renderer();
+    renderer.set_sample_rate(sample_rate.round() as u32);
+    // Room for the longest accent pattern up front, so automation never grows it in process()
+    renderer.set_clicks(Clicks::Subdivided(Vec::with_capacity(MAX_SUBDIVISIONS)));
+    apply_params(&plugin.params, &mut renderer);
+    plugin.params_changed.store(false, Ordering::Relaxed);
+
+    match plugin.renderer.lock() {
+        Ok(mut slot) => {
+            *slot = Some(renderer);
+            true
+        },
+        Err(_) => false,
+    }
+}
+
+unsafe extern "C" fn deactivate(plugin: *const clap_plugin) {
+    let plugin = unsafe { instance(plugin) };
+    if let Ok(mut renderer) = plugin.renderer.lock() {
+        *renderer = None;
+    }
+}
+
+unsafe extern "C" fn start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn reset(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn on_main_thread(_plugin: *const clap_plugin) {}
+
+// Renders the first output channel between events, so clicks, transport jumps
+// and automation all land on their exact frame, then copies it to the others
+unsafe extern "C" fn process(plugin: *const clap_plugin, process: *const clap_process) -> clap_process_status {
+    let plugin = unsafe { instance(plugin) };
+    let process = unsafe { &*process };
+    let Ok(mut renderer) = plugin.renderer.try_lock() else {
+        return CLAP_PROCESS_ERROR;
+    };
+    let Some(renderer) = renderer.as_mut() else {
+        return CLAP_PROCESS_ERROR;
+    };
+    if plugin.params_changed.swap(false, Ordering::Relaxed) {
+        apply_params(&plugin.params, renderer);
+    }
+
+    let Some(output) = (unsafe { process.audio_outputs.as_ref() }) else {
+        return CLAP_PROCESS_CONTINUE;
+    };
+    if process.audio_outputs_count == 0 || output.data32.is_null() || output.channel_count == 0 {
+        return CLAP_PROCESS_ERROR;
+    }
+    let frames = process.frames_count as usize;
+    let first = unsafe { *output.data32 };
+    if first.is_null() {
+        return CLAP_PROCESS_ERROR;
+    }
+    let samples = unsafe { std::slice::from_raw_parts_mut(first, frames) };
+
+    let sample_rate = renderer.sample_rate();
+    let mut transport = unsafe { process.transport.as_ref() }.map_or(STOPPED, host_transport);
+    let mut rendered = 0;
+
+    for event in unsafe { input_events(process.in_events) } {
+        let time = (event.time as usize).clamp(rendered, frames);
+        renderer.render_transport(&mut samples[rendered..time], &transport, |_, _| {});
+        transport = transport.advance(time - rendered, sample_rate);
+        rendered = time;
+
+        if unsafe { apply_param_event(&plugin.params, event) } {
+            apply_params(&plugin.params, renderer);
+        } else if event.space_id == CLAP_CORE_EVENT_SPACE_ID && event.type_ == CLAP_EVENT_TRANSPORT {
+            transport = host_transport(unsafe { &*(event as *const clap_event_header as *const clap_event_transport) });
+        }
+    }
+    renderer.render_transport(&mut samples[rendered..], &transport, |_, _| {});
+
+    for channel in 1..output.channel_count as usize {
+        let destination = unsafe { *output.data32.add(channel) };
+        if !destination.is_null() && destination != first {
+            unsafe { ptr::copy_nonoverlapping(first, destination, frames) };
+        }
+    }
+    CLAP_PROCESS_CONTINUE
+}
+
+unsafe extern "C" fn get_extension(_plugin: *const clap_plugin, id: *const c_char) -> *const c_void {
+    if id.is_null() {
+        return ptr::null();
+    }
+    let id = unsafe { CStr::from_ptr(id) };
+    if id == CLAP_EXT_PARAMS {
+        (&PARAMS as *const clap_plugin_params).cast()
+    } else if id == CLAP_EXT_AUDIO_PORTS {
+        (&AUDIO_PORTS as *const clap_plugin_audio_ports).cast()
+    } else if id == CLAP_EXT_STATE {
+        (&STATE as *const clap_plugin_state).cast()
+    } else {
+        ptr::null()
+    }
+}
+
+// Copies as much of `text` as fits, always leaving a terminating nul
+fn write_str(destination: &mut [c_char], text: &str) {
+    let length = text.len().min(destination.len().saturating_sub(1));
+    for (target, byte) in destination.iter_mut().zip(&text.as_bytes()[..length]) {
+        *target = *byte as c_char;
+    }
+    if let Some(end) = destination.get_mut(length) {
+        *end = 0;
+    }
+}
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count: Some(params_count),
+    get_info: Some(params_get_info),
+    get_value: Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush: Some(params_flush),
+};
+
+unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
+    unsafe { instance(plugin) }.params.count() as u32
+}
+
+unsafe extern "C" fn params_get_info(plugin: *const clap_plugin, index: u32, info: *mut clap_param_info) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    let (Some(param), Some(info)) = (plugin.params.info(index as usize), unsafe { info.as_mut() }) else {
+        return false;
+    };
+
+    info.id = param.id;
+    info.flags = CLAP_PARAM_IS_STEPPED | CLAP_PARAM_IS_AUTOMATABLE;
+    if let Display::Names(_) | Display::Toggle = param.display {
+        info.flags |= CLAP_PARAM_IS_ENUM;
+    }
+    info.cookie = ptr::null_mut();
+    write_str(&mut info.name, param.name);
+    write_str(&mut info.module, "");
+    info.min_value = param.min;
+    info.max_value = param.max;
+    info.default_value = param.default;
+    true
+}
+
+unsafe extern "C" fn params_get_value(plugin: *const clap_plugin, id: clap_id, value: *mut f64) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    match (plugin.params.get(id), unsafe { value.as_mut() }) {
+        (Some(current), Some(value)) => {
+            *value = current;
+            true
+        },
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn params_value_to_text(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    value: f64,
+    buffer: *mut c_char,
+    capacity: u32,
+) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    let Some(text) = plugin.params.value_to_text(id, value) else { return false };
+    if buffer.is_null() || capacity == 0 {
+        return false;
+    }
+    write_str(unsafe { std::slice::from_raw_parts_mut(buffer, capacity as usize) }, &text);
+    true
+}
+
+unsafe extern "C" fn params_text_to_value(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    text: *const c_char,
+    value: *mut f64,
+) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    if text.is_null() {
+        return false;
+    }
+    let Ok(text) = unsafe { CStr::from_ptr(text) }.to_str() else { return false };
+    match (plugin.params.text_to_value(id, text), unsafe { value.as_mut() }) {
+        (Some(parsed), Some(value)) => {
+            *value = parsed;
+            true
+        },
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn params_flush(
+    plugin: *const clap_plugin,
+    in_events: *const clap_input_events,
+    _out_events: *const clap_output_events,
+) {
+    let plugin = unsafe { instance(plugin) };
+    for event in unsafe { input_events(in_events) } {
+        if unsafe { apply_param_event(&plugin.params, event) } {
+            plugin.params_changed.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get: Some(audio_ports_get),
+};
+
+// A single stereo output and no inputs
+unsafe extern "C" fn audio_ports_count(_plugin: *const clap_plugin, is_input: bool) -> u32 {
+    if is_input { 0 } else { 1 }
+}
+
+unsafe extern "C" fn audio_ports_get(
+    _plugin: *const clap_plugin,
+    index: u32,
+    is_input: bool,
+    info: *mut clap_audio_port_info,
+) -> bool {
+    let Some(info) = (unsafe { info.as_mut() }) else { return false };
+    if is_input || index != 0 {
+        return false;
+    }
+    info.id = 0;
+    write_str(&mut info.name, "Click");
+    info.flags = CLAP_AUDIO_PORT_IS_MAIN;
+    info.channel_count = 2;
+    info.port_type = CLAP_PORT_STEREO.as_ptr();
+    info.in_place_pair = CLAP_INVALID_ID;
+    true
+}
+
+static STATE: clap_plugin_state = clap_plugin_state {
+    save: Some(state_save),
+    load: Some(state_load),
+};
+
+unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    let Some(write) = (unsafe { stream.as_ref() }).and_then(|stream| stream.write) else {
+        return false;
+    };
+
+    // Streams may take fewer bytes than offered
+    let bytes = plugin.params.save();
+    let mut written = 0;
+    while written < bytes.len() {
+        let remaining = &bytes[written..];
+        let count = unsafe { write(stream, remaining.as_ptr().cast(), remaining.len() as u64) };
+        if count <= 0 {
+            return false;
+        }
+        written += count as usize;
+    }
+    true
+}
+
+unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let plugin = unsafe { instance(plugin) };
+    let Some(read) = (unsafe { stream.as_ref() }).and_then(|stream| stream.read) else {
+        return false;
+    };
+
+    let mut bytes = Vec::new();
+    let mut buffer = [0u8; 256];
+    loop {
+        let count = unsafe { read(stream, buffer.as_mut_ptr().cast(), buffer.len() as u64) };
+        match count {
+            0 => break,
+            count if count < 0 => return false,
+            count => bytes.extend_from_slice(&buffer[..count as usize]),
+        }
+    }
+
+    let loaded = plugin.params.load(&bytes);
+    plugin.params_changed.store(true, Ordering::Relaxed);
+    loaded
+}
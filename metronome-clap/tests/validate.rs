@@ -0,0 +1,376 @@
+// Headless host that walks the plugin through the checks a plugin validator
+// makes: factory and descriptor, parameters, ports, state, and processing
+// against a playing transport with sample-accurate clicks.
+
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::ptr;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::events::{
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE, CLAP_TRANSPORT_HAS_BEATS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO,
+    CLAP_TRANSPORT_HAS_TIME_SIGNATURE, CLAP_TRANSPORT_IS_PLAYING, clap_event_header, clap_event_param_value,
+    clap_event_transport, clap_input_events, clap_output_events,
+};
+use clap_sys::ext::audio_ports::{CLAP_EXT_AUDIO_PORTS, clap_audio_port_info, clap_plugin_audio_ports};
+use clap_sys::ext::params::{CLAP_EXT_PARAMS, clap_param_info, clap_plugin_params};
+use clap_sys::ext::state::{CLAP_EXT_STATE, clap_plugin_state};
+use clap_sys::factory::plugin_factory::{CLAP_PLUGIN_FACTORY_ID, clap_plugin_factory};
+use clap_sys::fixedpoint::CLAP_BEATTIME_FACTOR;
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{CLAP_PROCESS_CONTINUE, clap_process};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::{CLAP_VERSION, clap_version_is_compatible};
+use metronome_clap::{PLUGIN_ID, clap_entry};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const SUBDIVISION: u32 = 2;
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+fn host() -> clap_host {
+    clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: ptr::null_mut(),
+        name: c"validate".as_ptr(),
+        vendor: c"".as_ptr(),
+        url: c"".as_ptr(),
+        version: c"0".as_ptr(),
+        get_extension: Some(host_get_extension),
+        request_restart: Some(host_request),
+        request_process: Some(host_request),
+        request_callback: Some(host_request),
+    }
+}
+
+// Input event list over a vector of boxed events, sorted by time
+struct Events(Vec<clap_event_param_value>);
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    unsafe { (*((*list).ctx as *const Events)).0.len() as u32 }
+}
+
+unsafe extern "C" fn events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = unsafe { &*((*list).ctx as *const Events) };
+    events.0.get(index as usize).map_or(ptr::null(), |event| &event.header)
+}
+
+unsafe extern "C" fn events_push(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    true
+}
+
+fn input_events(events: &Events) -> clap_input_events {
+    clap_input_events {
+        ctx: events as *const Events as *mut c_void,
+        size: Some(events_size),
+        get: Some(events_get),
+    }
+}
+
+fn output_events() -> clap_output_events {
+    clap_output_events {
+        ctx: ptr::null_mut(),
+        try_push: Some(events_push),
+    }
+}
+
+fn param_event(time: u32, param_id: u32, value: f64) -> clap_event_param_value {
+    clap_event_param_value {
+        header: clap_event_header {
+            size: size_of::<clap_event_param_value>() as u32,
+            time,
+            space_id: CLAP_CORE_EVENT_SPACE_ID,
+            type_: CLAP_EVENT_PARAM_VALUE,
+            flags: 0,
+        },
+        param_id,
+        cookie: ptr::null_mut(),
+        note_id: -1,
+        port_index: -1,
+        channel: -1,
+        key: -1,
+        value,
+    }
+}
+
+fn transport(playing: bool, tempo: f64, beats: f64) -> clap_event_transport {
+    let mut flags = CLAP_TRANSPORT_HAS_TEMPO | CLAP_TRANSPORT_HAS_BEATS_TIMELINE | CLAP_TRANSPORT_HAS_TIME_SIGNATURE;
+    if playing {
+        flags |= CLAP_TRANSPORT_IS_PLAYING;
+    }
+    let bar = (beats / 4.0).floor();
+    clap_event_transport {
+        header: clap_event_header {
+            size: size_of::<clap_event_transport>() as u32,
+            time: 0,
+            space_id: CLAP_CORE_EVENT_SPACE_ID,
+            type_: clap_sys::events::CLAP_EVENT_TRANSPORT,
+            flags: 0,
+        },
+        flags,
+        song_pos_beats: (beats * CLAP_BEATTIME_FACTOR as f64).round() as i64,
+        song_pos_seconds: 0,
+        tempo,
+        tempo_inc: 0.0,
+        loop_start_beats: 0,
+        loop_end_beats: 0,
+        loop_start_seconds: 0,
+        loop_end_seconds: 0,
+        bar_start: (bar * 4.0 * CLAP_BEATTIME_FACTOR as f64) as i64,
+        bar_number: bar as i32,
+        tsig_num: 4,
+        tsig_denom: 4,
+    }
+}
+
+struct Plugin(*const clap_plugin);
+
+impl Plugin {
+    fn get<'a>(&self) -> &'a clap_plugin {
+        unsafe { &*self.0 }
+    }
+
+    fn extension<T>(&self, id: &CStr) -> &T {
+        let extension = unsafe { self.get().get_extension.unwrap()(self.0, id.as_ptr()) };
+        assert!(!extension.is_null(), "missing extension {:?}", id);
+        unsafe { &*(extension as *const T) }
+    }
+
+    // Renders `frames` stereo frames, with the transport and events as given
+    fn process(&self, frames: usize, transport: Option<&clap_event_transport>, events: &Events) -> [Vec<f32>; 2] {
+        let mut left = vec![f32::NAN; frames];
+        let mut right = vec![f32::NAN; frames];
+        let mut channels = [left.as_mut_ptr(), right.as_mut_ptr()];
+        let mut output = clap_audio_buffer {
+            data32: channels.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let in_events = input_events(events);
+        let out_events = output_events();
+        let process = clap_process {
+            steady_time: -1,
+            frames_count: frames as u32,
+            transport: transport.map_or(ptr::null(), |transport| transport as *const _),
+            audio_inputs: ptr::null(),
+            audio_outputs: &mut output,
+            audio_inputs_count: 0,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+        let status = unsafe { self.get().process.unwrap()(self.0, &process) };
+        assert_eq!(status, CLAP_PROCESS_CONTINUE);
+        [left, right]
+    }
+}
+
+struct Buffer {
+    bytes: Vec<u8>,
+    read: usize,
+}
+
+unsafe extern "C" fn stream_write(stream: *const clap_ostream, data: *const c_void, size: u64) -> i64 {
+    let buffer = unsafe { &mut *((*stream).ctx as *mut Buffer) };
+    // Take at most 7 bytes at a time to exercise partial writes
+    let size = size.min(7) as usize;
+    buffer.bytes.extend_from_slice(unsafe { std::slice::from_raw_parts(data as *const u8, size) });
+    size as i64
+}
+
+unsafe extern "C" fn stream_read(stream: *const clap_istream, data: *mut c_void, size: u64) -> i64 {
+    let buffer = unsafe { &mut *((*stream).ctx as *mut Buffer) };
+    let size = (size as usize).min(buffer.bytes.len() - buffer.read).min(5);
+    unsafe { ptr::copy_nonoverlapping(buffer.bytes[buffer.read..].as_ptr(), data as *mut u8, size) };
+    buffer.read += size;
+    size as i64
+}
+
+fn create_plugin(factory: &clap_plugin_factory, host: &clap_host) -> Plugin {
+    let wrong = unsafe { factory.create_plugin.unwrap()(factory, host, c"not.a.plugin".as_ptr()) };
+    assert!(wrong.is_null());
+
+    let plugin = unsafe { factory.create_plugin.unwrap()(factory, host, PLUGIN_ID.as_ptr()) };
+    assert!(!plugin.is_null());
+    let plugin = Plugin(plugin);
+    assert!(unsafe { plugin.get().init.unwrap()(plugin.0) });
+    plugin
+}
+
+fn factory<'a>() -> &'a clap_plugin_factory {
+    assert!(clap_version_is_compatible(clap_entry.clap_version));
+    let path = CString::new(env!("CARGO_MANIFEST_DIR")).unwrap();
+    assert!(unsafe { clap_entry.init.unwrap()(path.as_ptr()) });
+
+    assert!(unsafe { clap_entry.get_factory.unwrap()(c"clap.unknown-factory".as_ptr()) }.is_null());
+    let factory = unsafe { clap_entry.get_factory.unwrap()(CLAP_PLUGIN_FACTORY_ID.as_ptr()) };
+    assert!(!factory.is_null());
+    unsafe { &*(factory as *const clap_plugin_factory) }
+}
+
+#[test]
+fn descriptor() {
+    let factory = factory();
+    assert_eq!(unsafe { factory.get_plugin_count.unwrap()(factory) }, 1);
+    assert!(unsafe { factory.get_plugin_descriptor.unwrap()(factory, 1) }.is_null());
+
+    let descriptor = unsafe { &*factory.get_plugin_descriptor.unwrap()(factory, 0) };
+    assert!(clap_version_is_compatible(descriptor.clap_version));
+    assert_eq!(unsafe { CStr::from_ptr(descriptor.id) }, PLUGIN_ID);
+    for field in [descriptor.name, descriptor.vendor, descriptor.version, descriptor.description] {
+        assert!(!field.is_null());
+    }
+    let mut features = descriptor.features;
+    let mut count = 0;
+    while !unsafe { *features }.is_null() {
+        count += 1;
+        features = unsafe { features.add(1) };
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn params_and_ports() {
+    let host = host();
+    let plugin = create_plugin(factory(), &host);
+    let params: &clap_plugin_params = plugin.extension(CLAP_EXT_PARAMS);
+
+    let count = unsafe { params.count.unwrap()(plugin.0) };
+    assert!(count > 0);
+    for index in 0..count {
+        let mut info: clap_param_info = unsafe { std::mem::zeroed() };
+        assert!(unsafe { params.get_info.unwrap()(plugin.0, index, &mut info) });
+        assert!(info.min_value <= info.default_value && info.default_value <= info.max_value);
+
+        let mut value = f64::NAN;
+        assert!(unsafe { params.get_value.unwrap()(plugin.0, info.id, &mut value) });
+        assert_eq!(value, info.default_value);
+
+        // Every value survives a round trip through its text
+        let mut step = info.min_value;
+        while step <= info.max_value {
+            let mut text = [0 as c_char; 64];
+            assert!(unsafe { params.value_to_text.unwrap()(plugin.0, info.id, step, text.as_mut_ptr(), 64) });
+            let mut parsed = f64::NAN;
+            assert!(unsafe { params.text_to_value.unwrap()(plugin.0, info.id, text.as_ptr(), &mut parsed) });
+            assert_eq!(parsed, step);
+            step += 1.0;
+        }
+    }
+    let mut info: clap_param_info = unsafe { std::mem::zeroed() };
+    assert!(!unsafe { params.get_info.unwrap()(plugin.0, count, &mut info) });
+
+    let ports: &clap_plugin_audio_ports = plugin.extension(CLAP_EXT_AUDIO_PORTS);
+    assert_eq!(unsafe { ports.count.unwrap()(plugin.0, true) }, 0);
+    assert_eq!(unsafe { ports.count.unwrap()(plugin.0, false) }, 1);
+    let mut port: clap_audio_port_info = unsafe { std::mem::zeroed() };
+    assert!(unsafe { ports.get.unwrap()(plugin.0, 0, false, &mut port) });
+    assert_eq!(port.channel_count, 2);
+
+    unsafe { plugin.get().destroy.unwrap()(plugin.0) };
+}
+
+#[test]
+fn state_round_trip() {
+    let host = host();
+    let plugin = create_plugin(factory(), &host);
+    let params: &clap_plugin_params = plugin.extension(CLAP_EXT_PARAMS);
+    let state: &clap_plugin_state = plugin.extension(CLAP_EXT_STATE);
+    let flush = |id, value| {
+        let events = Events(vec![param_event(0, id, value)]);
+        unsafe { params.flush.unwrap()(plugin.0, &input_events(&events), &output_events()) };
+    };
+    let value = |id| {
+        let mut value = f64::NAN;
+        unsafe { params.get_value.unwrap()(plugin.0, id, &mut value) };
+        value
+    };
+
+    flush(SUBDIVISION, 3.0);
+    let mut buffer = Buffer { bytes: Vec::new(), read: 0 };
+    let stream = clap_ostream {
+        ctx: &mut buffer as *mut Buffer as *mut c_void,
+        write: Some(stream_write),
+    };
+    assert!(unsafe { state.save.unwrap()(plugin.0, &stream) });
+
+    flush(SUBDIVISION, 1.0);
+    assert_eq!(value(SUBDIVISION), 1.0);
+    let stream = clap_istream {
+        ctx: &mut buffer as *mut Buffer as *mut c_void,
+        read: Some(stream_read),
+    };
+    assert!(unsafe { state.load.unwrap()(plugin.0, &stream) });
+    assert_eq!(value(SUBDIVISION), 3.0);
+
+    unsafe { plugin.get().destroy.unwrap()(plugin.0) };
+}
+
+// Frames where a click starts: sound after at least 100 silent frames. The
+// built-in sounds are sines starting on a zero crossing, so the first frame of
+// a click is the silent one before its first audible sample.
+fn onsets(samples: &[f32]) -> Vec<usize> {
+    let mut onsets = Vec::new();
+    let mut silent = usize::MAX;
+    for (frame, sample) in samples.iter().enumerate() {
+        if *sample != 0.0 {
+            if silent >= 100 {
+                onsets.push(frame - 1);
+            }
+            silent = 0;
+        } else {
+            silent = silent.saturating_add(1);
+        }
+    }
+    onsets
+}
+
+#[test]
+fn follows_transport() {
+    let host = host();
+    let plugin = create_plugin(factory(), &host);
+    let raw = plugin.get();
+    assert!(unsafe { raw.activate.unwrap()(plugin.0, SAMPLE_RATE, 1, 4096) });
+    assert!(unsafe { raw.start_processing.unwrap()(plugin.0) });
+    let no_events = Events(Vec::new());
+
+    // No transport, or a stopped one, renders silence
+    for transport in [None, Some(&transport(false, 120.0, 0.0))] {
+        let [left, _] = plugin.process(1024, transport, &no_events);
+        assert!(left.iter().all(|sample| *sample == 0.0));
+    }
+
+    // Two seconds at 120 BPM in odd-sized blocks, starting a little before bar 2
+    let block = 777;
+    let start_beats = 3.75;
+    let total = 2 * SAMPLE_RATE as usize;
+    let mut left = Vec::new();
+    let mut position = 0;
+    while position < total {
+        let frames = block.min(total - position);
+        let beats = start_beats + position as f64 / SAMPLE_RATE * 2.0;
+        let [block_left, block_right] = plugin.process(frames, Some(&transport(true, 120.0, beats)), &no_events);
+        assert_eq!(block_left, block_right);
+        left.extend(block_left);
+        position += frames;
+    }
+
+    // Beat 4 is 0.25 beats (6000 frames) away, then one every 24000 frames
+    assert_eq!(onsets(&left), vec![6000, 30000, 54000, 78000]);
+
+    // Switching to eighths mid-block doubles the clicks from that frame on
+    let events = Events(vec![param_event(100, SUBDIVISION, 2.0)]);
+    let [left, _] = plugin.process(48000, Some(&transport(true, 120.0, 8.0)), &events);
+    assert_eq!(onsets(&left), vec![0, 12000, 24000, 36000]);
+
+    unsafe { raw.stop_processing.unwrap()(plugin.0) };
+    unsafe { raw.deactivate.unwrap()(plugin.0) };
+    unsafe { raw.destroy.unwrap()(plugin.0) };
+    unsafe { clap_entry.deinit.unwrap()() };
+}
@@ -0,0 +1,81 @@
+// Compares the per-beat cost of the old copy-and-scale path with the shared
+// `Arc<[f32]>` + mixer path. Run with `cargo bench --bench audio_path`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use metronome_engine::utilities::loudness::limit;
+use metronome_engine::utilities::mixer::mixer;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const BEATS: usize = 10_000;
+const CLICK_SAMPLES: usize = 4410;
+
+fn report(name: &str, run: impl FnOnce()) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    run();
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<28} {:>8.2} allocations/beat {:>10.0} ns/beat",
+        name,
+        allocations as f64 / BEATS as f64,
+        elapsed.as_nanos() as f64 / BEATS as f64
+    );
+}
+
+fn main() {
+    let sound: Vec<f32> = (0..CLICK_SAMPLES).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+    let shared: Arc<[f32]> = sound.clone().into();
+
+    // Old path: clone the cached buffer and scale it on the timing thread
+    report("clone + map/collect (old)", || {
+        for _ in 0..BEATS {
+            let scaled: Vec<f32> = sound.clone().iter().map(|&s| limit(s * 0.8)).collect();
+            black_box(scaled);
+        }
+    });
+
+    let (handle, mut mixer) = mixer();
+
+    // New path on the timing thread: bump the refcount and queue the voice.
+    // One poll interval of output is rendered per beat so the queue never fills.
+    report("Arc trigger + voice pickup", || {
+        for _ in 0..BEATS {
+            handle.play(black_box(&shared), 0.8);
+            for _ in 0..64 {
+                black_box(mixer.next());
+            }
+        }
+    });
+
+    // Mixing alone, as the output callback sees it
+    report("mixer render (output)", || {
+        for _ in 0..BEATS {
+            for _ in 0..CLICK_SAMPLES {
+                black_box(mixer.next());
+            }
+        }
+    });
+}
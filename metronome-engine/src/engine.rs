@@ -0,0 +1,982 @@
+use rodio::OutputStream;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{
+    Arc,
+    mpsc::{self, Receiver, RecvTimeoutError, Sender},
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+
+use crate::render::Renderer;
+use crate::utilities::config::Config;
+use crate::utilities::kit::{SoundKit, VoiceRole, builtin_kits};
+use crate::utilities::loudness::match_loudness;
+use crate::utilities::mixer::{MixerHandle, mixer};
+use crate::utilities::mode::{MetronomeModeBehavior, ModePanel, Setting, SettingValue};
+use crate::utilities::modes::builtin_modes;
+use crate::utilities::sink::{AudioSink, BeatSink, ScheduledBeat};
+use crate::utilities::snapshot::SnapshotPublisher;
+use crate::utilities::sound::{
+    create_beep_sound, create_celebration_sound, create_click_sound, create_cowbell_sound,
+    create_hihat_sound, create_kick_sound, create_square_sound, create_triangle_sound,
+    create_wood_block_sound,
+};
+use crate::utilities::timeline::{BEATS_PER_BAR, Timeline, TimelineEvent};
+
+// Sound cache keys 0-7 are the built-in clicks and 8 is the celebration sound
+pub const CELEBRATION_SOUND: u32 = 8;
+pub const CUSTOM_SOUND_START: u32 = 9;
+// The tempos the apps offer; saved configs and presets are held to them when loaded
+pub const MIN_BPM: u32 = 30;
+pub const MAX_BPM: u32 = 300;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sound {
+    Beep,
+    Kick,
+    Click,
+    Cowbell,
+    HiHat,
+    Woodblock,
+    Triangle,
+    Square,
+    // The nth sound added with `MetronomeBuilder::custom_sound`
+    Custom(u32),
+}
+
+impl Sound {
+    // Key of the sound in the engine's sound cache
+    pub fn key(self) -> u32 {
+        match self {
+            Sound::Beep => 0,
+            Sound::Kick => 1,
+            Sound::Click => 2,
+            Sound::Cowbell => 3,
+            Sound::HiHat => 4,
+            Sound::Woodblock => 5,
+            Sound::Triangle => 6,
+            Sound::Square => 7,
+            Sound::Custom(index) => CUSTOM_SOUND_START + index,
+        }
+    }
+
+    pub fn from_key(key: u32) -> Option<Sound> {
+        let sound = match key {
+            0 => Sound::Beep,
+            1 => Sound::Kick,
+            2 => Sound::Click,
+            3 => Sound::Cowbell,
+            4 => Sound::HiHat,
+            5 => Sound::Woodblock,
+            6 => Sound::Triangle,
+            7 => Sound::Square,
+            CELEBRATION_SOUND => return None,
+            _ => Sound::Custom(key - CUSTOM_SOUND_START),
+        };
+        Some(sound)
+    }
+}
+
+// Commands sent to the metronome thread
+#[derive(Debug, Clone)]
+pub enum MetronomeCommand {
+    Start,
+    Stop,
+    // Held to MIN_BPM-MAX_BPM
+    ChangeBpm(u32),
+    // 0-100
+    ChangeVolume(u32),
+    ChangeSoundType(u32),
+    ChangeMode(usize),
+    ChangeKit(usize),
+    ChangeMeter(u32),
+    SetCountIn(bool),
+    AddCustomSound { key: u32, samples: Vec<f32> },
+    // Applies to the current mode
+    UpdateModeSetting { key: &'static str, value: SettingValue },
+    // Recalls a saved config or preset at once; `sound_type` is its sound, resolved by the frontend
+    LoadConfig { config: Box<Config>, sound_type: Option<u32> },
+    Reset,
+}
+
+// Events sent back from the metronome thread
+#[derive(Debug, Clone)]
+pub enum MetronomeEvent {
+    // `at` is when the click was scheduled to sound, not when the thread woke up
+    Beat { tick_count: u32, event: TimelineEvent, at: Instant },
+    // Sent just before the first beat of every bar, with the tempo it plays at
+    Bar { bar: u32, bpm: u32, at: Instant },
+    ModeChanged { mode: usize },
+    // Tempo changes made by the mode; `random` when the mode picked the tempo at random
+    BpmChanged { bpm: u32, random: bool },
+    // The mode moved on to another section, or looped back into one
+    SectionChanged { section: Arc<str> },
+    Started { mode: usize, bpm: u32 },
+    // Stopped by a command; a mode that runs to its end sends Finished instead
+    Stopped,
+    // The mode ran to its end and stopped the metronome; `countdown` when the
+    // mode counts down, see `MetronomeModeBehavior::is_countdown`
+    Finished { mode: usize, bpm: u32, countdown: bool },
+    Error { message: String },
+}
+
+// Called with every event; returning false unsubscribes
+pub(crate) type Subscriber = Box<dyn FnMut(&MetronomeEvent) -> bool + Send>;
+
+// Everything the engine thread receives; subscriptions are not commands
+pub(crate) enum Request {
+    Command(MetronomeCommand),
+    Subscribe(Subscriber),
+    // Called once every earlier request is applied and the new state is published
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    Applied(Box<dyn FnOnce() + Send>),
+    Shutdown,
+}
+
+// Immutable copy of the engine state, published after every change.
+// Cloning is cheap: the only collections are shared slices.
+#[derive(Clone, Debug)]
+pub struct MetronomeSnapshot {
+    // Core state
+    pub bpm: u32,
+    pub is_running: bool,
+    pub volume: u32,
+    pub sound_type: u32,
+    pub tick_count: u32,
+    pub beats_per_bar: u32,
+
+    // 0 plays the selected sound for every beat, n selects kits[n - 1]
+    pub kit: usize,
+    pub count_in: bool,
+
+    // Index into modes, which lists (icon, name) for every mode the engine knows
+    pub mode: usize,
+    pub modes: Arc<[(&'static str, &'static str)]>,
+    pub settings: Arc<[Setting]>,
+    pub panel: Arc<ModePanel>,
+    // Time between clicks in beats, as laid out by the mode's timeline
+    pub interval: f32,
+
+    // Beat timing
+    pub last_beat: Instant,
+}
+
+impl Default for MetronomeSnapshot {
+    fn default() -> Self {
+        Self {
+            bpm: 120,
+            is_running: false,
+            volume: 80,
+            sound_type: 0,
+            tick_count: 0,
+            beats_per_bar: BEATS_PER_BAR,
+            kit: 0,
+            count_in: false,
+            mode: 0,
+            modes: Arc::new([]),
+            settings: Arc::new([]),
+            panel: Arc::default(),
+            interval: 1.0,
+            last_beat: Instant::now(),
+        }
+    }
+}
+
+impl MetronomeSnapshot {
+    fn with_modes(modes: &[Box<dyn MetronomeModeBehavior>], mode: usize) -> Self {
+        Self {
+            modes: modes.iter().map(|mode| (mode.icon(), mode.name())).collect(),
+            mode,
+            settings: modes[mode].settings().into(),
+            panel: Arc::new(modes[mode].panel()),
+            ..Self::default()
+        }
+    }
+}
+
+pub struct MetronomeBuilder {
+    bpm: u32,
+    beats_per_bar: u32,
+    volume: u32,
+    sound: Sound,
+    count_in: bool,
+    modes: Vec<Box<dyn MetronomeModeBehavior>>,
+    selected_mode: usize,
+    kits: Vec<SoundKit>,
+    kit: usize,
+    custom_sounds: Vec<Vec<f32>>,
+    sinks: Vec<Box<dyn BeatSink>>,
+    audio_clicks: bool,
+    audio_latency: Duration,
+}
+
+impl Default for MetronomeBuilder {
+    fn default() -> Self {
+        Self {
+            bpm: 120,
+            beats_per_bar: BEATS_PER_BAR,
+            volume: 80,
+            sound: Sound::Beep,
+            count_in: false,
+            modes: builtin_modes(),
+            selected_mode: 0,
+            kits: builtin_kits(),
+            kit: 0,
+            custom_sounds: Vec::new(),
+            sinks: Vec::new(),
+            audio_clicks: true,
+            audio_latency: Duration::ZERO,
+        }
+    }
+}
+
+impl MetronomeBuilder {
+    // MIN_BPM-MAX_BPM, like every tempo the engine is given
+    pub fn bpm(mut self, bpm: u32) -> Self {
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        self
+    }
+
+    // Beats per bar; the first beat of every bar plays as the downbeat
+    pub fn meter(mut self, beats_per_bar: u32) -> Self {
+        self.beats_per_bar = beats_per_bar.max(1);
+        self
+    }
+
+    pub fn sound(mut self, sound: Sound) -> Self {
+        self.sound = sound;
+        self
+    }
+
+    // 0-100
+    pub fn volume(mut self, volume: u32) -> Self {
+        self.volume = volume.min(100);
+        self
+    }
+
+    pub fn count_in(mut self, enabled: bool) -> Self {
+        self.count_in = enabled;
+        self
+    }
+
+    // Adds a mode after the built-in ones
+    pub fn mode(mut self, mode: Box<dyn MetronomeModeBehavior>) -> Self {
+        self.modes.push(mode);
+        self
+    }
+
+    // Index of the mode to start in, counting the built-in ones
+    pub fn select_mode(mut self, mode: usize) -> Self {
+        self.selected_mode = mode;
+        self
+    }
+
+    // Replaces the built-in kits
+    pub fn kits(mut self, kits: Vec<SoundKit>) -> Self {
+        self.kits = kits;
+        self
+    }
+
+    // 0 plays the selected sound for every beat, n selects kits[n - 1]
+    pub fn kit(mut self, kit: usize) -> Self {
+        self.kit = kit;
+        self
+    }
+
+    // Restores a saved config; call it after adding kits, sounds and modes so names resolve.
+    // `resolve_sound` maps a sound name to its sound cache key, like `load_kits`.
+    pub fn config(mut self, config: &Config, resolve_sound: impl Fn(&str) -> Option<u32>) -> Self {
+        self = self.bpm(config.bpm).volume(config.volume).meter(config.beats_per_bar).count_in(config.count_in);
+        if let Some(sound) = resolve_sound(&config.sound).and_then(Sound::from_key) {
+            self.sound = sound;
+        }
+        self.kit = self.kits.iter().position(|kit| kit.name == config.kit).map_or(0, |i| i + 1);
+        if let Some(index) = self.modes.iter().position(|mode| mode.name() == config.mode) {
+            self.selected_mode = index;
+        }
+        for mode in &mut self.modes {
+            for (key, value) in config.modes.get(mode.name()).into_iter().flatten() {
+                mode.apply_setting(key, value.clone());
+            }
+        }
+        self
+    }
+
+    // Mono 44.1 kHz samples, played as `Sound::Custom` in the order they were added
+    pub fn custom_sound(mut self, mut samples: Vec<f32>) -> Self {
+        match_loudness(&mut samples);
+        self.custom_sounds.push(samples);
+        self
+    }
+
+    // Adds an output that gets every click alongside the audio, see `BeatSink`
+    pub fn sink(mut self, sink: Box<dyn BeatSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    // Whether clicks play through the audio output; `Metronome::play` always does
+    pub fn audio_clicks(mut self, enabled: bool) -> Self {
+        self.audio_clicks = enabled;
+        self
+    }
+
+    // Output latency to make up for, so the audio lines up with the other sinks
+    pub fn audio_latency(mut self, latency: Duration) -> Self {
+        self.audio_latency = latency;
+        self
+    }
+
+    // Opens the default audio output and starts the engine thread, stopped
+    pub fn build(mut self) -> Result<Metronome, Box<dyn Error>> {
+        let (state, sound_cache) = self.initial_state();
+        let (publisher, snapshots) = SnapshotPublisher::new(state.clone());
+
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (ready_sender, ready_receiver) = mpsc::sync_channel(1);
+        let (mixer_handle, mixer) = mixer();
+        let engine_mixer = mixer_handle.clone();
+        let modes = self.modes;
+        let kits = self.kits;
+        let mut sinks = self.sinks;
+        if self.audio_clicks {
+            sinks.insert(0, Box::new(AudioSink::new(mixer_handle.clone(), self.audio_latency)));
+        }
+
+        let thread = thread::spawn(move || {
+            // The output stream is not Send, so it lives on the engine thread
+            let _stream = match OutputStream::try_default() {
+                Ok((stream, stream_handle)) => match stream_handle.play_raw(mixer) {
+                    Ok(()) => stream,
+                    Err(error) => {
+                        let _ = ready_sender.send(Err(error.to_string()));
+                        return;
+                    }
+                },
+                Err(error) => {
+                    let _ = ready_sender.send(Err(error.to_string()));
+                    return;
+                }
+            };
+            let _ = ready_sender.send(Ok(()));
+
+            let outputs = Outputs { mixer: engine_mixer, sinks };
+            metronome_thread(state, publisher, modes, outputs, sound_cache, kits, request_receiver);
+        });
+
+        match ready_receiver.recv() {
+            Ok(Ok(())) => Ok(Metronome {
+                requests: request_sender,
+                snapshots,
+                mixer: mixer_handle,
+                thread: Some(thread),
+            }),
+            Ok(Err(message)) => Err(format!("Failed to open audio output: {}", message).into()),
+            Err(_) => Err("Metronome thread exited during startup".into()),
+        }
+    }
+
+    // Renders the selected mode offline instead of playing it, see `Renderer`
+    pub fn renderer(mut self) -> Renderer {
+        let (state, sound_cache) = self.initial_state();
+        let mut mode = self.modes.swap_remove(state.mode);
+        mode.on_start();
+        let timeline = compile(mode.as_ref(), &state);
+        let clicks = mode.pattern().clicks;
+        Renderer::new(state, mode, timeline, clicks, self.kits, sound_cache)
+    }
+
+    fn initial_state(&mut self) -> (MetronomeSnapshot, HashMap<u32, Arc<[f32]>>) {
+        let mut sound_cache = builtin_sound_cache();
+        for (i, samples) in self.custom_sounds.drain(..).enumerate() {
+            sound_cache.insert(CUSTOM_SOUND_START + i as u32, samples.into());
+        }
+
+        let state = MetronomeSnapshot {
+            bpm: self.bpm,
+            volume: self.volume,
+            sound_type: self.sound.key(),
+            beats_per_bar: self.beats_per_bar,
+            count_in: self.count_in,
+            kit: self.kit.min(self.kits.len()),
+            ..MetronomeSnapshot::with_modes(&self.modes, self.selected_mode.min(self.modes.len() - 1))
+        };
+        (state, sound_cache)
+    }
+}
+
+/// A running metronome engine. Every change goes through commands to the
+/// engine thread; the state is read back from lock-free snapshots.
+pub struct Metronome {
+    pub(crate) requests: Sender<Request>,
+    pub(crate) snapshots: Arc<ArcSwap<MetronomeSnapshot>>,
+    mixer: MixerHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Metronome {
+    pub fn builder() -> MetronomeBuilder {
+        MetronomeBuilder::default()
+    }
+
+    pub fn send(&self, command: MetronomeCommand) {
+        let _ = self.requests.send(Request::Command(command));
+    }
+
+    pub fn start(&self) {
+        self.send(MetronomeCommand::Start);
+    }
+
+    pub fn stop(&self) {
+        self.send(MetronomeCommand::Stop);
+    }
+
+    // Held to MIN_BPM-MAX_BPM
+    pub fn set_tempo(&self, bpm: u32) {
+        self.send(MetronomeCommand::ChangeBpm(bpm));
+    }
+
+    // Every subscriber gets its own copy of each event from now on
+    pub fn subscribe(&self) -> Receiver<MetronomeEvent> {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let _ = self.requests.send(Request::Subscribe(Box::new(move |event| {
+            event_sender.send(event.clone()).is_ok()
+        })));
+        event_receiver
+    }
+
+    // Calls `callback` on the engine thread with every event until it returns
+    // false, so it must return quickly
+    pub fn on_event(&self, callback: impl FnMut(&MetronomeEvent) -> bool + Send + 'static) {
+        let _ = self.requests.send(Request::Subscribe(Box::new(callback)));
+    }
+
+    pub fn snapshot(&self) -> Arc<MetronomeSnapshot> {
+        self.snapshots.load_full()
+    }
+
+    // Clonable command handle and event stream for async code
+    #[cfg(feature = "async")]
+    pub fn handle(&self) -> crate::MetronomeHandle {
+        crate::MetronomeHandle::new(self.requests.clone(), self.snapshots.clone())
+    }
+
+    // Plays a sound through the metronome's output, e.g. to audition it
+    pub fn play(&self, samples: &Arc<[f32]>, gain: f32) {
+        self.mixer.play(samples, gain);
+    }
+}
+
+impl Drop for Metronome {
+    fn drop(&mut self) {
+        let _ = self.requests.send(Request::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn builtin_sound_cache() -> HashMap<u32, Arc<[f32]>> {
+    (0..=CELEBRATION_SOUND)
+        .filter_map(|key| Some((key, builtin_sound(key)?.into())))
+        .collect()
+}
+
+// Synthesizes a built-in sound at the level the engine plays it, None for custom keys
+pub fn builtin_sound(key: u32) -> Option<Vec<f32>> {
+    let mut sound_data = match key {
+        0 => create_beep_sound(),
+        1 => create_kick_sound(),
+        2 => create_click_sound(),
+        3 => create_cowbell_sound(),
+        4 => create_hihat_sound(),
+        5 => create_wood_block_sound(),
+        6 => create_triangle_sound(),
+        7 => create_square_sound(),
+        CELEBRATION_SOUND => create_celebration_sound(),
+        _ => return None,
+    };
+    // The celebration is a fanfare, not a click, so it keeps its own level
+    if key != CELEBRATION_SOUND {
+        match_loudness(&mut sound_data);
+    }
+    Some(sound_data)
+}
+
+// Clicks go to the sinks; the mixer is kept for the celebration sound
+struct Outputs {
+    mixer: MixerHandle,
+    sinks: Vec<Box<dyn BeatSink>>,
+}
+
+fn metronome_thread(
+    mut state: MetronomeSnapshot,
+    mut publisher: SnapshotPublisher<MetronomeSnapshot>,
+    mut modes: Vec<Box<dyn MetronomeModeBehavior>>,
+    outputs: Outputs,
+    mut sound_cache: HashMap<u32, Arc<[f32]>>,
+    kits: Vec<SoundKit>,
+    request_receiver: Receiver<Request>,
+) {
+    let Outputs { mixer, mut sinks } = outputs;
+    // Clicks are handed out this far ahead, for the slowest sink
+    let lead = sinks.iter().map(|sink| sink.latency()).max().unwrap_or_default();
+    let count_in_label: Arc<str> = "Count-in".into();
+    let mut subscribers: Vec<Subscriber> = Vec::new();
+    let mut applied: Vec<Box<dyn FnOnce() + Send>> = Vec::new();
+    let mut last_tick = Instant::now();
+    let mut count_in_remaining = 0u32;
+    // The mode's timeline while running, and the instant its time zero falls on
+    let mut timeline: Option<Timeline> = None;
+    let mut timeline_start = Instant::now();
+    // None while stopped: the thread then sleeps until the next command
+    let mut wake_at: Option<Instant> = None;
+    // Settings follow commands and the panel follows the mode; both are only
+    // rebuilt when they changed, so a plain beat publishes just the counters
+    let mut settings_changed = false;
+    let mut panel_changed = false;
+
+    // The engine is the only owner of the state; everyone else sees published snapshots
+    loop {
+        let mode = &modes[state.mode];
+        if settings_changed {
+            state.settings = mode.settings().into();
+        }
+        if settings_changed || panel_changed {
+            state.panel = Arc::new(mode.panel());
+        }
+        settings_changed = false;
+        panel_changed = false;
+        publisher.publish(&state);
+        for callback in applied.drain(..) {
+            callback();
+        }
+
+        // Sleep until a request arrives or the next timer deadline is due
+        let first_request = match wake_at {
+            Some(deadline) => {
+                match request_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(request) => Some(request),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            },
+            None => match request_receiver.recv() {
+                Ok(request) => Some(request),
+                Err(_) => return,
+            },
+        };
+        wake_at = None;
+
+        // Mode and setting changes while playing recompile the timeline at the current position
+        let mut recompile = false;
+
+        for request in first_request.into_iter().chain(request_receiver.try_iter()) {
+            let command = match request {
+                Request::Command(command) => command,
+                Request::Subscribe(subscriber) => {
+                    subscribers.push(subscriber);
+                    continue;
+                },
+                Request::Applied(callback) => {
+                    applied.push(callback);
+                    continue;
+                },
+                Request::Shutdown => return,
+            };
+            settings_changed = true;
+            match command {
+                MetronomeCommand::Start => {
+                    state.is_running = true;
+                    state.tick_count = 0;
+                    last_tick = Instant::now();
+                    count_in_remaining = if state.count_in {
+                        state.beats_per_bar
+                    } else {
+                        0
+                    };
+
+                    let mode = &mut modes[state.mode];
+                    mode.on_start();
+                    timeline = Some(compile(mode.as_ref(), &state));
+                    // The first click falls one beat after starting
+                    timeline_start = last_tick + tick_interval(state.bpm, 1.0);
+                    emit(&mut subscribers, MetronomeEvent::Started { mode: state.mode, bpm: state.bpm });
+                },
+                MetronomeCommand::Stop => {
+                    if state.is_running {
+                        sinks.iter_mut().for_each(|sink| sink.stop());
+                        emit(&mut subscribers, MetronomeEvent::Stopped);
+                    }
+                    state.is_running = false;
+                    timeline = None;
+                },
+                MetronomeCommand::ChangeBpm(bpm) => {
+                    state.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+                    if let Some(timeline) = timeline.as_mut() {
+                        timeline.set_user_tempo(state.bpm);
+                    }
+                },
+                MetronomeCommand::ChangeVolume(volume) => {
+                    state.volume = volume.min(100);
+                },
+                MetronomeCommand::ChangeSoundType(sound_type) => {
+                    state.sound_type = sound_type;
+                },
+                MetronomeCommand::ChangeMode(mode) => {
+                    if mode < modes.len() {
+                        state.mode = mode;
+                        recompile = true;
+                        emit(&mut subscribers, MetronomeEvent::ModeChanged { mode });
+                    }
+                },
+                MetronomeCommand::ChangeKit(kit) => {
+                    state.kit = kit.min(kits.len());
+                },
+                MetronomeCommand::ChangeMeter(beats_per_bar) => {
+                    state.beats_per_bar = beats_per_bar.max(1);
+                    recompile = true;
+                },
+                MetronomeCommand::SetCountIn(enabled) => {
+                    state.count_in = enabled;
+                },
+                MetronomeCommand::AddCustomSound { key, samples } => {
+                    sound_cache.insert(key, samples.into());
+                },
+                MetronomeCommand::UpdateModeSetting { key, value } => {
+                    modes[state.mode].apply_setting(key, value);
+                    recompile = true;
+                },
+                MetronomeCommand::LoadConfig { config, sound_type } => {
+                    state.bpm = config.bpm.clamp(MIN_BPM, MAX_BPM);
+                    if let Some(timeline) = timeline.as_mut() {
+                        timeline.set_user_tempo(state.bpm);
+                    }
+                    state.volume = config.volume.min(100);
+                    if let Some(sound_type) = sound_type {
+                        state.sound_type = sound_type;
+                    }
+                    state.kit = kits.iter().position(|kit| kit.name == config.kit).map_or(0, |i| i + 1);
+                    state.beats_per_bar = config.beats_per_bar.max(1);
+                    state.count_in = config.count_in;
+                    for mode in modes.iter_mut() {
+                        for (key, value) in config.modes.get(mode.name()).into_iter().flatten() {
+                            mode.apply_setting(key, value.clone());
+                        }
+                    }
+                    if let Some(mode) = modes.iter().position(|mode| mode.name() == config.mode)
+                        && mode != state.mode
+                    {
+                        state.mode = mode;
+                        emit(&mut subscribers, MetronomeEvent::ModeChanged { mode });
+                    }
+                    recompile = true;
+                },
+                MetronomeCommand::Reset => {
+                    state.tick_count = 0;
+                },
+            }
+        }
+
+        if recompile && let Some(previous) = timeline.as_ref() {
+            let mut recompiled = compile(modes[state.mode].as_ref(), &state);
+            recompiled.continue_from(previous);
+            timeline = Some(recompiled);
+        }
+
+        if let Some(active) = timeline.as_mut() {
+            let mode = &mut modes[state.mode];
+            // The mode shapes a bar as soon as its first click is the next one, so a slow
+            // on_bar runs a click ahead instead of holding up the downbeat
+            panel_changed |= prepare_bar(mode.as_mut(), active, &mut subscribers);
+
+            // Count-in clicks on plain quarter notes before the timeline starts
+            if count_in_remaining > 0 {
+                let beat_interval = tick_interval(state.bpm, 1.0);
+                let due = last_tick + beat_interval;
+                if due <= Instant::now() + lead {
+                    let event = TimelineEvent {
+                        time: 0.0,
+                        voice: VoiceRole::CountIn,
+                        velocity: 1.0,
+                        bar: 0,
+                        beat: state.beats_per_bar - count_in_remaining,
+                        click: 0,
+                        section: Arc::clone(&count_in_label),
+                        bpm: state.bpm,
+                    };
+                    count_in_remaining -= 1;
+                    state.last_beat = Instant::now();
+
+                    let (sound_type, volume) = select_voice(&state, &kits, VoiceRole::CountIn, 1.0);
+                    dispatch(&mut sinks, &event, due, sound_cache.get(&sound_type), volume);
+                    last_tick = due;
+                    timeline_start = last_tick + beat_interval;
+                }
+                wake_at = Some(ahead(last_tick + beat_interval, lead));
+                continue;
+            }
+
+            let refresh = mode.on_tick(Instant::now().saturating_duration_since(timeline_start));
+            panel_changed |= refresh.is_some();
+
+            let due = timeline_start + active.next_due();
+            if due <= Instant::now() + lead {
+                let sections_entered = active.sections_entered();
+                let Some(event) = active.next() else {
+                    state.is_running = false;
+                    timeline = None;
+                    sinks.iter_mut().for_each(|sink| sink.stop());
+
+                    // Play celebration sound
+                    let volume = state.volume as f32 / 100.0;
+                    if let Some(celebration_sound) = sound_cache.get(&CELEBRATION_SOUND) {
+                        mixer.play(celebration_sound, volume * 1.5); // Louder for celebration
+                    }
+
+                    let countdown = mode.is_countdown();
+                    emit(&mut subscribers, MetronomeEvent::Finished { mode: state.mode, bpm: state.bpm, countdown });
+                    continue;
+                };
+
+                panel_changed |= mode.on_event(&event);
+                // The tempo goes first, so a section that changes it is announced at its own
+                if event.bpm != state.bpm {
+                    state.bpm = event.bpm;
+                    let random = active.random_tempo();
+                    emit(&mut subscribers, MetronomeEvent::BpmChanged { bpm: event.bpm, random });
+                }
+                if active.sections_entered() != sections_entered {
+                    let section = Arc::clone(&event.section);
+                    emit(&mut subscribers, MetronomeEvent::SectionChanged { section });
+                }
+                state.interval = active.step() as f32;
+                state.tick_count += 1;
+                state.last_beat = Instant::now();
+
+                // Muted bars still count and reach sinks and subscribers
+                let (sound_type, final_volume) = select_voice(&state, &kits, event.voice, event.velocity);
+                let samples = sound_cache.get(&sound_type).filter(|_| event.velocity > 0.0);
+                dispatch(&mut sinks, &event, due, samples, final_volume);
+
+                if event.beat == 0 && event.click == 0 {
+                    emit(&mut subscribers, MetronomeEvent::Bar { bar: event.bar, bpm: event.bpm, at: due });
+                }
+                emit(&mut subscribers, MetronomeEvent::Beat {
+                    tick_count: state.tick_count,
+                    event,
+                    at: due,
+                });
+                panel_changed |= prepare_bar(mode.as_mut(), active, &mut subscribers);
+            }
+
+            let mut next_wake = ahead(timeline_start + active.next_due(), lead);
+            if let Some(refresh) = refresh {
+                next_wake = next_wake.min(Instant::now() + refresh);
+            }
+            wake_at = Some(next_wake);
+        }
+    }
+}
+
+// Modes that leave the bar length open follow the metronome's meter
+pub(crate) fn compile(mode: &dyn MetronomeModeBehavior, state: &MetronomeSnapshot) -> Timeline {
+    let mut pattern = mode.pattern();
+    pattern.beats_per_bar.get_or_insert(state.beats_per_bar);
+    Timeline::new(pattern, state.bpm)
+}
+
+// Hands a click to every sink, each early by its own latency
+fn dispatch(
+    sinks: &mut [Box<dyn BeatSink>],
+    event: &TimelineEvent,
+    due: Instant,
+    samples: Option<&Arc<[f32]>>,
+    volume: f32,
+) {
+    for sink in sinks.iter_mut() {
+        let at = ahead(due, sink.latency());
+        sink.schedule(ScheduledBeat { event: event.clone(), at, samples: samples.cloned(), volume });
+    }
+}
+
+fn ahead(instant: Instant, by: Duration) -> Instant {
+    instant.checked_sub(by).unwrap_or(instant)
+}
+
+// Lets the mode reshape the bar about to start, if one is and the mode was not asked yet
+pub(crate) fn start_bar(mode: &mut dyn MetronomeModeBehavior, timeline: &mut Timeline) -> Result<(), String> {
+    let Some((bar, bpm)) = timeline.take_upcoming_bar() else { return Ok(()) };
+    match mode.on_bar(bar, bpm) {
+        Ok(Some(change)) => timeline.apply_bar(&change),
+        Ok(None) => {},
+        Err(error) => return Err(format!("{}: {}", mode.name(), error)),
+    }
+    Ok(())
+}
+
+// Reports a failed on_bar to the subscribers; true when the mode's panel shows the error
+fn prepare_bar(mode: &mut dyn MetronomeModeBehavior, timeline: &mut Timeline, subscribers: &mut Vec<Subscriber>) -> bool {
+    match start_bar(mode, timeline) {
+        Ok(()) => false,
+        Err(message) => {
+            emit(subscribers, MetronomeEvent::Error { message });
+            true
+        },
+    }
+}
+
+// Sends to every subscriber, forgetting the ones that hung up
+fn emit(subscribers: &mut Vec<Subscriber>, event: MetronomeEvent) {
+    subscribers.retain_mut(|subscriber| subscriber(&event));
+}
+
+// `interval` is measured in beats at the given tempo
+fn tick_interval(bpm: u32, interval: f32) -> Duration {
+    Duration::from_secs_f32(60.0 * interval / bpm.max(1) as f32)
+}
+
+// Picks the sound cache key and volume for a click. Without a kit every role
+// shares the selected sound, with accents played louder instead.
+pub(crate) fn select_voice(
+    state: &MetronomeSnapshot,
+    kits: &[SoundKit],
+    role: VoiceRole,
+    velocity: f32,
+) -> (u32, f32) {
+    let volume = state.volume as f32 / 100.0;
+    let kit_index = state.kit;
+
+    if let Some(kit) = kit_index.checked_sub(1).and_then(|i| kits.get(i)) {
+        return (kit.sound_for(role), volume);
+    }
+
+    let mut sound_type = state.sound_type;
+    // The second rhythm takes the next built-in sound; a custom sound has no
+    // neighbour that goes with it, so it plays both
+    if role == VoiceRole::PolyVoice && sound_type < CELEBRATION_SOUND {
+        sound_type = (sound_type + 1) % CELEBRATION_SOUND;
+    }
+    (sound_type, (volume * velocity).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::modes::StandardMode;
+
+    // Hands every click to the test instead of playing it
+    struct RecordingSink(Sender<ScheduledBeat>);
+
+    impl BeatSink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn schedule(&mut self, beat: ScheduledBeat) {
+            let _ = self.0.send(beat);
+        }
+    }
+
+    fn state(sound_type: u32, kit: usize) -> MetronomeSnapshot {
+        MetronomeSnapshot { sound_type, kit, volume: 50, ..MetronomeSnapshot::default() }
+    }
+
+    #[test]
+    fn kits_pick_the_sound_for_each_role() {
+        let kits = builtin_kits();
+        // Drum Kit: kick on the downbeat, hi-hat on the beat
+        let drums = state(0, 2);
+        assert_eq!(select_voice(&drums, &kits, VoiceRole::Downbeat, 1.0), (1, 0.5));
+        assert_eq!(select_voice(&drums, &kits, VoiceRole::Beat, 1.0), (4, 0.5));
+        // A kit shows accents by sound, not by level
+        assert_eq!(select_voice(&drums, &kits, VoiceRole::Accent, 1.5), (3, 0.5));
+    }
+
+    #[test]
+    fn without_a_kit_accents_play_louder() {
+        let kits = builtin_kits();
+        assert_eq!(select_voice(&state(2, 0), &kits, VoiceRole::Accent, 1.5), (2, 0.75));
+        assert_eq!(select_voice(&MetronomeSnapshot { volume: 80, ..state(2, 0) }, &kits, VoiceRole::Accent, 1.5), (2, 1.0));
+        assert_eq!(select_voice(&state(2, 0), &kits, VoiceRole::Subdivision, 1.0), (2, 0.5));
+    }
+
+    #[test]
+    fn poly_voice_takes_the_next_builtin_sound() {
+        let kits = builtin_kits();
+        assert_eq!(select_voice(&state(Sound::Kick.key(), 0), &kits, VoiceRole::PolyVoice, 1.0).0, Sound::Click.key());
+        // The last built-in sound wraps around rather than reaching the celebration
+        assert_eq!(select_voice(&state(Sound::Square.key(), 0), &kits, VoiceRole::PolyVoice, 1.0).0, Sound::Beep.key());
+        let custom = Sound::Custom(0).key();
+        assert_eq!(select_voice(&state(custom, 0), &kits, VoiceRole::PolyVoice, 1.0).0, custom);
+    }
+
+    #[test]
+    fn count_in_plays_a_bar_before_bar_0() {
+        let modes: Vec<Box<dyn MetronomeModeBehavior>> = vec![Box::new(StandardMode)];
+        let state = MetronomeSnapshot {
+            bpm: 300,
+            beats_per_bar: 2,
+            count_in: true,
+            ..MetronomeSnapshot::with_modes(&modes, 0)
+        };
+        let (publisher, _) = SnapshotPublisher::new(state.clone());
+        let (beat_sender, beats) = mpsc::channel();
+        let (mixer_handle, _mixer) = mixer();
+        let outputs = Outputs { mixer: mixer_handle, sinks: vec![Box::new(RecordingSink(beat_sender))] };
+        let (requests, request_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            metronome_thread(state, publisher, modes, outputs, builtin_sound_cache(), builtin_kits(), request_receiver)
+        });
+
+        requests.send(Request::Command(MetronomeCommand::Start)).unwrap();
+        let clicks: Vec<ScheduledBeat> = (0..4).map(|_| beats.recv_timeout(Duration::from_secs(2)).unwrap()).collect();
+        requests.send(Request::Shutdown).unwrap();
+        thread.join().unwrap();
+
+        let played: Vec<(VoiceRole, u32, u32)> =
+            clicks.iter().map(|click| (click.event.voice, click.event.bar, click.event.beat)).collect();
+        assert_eq!(
+            played,
+            [(VoiceRole::CountIn, 0, 0), (VoiceRole::CountIn, 0, 1), (VoiceRole::Downbeat, 0, 0), (VoiceRole::Beat, 0, 1)]
+        );
+        // The count-in keeps the beat going into the first bar
+        for pair in clicks.windows(2) {
+            let gap = pair[1].at - pair[0].at;
+            assert!(gap.abs_diff(Duration::from_millis(200)) < Duration::from_millis(1), "{:?}", gap);
+        }
+    }
+
+    #[test]
+    fn tempo_and_volume_commands_stay_in_range() {
+        let modes: Vec<Box<dyn MetronomeModeBehavior>> = vec![Box::new(StandardMode)];
+        let state = MetronomeSnapshot::with_modes(&modes, 0);
+        let (publisher, snapshots) = SnapshotPublisher::new(state.clone());
+        let (mixer_handle, _mixer) = mixer();
+        let outputs = Outputs { mixer: mixer_handle, sinks: Vec::new() };
+        let (requests, request_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            metronome_thread(state, publisher, modes, outputs, builtin_sound_cache(), builtin_kits(), request_receiver)
+        });
+
+        let (applied_sender, applied) = mpsc::channel();
+        let published = |command| {
+            requests.send(Request::Command(command)).unwrap();
+            let applied_sender = applied_sender.clone();
+            requests.send(Request::Applied(Box::new(move || applied_sender.send(()).unwrap()))).unwrap();
+            applied.recv_timeout(Duration::from_secs(2)).unwrap();
+            snapshots.load_full()
+        };
+        assert_eq!(published(MetronomeCommand::ChangeBpm(1000)).bpm, MAX_BPM);
+        assert_eq!(published(MetronomeCommand::ChangeBpm(0)).bpm, MIN_BPM);
+        assert_eq!(published(MetronomeCommand::ChangeBpm(96)).bpm, 96);
+        assert_eq!(published(MetronomeCommand::ChangeVolume(250)).volume, 100);
+        assert_eq!(published(MetronomeCommand::ChangeVolume(40)).volume, 40);
+
+        requests.send(Request::Shutdown).unwrap();
+        thread.join().unwrap();
+    }
+}
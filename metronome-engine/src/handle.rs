@@ -0,0 +1,62 @@
+use std::sync::{Arc, mpsc::Sender};
+
+use arc_swap::ArcSwap;
+use futures::channel::{mpsc, oneshot};
+
+use crate::engine::Request;
+use crate::{MetronomeCommand, MetronomeEvent, MetronomeSnapshot};
+
+pub type EventStream = mpsc::UnboundedReceiver<MetronomeEvent>;
+
+/// An async view of a running [`Metronome`](crate::Metronome), from
+/// [`Metronome::handle`](crate::Metronome::handle). It can be cloned and moved
+/// into tasks on any executor; nothing here blocks. Once the metronome is
+/// dropped, commands are ignored and streams end.
+#[derive(Clone)]
+pub struct MetronomeHandle {
+    requests: Sender<Request>,
+    snapshots: Arc<ArcSwap<MetronomeSnapshot>>,
+}
+
+impl MetronomeHandle {
+    pub(crate) fn new(requests: Sender<Request>, snapshots: Arc<ArcSwap<MetronomeSnapshot>>) -> Self {
+        Self { requests, snapshots }
+    }
+
+    // Resolves once the engine has applied the command, with the state it published
+    pub async fn send(&self, command: MetronomeCommand) -> Arc<MetronomeSnapshot> {
+        let (applied_sender, applied) = oneshot::channel();
+        let _ = self.requests.send(Request::Command(command));
+        let _ = self.requests.send(Request::Applied(Box::new(move || {
+            let _ = applied_sender.send(());
+        })));
+        let _ = applied.await;
+        self.snapshot()
+    }
+
+    pub async fn start(&self) -> Arc<MetronomeSnapshot> {
+        self.send(MetronomeCommand::Start).await
+    }
+
+    pub async fn stop(&self) -> Arc<MetronomeSnapshot> {
+        self.send(MetronomeCommand::Stop).await
+    }
+
+    // Held to MIN_BPM-MAX_BPM
+    pub async fn set_tempo(&self, bpm: u32) -> Arc<MetronomeSnapshot> {
+        self.send(MetronomeCommand::ChangeBpm(bpm)).await
+    }
+
+    // A `Stream` of every event from now on; dropping it unsubscribes
+    pub fn events(&self) -> EventStream {
+        let (event_sender, event_receiver) = mpsc::unbounded();
+        let _ = self.requests.send(Request::Subscribe(Box::new(move |event| {
+            event_sender.unbounded_send(event.clone()).is_ok()
+        })));
+        event_receiver
+    }
+
+    pub fn snapshot(&self) -> Arc<MetronomeSnapshot> {
+        self.snapshots.load_full()
+    }
+}
@@ -0,0 +1,34 @@
+//! The metronome engine behind the desktop apps, for embedding in other Rust tools.
+//!
+//! ```no_run
+//! use metronome_engine::{Metronome, MetronomeEvent, Sound};
+//!
+//! let metronome = Metronome::builder()
+//!     .bpm(96)
+//!     .meter(3)
+//!     .sound(Sound::Woodblock)
+//!     .build()?;
+//! let events = metronome.subscribe();
+//! metronome.start();
+//!
+//! for event in events {
+//!     if let MetronomeEvent::Beat { event, .. } = event {
+//!         println!("bar {} beat {}", event.bar + 1, event.beat + 1);
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod utilities;
+mod engine;
+mod render;
+#[cfg(feature = "async")]
+mod handle;
+
+pub use engine::{
+    CELEBRATION_SOUND, CUSTOM_SOUND_START, MAX_BPM, MIN_BPM, Metronome, MetronomeBuilder,
+    MetronomeCommand, MetronomeEvent, MetronomeSnapshot, Sound, builtin_sound,
+};
+pub use render::{Renderer, Transport};
+#[cfg(feature = "async")]
+pub use handle::{EventStream, MetronomeHandle};
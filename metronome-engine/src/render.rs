@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::engine::{MAX_BPM, MIN_BPM, MetronomeSnapshot, select_voice, start_bar};
+use crate::utilities::kit::SoundKit;
+use crate::utilities::mode::MetronomeModeBehavior;
+use crate::utilities::mixer::{Mixer, SAMPLE_RATE, mixer};
+use crate::utilities::timeline::{ACCENT_VELOCITY, Clicks, Timeline, TimelineEvent, click_voice};
+
+/// Where a host's playhead is at the start of a buffer. Positions are in
+/// quarter notes, the way plugin hosts report them.
+#[derive(Clone, Copy, Debug)]
+pub struct Transport {
+    pub playing: bool,
+    pub tempo: f64,
+    pub position: f64,
+    // Start of the bar the playhead is in, and its number counting from 0
+    pub bar_start: f64,
+    pub bar_number: i32,
+    pub beats_per_bar: u32,
+    // Note value of one beat: 4 for quarter notes, 8 for eighths
+    pub beat_unit: u32,
+}
+
+impl Transport {
+    fn bar_length(&self) -> f64 {
+        self.beats_per_bar.max(1) as f64 * self.beat_length()
+    }
+
+    fn beat_length(&self) -> f64 {
+        4.0 / self.beat_unit.max(1) as f64
+    }
+
+    // The same transport `frames` later, assuming the tempo holds
+    pub fn advance(&self, frames: usize, sample_rate: u32) -> Transport {
+        let mut transport = *self;
+        if transport.playing {
+            transport.position += frames as f64 * transport.tempo / 60.0 / sample_rate.max(1) as f64;
+            while transport.position >= transport.bar_start + transport.bar_length() {
+                transport.bar_start += transport.bar_length();
+                transport.bar_number += 1;
+            }
+        }
+        transport
+    }
+}
+
+/// Renders clicks into buffers without opening an audio device, mixed and
+/// limited exactly like live playback. `render` plays the selected mode from
+/// its start, e.g. to bounce a click track; `render_transport` follows a host's
+/// playhead instead. Output is mono, at `SAMPLE_RATE` unless changed.
+pub struct Renderer {
+    state: MetronomeSnapshot,
+    mode: Box<dyn MetronomeModeBehavior>,
+    kits: Vec<SoundKit>,
+    // Sounds as synthesized, and resampled to the output rate
+    sources: HashMap<u32, Arc<[f32]>>,
+    sound_cache: HashMap<u32, Arc<[f32]>>,
+    sample_rate: u32,
+    timeline: Timeline,
+    // What `render_transport` plays on each beat
+    clicks: Clicks,
+    transport_label: Arc<str>,
+    mixer: Mixer,
+    // Frames rendered so far
+    position: u64,
+    finished: bool,
+    // `render` ends before the first click of this bar, when set
+    bars: Option<u32>,
+    // The mode's last error, until taken
+    error: Option<String>,
+}
+
+impl Renderer {
+    pub(crate) fn new(
+        state: MetronomeSnapshot,
+        mode: Box<dyn MetronomeModeBehavior>,
+        timeline: Timeline,
+        clicks: Clicks,
+        kits: Vec<SoundKit>,
+        sound_cache: HashMap<u32, Arc<[f32]>>,
+    ) -> Self {
+        // Voices are started directly, so the mixer's trigger handle is not needed
+        let (_, mixer) = mixer();
+        Self {
+            state,
+            mode,
+            kits,
+            sources: sound_cache.clone(),
+            sound_cache,
+            sample_rate: SAMPLE_RATE,
+            timeline,
+            clicks,
+            transport_label: "Transport".into(),
+            mixer,
+            position: 0,
+            finished: false,
+            bars: None,
+            error: None,
+        }
+    }
+
+    // Fills `output` and calls `on_beat` with every click and its frame within
+    // `output`. Returns how many frames were rendered: fewer than asked once the
+    // mode has ended and its last click has rung out, then 0 from there on.
+    pub fn render(&mut self, output: &mut [f32], mut on_beat: impl FnMut(&TimelineEvent, usize)) -> usize {
+        for frame in 0..output.len() {
+            while !self.finished && self.next_frame() <= self.position {
+                if let Err(message) = start_bar(self.mode.as_mut(), &mut self.timeline) {
+                    self.error = Some(message);
+                }
+                match self.timeline.next() {
+                    Some(event) if self.bars.is_some_and(|bars| event.bar >= bars) => self.finished = true,
+                    Some(event) => {
+                        self.mode.on_event(&event);
+                        self.play(&event);
+                        on_beat(&event, frame);
+                    },
+                    None => self.finished = true,
+                }
+            }
+
+            if self.finished && self.mixer.is_silent() {
+                output[frame..].fill(0.0);
+                return frame;
+            }
+
+            output[frame] = self.mixer.next().unwrap_or(0.0);
+            self.position += 1;
+        }
+        output.len()
+    }
+
+    // Fills `output` with the clicks that fall between the transport's position
+    // and the end of the buffer, each on the exact frame it is due. Sounds
+    // already playing ring out while the transport is stopped.
+    pub fn render_transport(
+        &mut self,
+        output: &mut [f32],
+        transport: &Transport,
+        mut on_beat: impl FnMut(&TimelineEvent, usize),
+    ) {
+        let clicks_per_beat = match &self.clicks {
+            Clicks::Subdivided(accents) => accents.len().max(1) as i64,
+            _ => 1,
+        };
+        let beats_per_bar = transport.beats_per_bar.max(1) as i64;
+        let step = transport.beat_length() / clicks_per_beat as f64;
+        let per_frame = transport.tempo.max(0.0) / 60.0 / self.sample_rate as f64;
+        let playing = transport.playing && per_frame > 0.0;
+
+        // Grid points are counted from the bar start, so a relocated playhead lands on the right beat.
+        // A click less than half a frame behind the playhead was not played by the previous buffer.
+        let behind = 0.5 * per_frame / step;
+        let mut next_click = ((transport.position - transport.bar_start) / step - behind).ceil().max(0.0) as i64;
+
+        // Each click goes on the frame nearest to it, whichever buffer that frame falls in
+        let due_frame = |click: i64| ((transport.bar_start + click as f64 * step - transport.position) / per_frame).round();
+
+        for (frame, sample) in output.iter_mut().enumerate() {
+            while playing && due_frame(next_click) <= frame as f64 {
+                let beats = next_click / clicks_per_beat;
+                let click = (next_click % clicks_per_beat) as u32;
+                let beat = (beats % beats_per_bar) as u32;
+                let (voice, accent) = click_voice(&self.clicks, beats_per_bar as u32, beat, click, false);
+
+                let event = TimelineEvent {
+                    time: (transport.bar_start + next_click as f64 * step) * 60.0 / transport.tempo,
+                    voice,
+                    velocity: if accent { ACCENT_VELOCITY } else { 1.0 },
+                    bar: (transport.bar_number as i64 + beats / beats_per_bar).max(0) as u32,
+                    beat,
+                    click,
+                    section: Arc::clone(&self.transport_label),
+                    bpm: transport.tempo.round() as u32,
+                };
+                self.play(&event);
+                on_beat(&event, frame);
+                next_click += 1;
+            }
+
+            *sample = self.mixer.next().unwrap_or(0.0);
+        }
+        self.position += output.len() as u64;
+    }
+
+    // Held to MIN_BPM-MAX_BPM like the live engine; a host transport's tempo is followed as it is
+    pub fn set_tempo(&mut self, bpm: u32) {
+        self.state.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        self.timeline.set_user_tempo(self.state.bpm);
+    }
+
+    // 0-100
+    pub fn set_volume(&mut self, volume: u32) {
+        self.state.volume = volume.min(100);
+    }
+
+    // Sound cache key, see `Sound::key`
+    pub fn set_sound(&mut self, sound_type: u32) {
+        self.state.sound_type = sound_type;
+    }
+
+    // 0 plays the selected sound for every beat, n selects the builder's kits[n - 1]
+    pub fn set_kit(&mut self, kit: usize) {
+        self.state.kit = kit.min(self.kits.len());
+    }
+
+    // What `render_transport` plays within each beat
+    pub fn set_clicks(&mut self, clicks: Clicks) {
+        self.clicks = clicks;
+    }
+
+    // Subdivided clicks with these accents, reusing the current pattern's
+    // storage so it does not allocate once the pattern has room for them
+    pub fn set_accents(&mut self, accents: &[bool]) {
+        match &mut self.clicks {
+            Clicks::Subdivided(pattern) => {
+                pattern.clear();
+                pattern.extend_from_slice(accents);
+            },
+            clicks => *clicks = Clicks::Subdivided(accents.to_vec()),
+        }
+    }
+
+    // Resamples every sound for the new output rate
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.max(1);
+        self.position = self.position * sample_rate as u64 / self.sample_rate as u64;
+        self.sample_rate = sample_rate;
+        self.sound_cache = self
+            .sources
+            .iter()
+            .map(|(&key, samples)| (key, resample(samples, SAMPLE_RATE, sample_rate)))
+            .collect();
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Ends `render` after this many bars of the mode, even one that would play on forever
+    pub fn set_bars(&mut self, bars: u32) {
+        self.bars = Some(bars);
+    }
+
+    // Frames rendered since the start
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // The mode's last error while rendering, see `MetronomeEvent::Error`
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    fn play(&mut self, event: &TimelineEvent) {
+        if event.velocity <= 0.0 {
+            return;
+        }
+        let (sound_type, volume) = select_voice(&self.state, &self.kits, event.voice, event.velocity);
+        if let Some(sound_data) = self.sound_cache.get(&sound_type) {
+            self.mixer.play_now(sound_data, volume);
+        }
+    }
+
+    fn next_frame(&self) -> u64 {
+        (self.timeline.next_time() * self.sample_rate as f64).round() as u64
+    }
+}
+
+// Linear interpolation is plenty for short percussive clicks
+fn resample(samples: &Arc<[f32]>, from: u32, to: u32) -> Arc<[f32]> {
+    if from == to || samples.is_empty() {
+        return Arc::clone(samples);
+    }
+    let ratio = from as f64 / to as f64;
+    let length = (samples.len() as f64 / ratio).ceil() as usize;
+    (0..length)
+        .map(|i| {
+            let source = i as f64 * ratio;
+            let index = source as usize;
+            let fraction = (source - index as f64) as f32;
+            let current = samples[index.min(samples.len() - 1)];
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            current + (next - current) * fraction
+        })
+        .collect()
+}
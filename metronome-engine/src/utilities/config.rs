@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::engine::MetronomeSnapshot;
+use crate::utilities::mode::SettingValue;
+use crate::utilities::profile::profile_dir;
+
+// Bump when a field is renamed or changes meaning, and add a step to `MIGRATIONS`
+pub const CONFIG_VERSION: u32 = 1;
+
+// MIGRATIONS[n] upgrades a version n file to version n + 1
+const MIGRATIONS: [fn(&mut Table); CONFIG_VERSION as usize] = [
+    // Files written before the version field was added
+    |_| {},
+];
+
+/// Settings every frontend restores on start: the last tempo, sound and mode,
+/// each mode's settings, the window of the egui app and the terminal app's
+/// keys. Sounds, kits and modes are saved by name, since their order differs
+/// between frontends.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub bpm: u32,
+    pub sound: String,
+    pub volume: u32,
+    pub mode: String,
+    pub beats_per_bar: u32,
+    pub count_in: bool,
+    // Empty for no kit
+    pub kit: String,
+    // Settings by mode name, then by setting key
+    pub modes: BTreeMap<String, BTreeMap<String, SettingValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowGeometry>,
+    // Key bindings of the terminal app by action name, e.g. `start_stop = ["space", "enter"]`;
+    // actions left out keep their default keys
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            bpm: 120,
+            sound: "Kick".to_string(),
+            volume: 80,
+            mode: "Standard".to_string(),
+            beats_per_bar: 4,
+            count_in: false,
+            kit: String::new(),
+            modes: BTreeMap::new(),
+            window: None,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn mode_setting(&self, mode: &str, key: &str) -> Option<&SettingValue> {
+        self.modes.get(mode)?.get(key)
+    }
+
+    pub fn set_mode_setting(&mut self, mode: &str, key: &str, value: SettingValue) {
+        self.modes.entry(mode.to_string()).or_default().insert(key.to_string(), value);
+    }
+
+    // Records the engine's state; settings of the other modes are kept from before
+    pub fn capture(&mut self, state: &MetronomeSnapshot, sound: &str, kit: &str) {
+        self.bpm = state.bpm;
+        self.volume = state.volume;
+        self.sound = sound.to_string();
+        self.kit = kit.to_string();
+        self.beats_per_bar = state.beats_per_bar;
+        self.count_in = state.count_in;
+        if let Some((_, mode)) = state.modes.get(state.mode) {
+            self.mode = mode.to_string();
+            for setting in state.settings.iter() {
+                self.set_mode_setting(mode, setting.key, setting.value.clone());
+            }
+        }
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    profile_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Reads the config, upgrading files written by older versions. A missing
+/// file gives the defaults. A file that cannot be read is copied to
+/// `config.toml.bak` before the error is returned, so saving over it loses nothing.
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+
+    parse_config(&contents).map_err(|message| {
+        let backup = path.with_extension("toml.bak");
+        match std::fs::copy(path, &backup) {
+            Ok(_) => format!("{}: {} (kept a copy in {})", path.display(), message, backup.display()),
+            Err(_) => format!("{}: {}", path.display(), message),
+        }
+    })
+}
+
+/// Reads a config file from elsewhere, such as a preset, as it is: nothing is
+/// written beside it, and a missing file is an error.
+pub fn read_config(path: &Path) -> Result<Config, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_config(&contents).map_err(|message| format!("{}: {}", path.display(), message))
+}
+
+/// The active profile's config, falling back to the defaults when it cannot be
+/// read; the message says why, for the frontend to show.
+pub fn load_config_or_default() -> (Config, Option<String>) {
+    match config_path().map(|path| load_config(&path)) {
+        Some(Ok(config)) => (config, None),
+        Some(Err(message)) => {
+            (Config::default(), Some(format!("Failed to load settings, starting from defaults: {}", message)))
+        },
+        None => (Config::default(), None),
+    }
+}
+
+fn parse_config(contents: &str) -> Result<Config, String> {
+    let mut table: Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+    let version = table.get("version").and_then(Value::as_integer).unwrap_or(0);
+    if version > CONFIG_VERSION as i64 {
+        return Err(format!("written by a newer version (schema {}, this one reads up to {})", version, CONFIG_VERSION));
+    }
+
+    for migrate in &MIGRATIONS[version.max(0) as usize..] {
+        migrate(&mut table);
+    }
+    table.insert("version".to_string(), Value::Integer(CONFIG_VERSION as i64));
+    table.try_into().map_err(|e: toml::de::Error| e.to_string())
+}
+
+// Written to a temporary file first, so a crash mid-save leaves the old config intact
+pub fn save_config(path: &Path, config: &Config) -> Result<(), String> {
+    let contents = toml::to_string(config).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let temporary = path.with_extension("toml.tmp");
+    std::fs::write(&temporary, contents).map_err(|e| format!("{}: {}", temporary.display(), e))?;
+    std::fs::rename(&temporary, path).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{Metronome, MetronomeEvent};
+use crate::utilities::profile::profile_dir;
+
+// Tempo time is kept in ranges this many BPM wide, keyed by their lowest tempo
+pub const TEMPO_RANGE_WIDTH: u32 = 20;
+
+/// One practice session, from start to stop, as a line of `history.jsonl`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_seconds: f64,
+    // In the order they were first used
+    pub modes: Vec<String>,
+    pub min_bpm: u32,
+    pub max_bpm: u32,
+    pub ticks: u32,
+    pub countdowns_completed: u32,
+    // Seconds played per tempo range
+    #[serde(default)]
+    pub tempo_seconds: BTreeMap<u32, f64>,
+}
+
+struct Session {
+    start: DateTime<Utc>,
+    started_at: Instant,
+    modes: Vec<String>,
+    min_bpm: u32,
+    max_bpm: u32,
+    ticks: u32,
+    countdowns_completed: u32,
+    tempo_seconds: BTreeMap<u32, f64>,
+    last_beat: Option<(Instant, u32)>,
+}
+
+/// Builds a session record from what a frontend plays. Every frontend feeds
+/// it the same way, whether it runs the engine or a timing loop of its own.
+#[derive(Default)]
+pub struct SessionRecorder {
+    session: Option<Session>,
+}
+
+impl SessionRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.session.is_some()
+    }
+
+    // Ends any session still open, which is then lost; call finish first to keep it
+    pub fn start(&mut self, mode: &str) {
+        self.session = Some(Session {
+            start: Utc::now(),
+            started_at: Instant::now(),
+            modes: vec![mode.to_string()],
+            min_bpm: u32::MAX,
+            max_bpm: 0,
+            ticks: 0,
+            countdowns_completed: 0,
+            tempo_seconds: BTreeMap::new(),
+            last_beat: None,
+        });
+    }
+
+    pub fn mode(&mut self, mode: &str) {
+        if let Some(session) = self.session.as_mut()
+            && !session.modes.iter().any(|used| used == mode)
+        {
+            session.modes.push(mode.to_string());
+        }
+    }
+
+    // The time since the previous beat counts towards that beat's tempo
+    pub fn beat(&mut self, bpm: u32, at: Instant) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Some((last_at, last_bpm)) = session.last_beat {
+            let seconds = at.saturating_duration_since(last_at).as_secs_f64();
+            *session.tempo_seconds.entry(tempo_range(last_bpm)).or_default() += seconds;
+        }
+        session.min_bpm = session.min_bpm.min(bpm);
+        session.max_bpm = session.max_bpm.max(bpm);
+        session.ticks += 1;
+        session.last_beat = Some((at, bpm));
+    }
+
+    pub fn countdown_finished(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.countdowns_completed += 1;
+        }
+    }
+
+    // None when nothing was played, so a quick start and stop leaves no record
+    pub fn finish(&mut self) -> Option<SessionRecord> {
+        let mut session = self.session.take()?;
+        let (last_at, last_bpm) = session.last_beat?;
+        // The last beat lasts until the stop, but no longer than a beat
+        let beat = Duration::from_secs_f64(60.0 / last_bpm.max(1) as f64);
+        let tail = last_at.elapsed().min(beat).as_secs_f64();
+        *session.tempo_seconds.entry(tempo_range(last_bpm)).or_default() += tail;
+
+        Some(SessionRecord {
+            start: session.start,
+            end: Utc::now(),
+            duration_seconds: session.started_at.elapsed().as_secs_f64(),
+            modes: session.modes,
+            min_bpm: session.min_bpm,
+            max_bpm: session.max_bpm,
+            ticks: session.ticks,
+            countdowns_completed: session.countdowns_completed,
+            tempo_seconds: session.tempo_seconds,
+        })
+    }
+}
+
+fn tempo_range(bpm: u32) -> u32 {
+    bpm / TEMPO_RANGE_WIDTH * TEMPO_RANGE_WIDTH
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    profile_dir().map(|dir| dir.join("history.jsonl"))
+}
+
+pub fn append_session(path: &Path, record: &SessionRecord) -> Result<(), String> {
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Reads every session in the history, oldest first. Lines that cannot be
+/// read, e.g. one cut short by a crash, are reported and skipped.
+pub fn load_history(path: &Path) -> (Vec<SessionRecord>, Vec<String>) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Vec::new(), Vec::new()),
+        Err(e) => return (Vec::new(), vec![format!("{}: {}", path.display(), e)]),
+    };
+
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for (number, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) => errors.push(format!("{}:{}: {}", path.display(), number + 1, e)),
+        }
+    }
+    (records, errors)
+}
+
+/// Appends every session the metronome plays to the history at `path`.
+/// Sessions are written on a thread of their own, never the engine's. A
+/// session still running when the metronome is dropped is saved too.
+/// Sessions that cannot be written are passed to `report`.
+pub fn record_history(metronome: &Metronome, path: PathBuf, report: impl Fn(String) + Send + 'static) {
+    let state = metronome.snapshot();
+    let mut history = EngineHistory {
+        recorder: SessionRecorder::new(),
+        modes: Arc::clone(&state.modes),
+        mode: state.mode,
+        path,
+        report: Box::new(report),
+    };
+    let (sender, receiver) = mpsc::channel();
+    let thread = std::thread::spawn(move || {
+        for event in receiver {
+            history.record(&event);
+        }
+    });
+
+    let worker = HistoryWorker {
+        sender: Some(sender),
+        thread: Some(thread),
+    };
+    // Only the events a session is built from leave the timing thread
+    metronome.on_event(move |event| match event {
+        MetronomeEvent::Started { .. }
+        | MetronomeEvent::ModeChanged { .. }
+        | MetronomeEvent::Beat { .. }
+        | MetronomeEvent::Finished { .. }
+        | MetronomeEvent::Stopped => worker.send(event),
+        _ => true,
+    });
+}
+
+// Lives in the engine thread's subscriber list, so it is dropped when the
+// engine shuts down; it then waits for the last session to be written
+struct HistoryWorker {
+    sender: Option<Sender<MetronomeEvent>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HistoryWorker {
+    fn send(&self, event: &MetronomeEvent) -> bool {
+        self.sender.as_ref().is_some_and(|sender| sender.send(event.clone()).is_ok())
+    }
+}
+
+impl Drop for HistoryWorker {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Owned by the history thread; dropping it saves a session still running
+struct EngineHistory {
+    recorder: SessionRecorder,
+    modes: Arc<[(&'static str, &'static str)]>,
+    mode: usize,
+    path: PathBuf,
+    report: Box<dyn Fn(String) + Send>,
+}
+
+impl EngineHistory {
+    fn mode_name(&self) -> &'static str {
+        self.modes.get(self.mode).map_or("", |(_, name)| name)
+    }
+
+    fn record(&mut self, event: &MetronomeEvent) {
+        match event {
+            MetronomeEvent::Started { mode, .. } => {
+                // Starting again while running begins a new session
+                self.save();
+                self.mode = *mode;
+                self.recorder.start(self.mode_name());
+            },
+            MetronomeEvent::ModeChanged { mode } => {
+                self.mode = *mode;
+                self.recorder.mode(self.mode_name());
+            },
+            MetronomeEvent::Beat { event, at, .. } => self.recorder.beat(event.bpm, *at),
+            MetronomeEvent::Finished { countdown, .. } => {
+                if *countdown {
+                    self.recorder.countdown_finished();
+                }
+                self.save();
+            },
+            MetronomeEvent::Stopped => self.save(),
+            _ => {},
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(record) = self.recorder.finish()
+            && let Err(message) = append_session(&self.path, &record)
+        {
+            (self.report)(format!("Failed to record practice session: {}", message));
+        }
+    }
+}
+
+impl Drop for EngineHistory {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+/// Practice totals from the history, by local calendar day.
+#[derive(Clone, Debug, Default)]
+pub struct PracticeStats {
+    pub sessions: usize,
+    pub total_seconds: f64,
+    // The last `days` days, oldest first, including days without practice
+    pub per_day: Vec<(NaiveDate, f64)>,
+    // Weeks starting on Monday, oldest first, including weeks without practice
+    pub per_week: Vec<(NaiveDate, f64)>,
+    // Days in a row up to today, or up to yesterday if today has no practice yet
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    // Seconds by tempo range, keyed by the range's lowest tempo
+    pub per_tempo: BTreeMap<u32, f64>,
+    pub countdowns_completed: u32,
+}
+
+pub fn practice_stats(records: &[SessionRecord], today: NaiveDate, days: u32, weeks: u32) -> PracticeStats {
+    let mut by_day: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    let mut stats = PracticeStats {
+        sessions: records.len(),
+        ..Default::default()
+    };
+    for record in records {
+        let day = record.start.with_timezone(&Local).date_naive();
+        *by_day.entry(day).or_default() += record.duration_seconds;
+        stats.total_seconds += record.duration_seconds;
+        stats.countdowns_completed += record.countdowns_completed;
+        for (range, seconds) in &record.tempo_seconds {
+            *stats.per_tempo.entry(*range).or_default() += seconds;
+        }
+    }
+
+    stats.per_day = (0..days)
+        .rev()
+        .filter_map(|back| today.checked_sub_days(Days::new(back as u64)))
+        .map(|day| (day, by_day.get(&day).copied().unwrap_or(0.0)))
+        .collect();
+
+    let this_week = week_start(today);
+    stats.per_week = (0..weeks)
+        .rev()
+        .filter_map(|back| this_week.checked_sub_days(Days::new(7 * back as u64)))
+        .map(|week| {
+            let seconds = by_day.range(week..week + Days::new(7)).map(|(_, seconds)| seconds).sum();
+            (week, seconds)
+        })
+        .collect();
+
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in by_day.keys() {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        stats.longest_streak = stats.longest_streak.max(run);
+        previous = Some(day);
+    }
+    if let Some(last) = previous
+        && (last == today || Some(last) == today.pred_opt())
+    {
+        stats.current_streak = run;
+    }
+    stats
+}
+
+pub fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(day.weekday().num_days_from_monday() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::kit::VoiceRole;
+    use crate::utilities::timeline::TimelineEvent;
+    use chrono::TimeZone;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // A session at noon local time, so it falls on `day` whatever the time zone
+    fn session(day: NaiveDate, seconds: f64) -> SessionRecord {
+        let start = Local.from_local_datetime(&day.and_hms_opt(12, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+        SessionRecord {
+            start,
+            end: start + chrono::Duration::seconds(seconds as i64),
+            duration_seconds: seconds,
+            modes: vec!["Standard".to_string()],
+            min_bpm: 120,
+            max_bpm: 120,
+            ticks: 0,
+            countdowns_completed: 0,
+            tempo_seconds: BTreeMap::new(),
+        }
+    }
+
+    fn beat(bpm: u32, at: Instant) -> MetronomeEvent {
+        let event = TimelineEvent {
+            time: 0.0,
+            voice: VoiceRole::Beat,
+            velocity: 1.0,
+            bar: 0,
+            beat: 0,
+            click: 0,
+            section: "Countdown".into(),
+            bpm,
+        };
+        MetronomeEvent::Beat { tick_count: 0, event, at }
+    }
+
+    // Plays the events into a history file of its own and reads back what was saved
+    fn recorded(name: &str, events: &[MetronomeEvent]) -> Vec<SessionRecord> {
+        let path = std::env::temp_dir().join(format!("metronome-history-{}-{}.jsonl", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        let mut history = EngineHistory {
+            recorder: SessionRecorder::new(),
+            modes: Arc::new([("🎵", "Standard"), ("⏱️", "Countdown")]),
+            mode: 0,
+            path: path.clone(),
+            report: Box::new(|message| panic!("{}", message)),
+        };
+        for event in events {
+            history.record(event);
+        }
+        drop(history);
+        let (records, errors) = load_history(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(errors.is_empty(), "{:?}", errors);
+        records
+    }
+
+    #[test]
+    fn a_finished_countdown_is_counted() {
+        let start = Instant::now();
+        let records = recorded("countdown", &[
+            MetronomeEvent::Started { mode: 1, bpm: 90 },
+            beat(90, start),
+            beat(90, start + Duration::from_millis(667)),
+            MetronomeEvent::Finished { mode: 1, bpm: 90, countdown: true },
+            MetronomeEvent::Started { mode: 0, bpm: 120 },
+            beat(120, start + Duration::from_secs(2)),
+            MetronomeEvent::Finished { mode: 0, bpm: 120, countdown: false },
+        ]);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].modes, ["Countdown"]);
+        assert_eq!(records[0].countdowns_completed, 1);
+        // Any other mode running to its end is not a countdown
+        assert_eq!(records[1].modes, ["Standard"]);
+        assert_eq!(records[1].countdowns_completed, 0);
+    }
+
+    #[test]
+    fn streak_runs_across_the_end_of_a_month() {
+        let records = [
+            session(date(2026, 1, 30), 60.0),
+            session(date(2026, 1, 31), 60.0),
+            session(date(2026, 2, 1), 60.0),
+        ];
+        let stats = practice_stats(&records, date(2026, 2, 1), 7, 2);
+        assert_eq!(stats.current_streak, 3);
+        assert_eq!(stats.longest_streak, 3);
+    }
+
+    #[test]
+    fn streak_holds_until_today_is_practised() {
+        let records = [session(date(2026, 2, 27), 60.0), session(date(2026, 2, 28), 60.0)];
+        assert_eq!(practice_stats(&records, date(2026, 3, 1), 7, 2).current_streak, 2);
+        assert_eq!(practice_stats(&records, date(2026, 3, 2), 7, 2).current_streak, 0);
+    }
+
+    #[test]
+    fn longest_streak_outlives_a_gap() {
+        let records = [
+            session(date(2026, 3, 29), 60.0),
+            session(date(2026, 3, 30), 60.0),
+            session(date(2026, 3, 31), 60.0),
+            session(date(2026, 4, 2), 60.0),
+        ];
+        let stats = practice_stats(&records, date(2026, 4, 2), 7, 2);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.longest_streak, 3);
+    }
+
+    #[test]
+    fn week_spanning_a_month_end_adds_up_both_months() {
+        // Monday 26 January to Sunday 1 February
+        let records = [
+            session(date(2026, 1, 25), 30.0),
+            session(date(2026, 1, 31), 60.0),
+            session(date(2026, 2, 1), 90.0),
+        ];
+        let stats = practice_stats(&records, date(2026, 2, 1), 3, 2);
+        assert_eq!(stats.per_week, vec![(date(2026, 1, 19), 30.0), (date(2026, 1, 26), 150.0)]);
+        assert_eq!(stats.per_day, vec![(date(2026, 1, 30), 0.0), (date(2026, 1, 31), 60.0), (date(2026, 2, 1), 90.0)]);
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(stats.total_seconds, 180.0);
+    }
+
+    #[test]
+    fn week_start_is_the_previous_monday() {
+        assert_eq!(week_start(date(2026, 3, 1)), date(2026, 2, 23));
+        assert_eq!(week_start(date(2026, 2, 23)), date(2026, 2, 23));
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::engine::{Metronome, MetronomeEvent};
+
+const DEFAULT_TIMEOUT_SECONDS: f64 = 10.0;
+// How often running hooks are checked against their timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Hooks still running past this are skipped, so a slow bar hook cannot pile up processes
+const MAX_RUNNING: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Start,
+    // Stopped, or the mode ran to its end
+    Stop,
+    Bar,
+    // The mode moved to another section, e.g. the next Practice section
+    Section,
+    RandomBpm,
+    CountdownFinished,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Start => "start",
+            HookEvent::Stop => "stop",
+            HookEvent::Bar => "bar",
+            HookEvent::Section => "section",
+            HookEvent::RandomBpm => "random_bpm",
+            HookEvent::CountdownFinished => "countdown_finished",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Hook {
+    pub on: HookEvent,
+    pub command: String,
+    pub timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct HookManifest {
+    #[serde(default, rename = "hook")]
+    hooks: Vec<HookEntry>,
+}
+
+#[derive(Deserialize)]
+struct HookEntry {
+    on: HookEvent,
+    command: String,
+    timeout_seconds: Option<f64>,
+}
+
+pub fn hooks_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("metronome").join("hooks.toml"))
+}
+
+/// Reads hooks from a TOML manifest of `[[hook]]` tables. A missing manifest is not an error.
+pub fn load_hooks(path: &Path) -> Result<Vec<Hook>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+
+    let manifest: HookManifest =
+        toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(manifest
+        .hooks
+        .into_iter()
+        .map(|entry| Hook {
+            on: entry.on,
+            command: entry.command,
+            timeout: Duration::from_secs_f64(entry.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS).max(0.0)),
+        })
+        .collect())
+}
+
+/// Runs each hook's shell command whenever its event happens, with the event's
+/// details in `METRONOME_*` environment variables. Commands run on their own
+/// thread and are killed once past their timeout, so they never hold up a beat.
+/// Hooks that fail, time out or cannot start are passed to `report`.
+pub fn run_hooks(metronome: &Metronome, hooks: Vec<Hook>, report: impl Fn(String) + Send + 'static) {
+    if hooks.is_empty() {
+        return;
+    }
+    let state = metronome.snapshot();
+    let runner = HookRunner {
+        hooks,
+        modes: Arc::clone(&state.modes),
+        mode: state.mode,
+        bpm: state.bpm,
+        running: Vec::new(),
+        report: Box::new(report),
+    };
+    let (sender, receiver) = mpsc::channel();
+
+    // Only the events hooks fire on or read the mode and tempo from leave the timing thread
+    metronome.on_event(move |event| match event {
+        MetronomeEvent::Beat { .. } | MetronomeEvent::Error { .. } => true,
+        _ => sender.send(event.clone()).is_ok(),
+    });
+    std::thread::spawn(move || runner.run(receiver));
+}
+
+// Owned by the hook thread. The mode and tempo follow the events rather than
+// the published state, which may have moved on by the time an event is handled.
+struct HookRunner {
+    hooks: Vec<Hook>,
+    modes: Arc<[(&'static str, &'static str)]>,
+    mode: usize,
+    bpm: u32,
+    running: Vec<(String, Child, Instant)>,
+    report: Box<dyn Fn(String) + Send>,
+}
+
+impl HookRunner {
+    // Returns once the metronome is gone and the last hook has exited
+    fn run(mut self, receiver: Receiver<MetronomeEvent>) {
+        let mut closed = false;
+        while !closed || !self.running.is_empty() {
+            if closed {
+                std::thread::sleep(POLL_INTERVAL);
+            } else {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(event) => self.handle(&event),
+                    Err(RecvTimeoutError::Timeout) => {},
+                    Err(RecvTimeoutError::Disconnected) => closed = true,
+                }
+            }
+            self.reap();
+        }
+    }
+
+    fn handle(&mut self, event: &MetronomeEvent) {
+        let mut fired = Vec::new();
+        let mut bar = None;
+        let mut section = None;
+        match event {
+            MetronomeEvent::Started { mode, bpm } => {
+                (self.mode, self.bpm) = (*mode, *bpm);
+                fired.push(HookEvent::Start);
+            },
+            MetronomeEvent::Stopped => fired.push(HookEvent::Stop),
+            MetronomeEvent::Finished { mode, bpm, countdown } => {
+                (self.mode, self.bpm) = (*mode, *bpm);
+                fired.push(HookEvent::Stop);
+                if *countdown {
+                    fired.push(HookEvent::CountdownFinished);
+                }
+            },
+            MetronomeEvent::ModeChanged { mode } => self.mode = *mode,
+            MetronomeEvent::Bar { bar: number, bpm, .. } => {
+                self.bpm = *bpm;
+                fired.push(HookEvent::Bar);
+                bar = Some(number + 1);
+            },
+            MetronomeEvent::SectionChanged { section: label } => {
+                fired.push(HookEvent::Section);
+                section = Some(label.to_string());
+            },
+            MetronomeEvent::BpmChanged { bpm, random } => {
+                self.bpm = *bpm;
+                if *random {
+                    fired.push(HookEvent::RandomBpm);
+                }
+            },
+            _ => {},
+        }
+
+        let mode = self.modes.get(self.mode).map_or("", |(_, name)| name);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        for kind in fired {
+            for hook in self.hooks.iter().filter(|hook| hook.on == kind) {
+                if self.running.len() >= MAX_RUNNING {
+                    (self.report)(format!("Skipping hook `{}`: {} hooks are still running", hook.command, MAX_RUNNING));
+                    continue;
+                }
+                let mut command = shell(&hook.command);
+                command
+                    .stdin(Stdio::null())
+                    .env("METRONOME_EVENT", kind.name())
+                    .env("METRONOME_MODE", mode)
+                    .env("METRONOME_BPM", self.bpm.to_string())
+                    .env("METRONOME_TIMESTAMP", timestamp.to_string());
+                if let Some(bar) = bar {
+                    command.env("METRONOME_BAR", bar.to_string());
+                }
+                if let Some(section) = &section {
+                    command.env("METRONOME_SECTION", section);
+                }
+                match command.spawn() {
+                    Ok(child) => self.running.push((hook.command.clone(), child, Instant::now() + hook.timeout)),
+                    Err(e) => (self.report)(format!("Failed to run hook `{}`: {}", hook.command, e)),
+                }
+            }
+        }
+    }
+
+    // Reports hooks that failed and stops the ones past their timeout
+    fn reap(&mut self) {
+        let report = &self.report;
+        self.running.retain_mut(|(command, child, deadline)| match child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    report(format!("Hook `{}` exited with {}", command, status));
+                }
+                false
+            },
+            Ok(None) if Instant::now() >= *deadline => {
+                report(format!("Hook `{}` timed out and was stopped", command));
+                let _ = child.kill();
+                let _ = child.wait();
+                false
+            },
+            Ok(None) => true,
+            Err(e) => {
+                report(format!("Lost track of hook `{}`: {}", command, e));
+                false
+            },
+        });
+    }
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook(on: HookEvent, command: &str, timeout_ms: u64) -> Hook {
+        Hook { on, command: command.to_string(), timeout: Duration::from_millis(timeout_ms) }
+    }
+
+    // Runs the hooks for the events until every one has exited, and returns the reports
+    fn run(hooks: Vec<Hook>, events: Vec<MetronomeEvent>) -> Vec<String> {
+        let (report_sender, reports) = mpsc::channel();
+        let runner = HookRunner {
+            hooks,
+            modes: Arc::new([("🎵", "Standard"), ("⏱️", "Countdown")]),
+            mode: 0,
+            bpm: 120,
+            running: Vec::new(),
+            report: Box::new(move |message| {
+                let _ = report_sender.send(message);
+            }),
+        };
+        let (sender, receiver) = mpsc::channel();
+        for event in events {
+            sender.send(event).unwrap();
+        }
+        drop(sender);
+        runner.run(receiver);
+        let mut reports: Vec<String> = reports.try_iter().collect();
+        reports.sort();
+        reports
+    }
+
+    #[test]
+    fn failing_and_slow_hooks_are_reported() {
+        let hooks = vec![
+            hook(HookEvent::Start, "true", 2000),
+            hook(HookEvent::Start, "exit 1", 2000),
+            hook(HookEvent::Start, "sleep 5", 100),
+        ];
+        let started = Instant::now();
+        let reports = run(hooks, vec![MetronomeEvent::Started { mode: 0, bpm: 96 }]);
+
+        assert!(started.elapsed() < Duration::from_secs(2), "the slow hook was not stopped");
+        assert_eq!(reports.len(), 2, "{:?}", reports);
+        assert!(reports[0].starts_with("Hook `exit 1` exited with"), "{}", reports[0]);
+        assert_eq!(reports[1], "Hook `sleep 5` timed out and was stopped");
+    }
+
+    #[test]
+    fn hooks_see_the_mode_and_tempo_of_their_event() {
+        // Each hook fails, and so is reported, unless its variables match
+        let hooks = vec![
+            hook(HookEvent::Start, r#"test "$METRONOME_MODE" = Standard && test "$METRONOME_BPM" = 96"#, 2000),
+            hook(HookEvent::Bar, r#"test "$METRONOME_BAR" = 3 && test "$METRONOME_MODE" = Countdown && test "$METRONOME_BPM" = 100"#, 2000),
+            hook(HookEvent::Stop, r#"test "$METRONOME_EVENT" = stop && test "$METRONOME_BPM" = 80"#, 2000),
+            hook(HookEvent::CountdownFinished, r#"test "$METRONOME_MODE" = Countdown && test "$METRONOME_BPM" = 80"#, 2000),
+        ];
+        let events = vec![
+            MetronomeEvent::Started { mode: 0, bpm: 96 },
+            MetronomeEvent::ModeChanged { mode: 1 },
+            MetronomeEvent::Bar { bar: 2, bpm: 100, at: Instant::now() },
+            MetronomeEvent::Finished { mode: 1, bpm: 80, countdown: true },
+        ];
+        assert_eq!(run(hooks, events), Vec::<String>::new());
+    }
+
+    #[test]
+    fn only_a_countdown_finishing_fires_countdown_hooks() {
+        let hooks = vec![hook(HookEvent::CountdownFinished, "exit 1", 2000)];
+        assert!(run(hooks.clone(), vec![MetronomeEvent::Finished { mode: 0, bpm: 80, countdown: false }]).is_empty());
+        assert_eq!(run(hooks, vec![MetronomeEvent::Finished { mode: 1, bpm: 80, countdown: true }]).len(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+// The role a single click plays in the bar; each kit maps every role to a sound
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VoiceRole {
+    Downbeat = 0,
+    Accent = 1,
+    Beat = 2,
+    Subdivision = 3,
+    PolyVoice = 4,
+    CountIn = 5,
+}
+
+#[derive(Clone, Debug)]
+pub struct SoundKit {
+    pub name: String,
+    voices: [u32; 6],
+}
+
+impl SoundKit {
+    fn new(name: &str, voices: [u32; 6]) -> Self {
+        Self {
+            name: name.to_string(),
+            voices,
+        }
+    }
+
+    pub fn sound_for(&self, role: VoiceRole) -> u32 {
+        self.voices[role as usize]
+    }
+}
+
+#[derive(Deserialize)]
+struct KitManifest {
+    #[serde(default, rename = "kit")]
+    kits: Vec<KitEntry>,
+}
+
+// Every role except `beat` is optional and falls back to the beat sound
+#[derive(Deserialize)]
+struct KitEntry {
+    name: String,
+    beat: String,
+    downbeat: Option<String>,
+    accent: Option<String>,
+    subdivision: Option<String>,
+    poly: Option<String>,
+    count_in: Option<String>,
+}
+
+pub fn kits_manifest_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("metronome").join("kits.toml"))
+}
+
+// Sound keys follow the sound cache: 0 Beep, 1 Kick, 2 Click, 3 Cowbell,
+// 4 Hi-hat, 5 Woodblock, 6 Triangle, 7 Square
+pub fn builtin_kits() -> Vec<SoundKit> {
+    vec![
+        SoundKit::new("Classic", [3, 5, 2, 4, 6, 0]),
+        SoundKit::new("Drum Kit", [1, 3, 4, 4, 5, 2]),
+        SoundKit::new("Electronic", [7, 6, 0, 2, 6, 7]),
+    ]
+}
+
+/// Reads user kits from a TOML manifest of `[[kit]]` tables.
+/// `resolve` maps a sound name to its sound cache key. A missing manifest is not an error.
+pub fn load_kits(
+    path: &Path,
+    resolve: impl Fn(&str) -> Option<u32>,
+) -> Result<Vec<SoundKit>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+
+    let manifest: KitManifest =
+        toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    manifest
+        .kits
+        .into_iter()
+        .map(|entry| {
+            let lookup = |sound: &str| {
+                resolve(sound)
+                    .ok_or_else(|| format!("kit '{}': unknown sound '{}'", entry.name, sound))
+            };
+            let beat = lookup(&entry.beat)?;
+            let role_sound = |sound: &Option<String>| match sound {
+                Some(sound) => lookup(sound),
+                None => Ok(beat),
+            };
+
+            let voices = [
+                role_sound(&entry.downbeat)?,
+                role_sound(&entry.accent)?,
+                beat,
+                role_sound(&entry.subdivision)?,
+                role_sound(&entry.poly)?,
+                role_sound(&entry.count_in)?,
+            ];
+            Ok(SoundKit::new(&entry.name, voices))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sound names the way a frontend resolves them, with the built-in keys
+    fn resolve(name: &str) -> Option<u32> {
+        ["beep", "kick", "click", "cowbell", "hihat", "woodblock", "triangle", "square"]
+            .iter()
+            .position(|sound| *sound == name)
+            .map(|key| key as u32)
+    }
+
+    fn manifest(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("metronome-kits-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn roles_left_out_play_the_beat_sound() {
+        let path = manifest(
+            "fallback",
+            r#"
+            [[kit]]
+            name = "Wood"
+            beat = "woodblock"
+            downbeat = "cowbell"
+            poly = "triangle"
+
+            [[kit]]
+            name = "Plain"
+            beat = "click"
+            "#,
+        );
+        let kits = load_kits(&path, resolve).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(kits.iter().map(|kit| kit.name.as_str()).collect::<Vec<_>>(), ["Wood", "Plain"]);
+        assert_eq!(kits[0].sound_for(VoiceRole::Downbeat), 3);
+        assert_eq!(kits[0].sound_for(VoiceRole::Beat), 5);
+        assert_eq!(kits[0].sound_for(VoiceRole::Accent), 5);
+        assert_eq!(kits[0].sound_for(VoiceRole::PolyVoice), 6);
+        assert_eq!(kits[0].sound_for(VoiceRole::CountIn), 5);
+        assert!(
+            [VoiceRole::Downbeat, VoiceRole::Accent, VoiceRole::Beat, VoiceRole::Subdivision, VoiceRole::PolyVoice, VoiceRole::CountIn]
+                .into_iter()
+                .all(|role| kits[1].sound_for(role) == 2)
+        );
+    }
+
+    #[test]
+    fn unknown_sounds_name_the_kit() {
+        let path = manifest("unknown", "[[kit]]\nname = \"Odd\"\nbeat = \"click\"\naccent = \"gong\"\n");
+        let error = load_kits(&path, resolve).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error, "kit 'Odd': unknown sound 'gong'");
+    }
+
+    #[test]
+    fn a_kit_needs_a_beat_sound() {
+        let path = manifest("no-beat", "[[kit]]\nname = \"Empty\"\ndownbeat = \"kick\"\n");
+        let error = load_kits(&path, resolve).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains("beat"), "{}", error);
+    }
+
+    #[test]
+    fn a_missing_manifest_has_no_kits() {
+        let path = std::env::temp_dir().join(format!("metronome-kits-{}-missing.toml", std::process::id()));
+        assert!(load_kits(&path, resolve).unwrap().is_empty());
+    }
+}
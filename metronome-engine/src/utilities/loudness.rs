@@ -0,0 +1,137 @@
+use std::f32::consts::PI;
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+// Clicks are far shorter than the 3 s EBU short-term window, so loudness is
+// taken from the loudest 50 ms of the sound instead
+const WINDOW_MS: f32 = 50.0;
+const HOP_MS: f32 = 5.0;
+
+pub const TARGET_LOUDNESS_LUFS: f32 = -14.0;
+
+// Gain matching never pushes a sound's peak above this
+const PEAK_CEILING: f32 = 0.8;
+
+const LIMITER_THRESHOLD: f32 = 0.8;
+
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    fn new(b: [f32; 3], a0: f32, a: [f32; 2]) -> Self {
+        Self {
+            b: [b[0] / a0, b[1] / a0, b[2] / a0],
+            a: [a[0] / a0, a[1] / a0],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    // ITU-R BS.1770 stage 1: +4 dB high shelf modelling the head
+    fn high_shelf() -> Self {
+        let gain = 10.0f32.powf(4.0 / 40.0);
+        let w0 = 2.0 * PI * 1500.0 / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        let sqrt_alpha = 2.0 * gain.sqrt() * alpha;
+
+        Self::new(
+            [
+                gain * ((gain + 1.0) + (gain - 1.0) * cos + sqrt_alpha),
+                -2.0 * gain * ((gain - 1.0) + (gain + 1.0) * cos),
+                gain * ((gain + 1.0) + (gain - 1.0) * cos - sqrt_alpha),
+            ],
+            (gain + 1.0) - (gain - 1.0) * cos + sqrt_alpha,
+            [
+                2.0 * ((gain - 1.0) - (gain + 1.0) * cos),
+                (gain + 1.0) - (gain - 1.0) * cos - sqrt_alpha,
+            ],
+        )
+    }
+
+    // ITU-R BS.1770 stage 2: high pass that ignores sub-bass energy
+    fn high_pass() -> Self {
+        let w0 = 2.0 * PI * 38.0 / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * 0.5);
+        let cos = w0.cos();
+
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            1.0 + alpha,
+            [-2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// Short-term loudness in LUFS: K-weighted mean square of the loudest window.
+pub fn measure_loudness(samples: &[f32]) -> f32 {
+    let mut shelf = Biquad::high_shelf();
+    let mut high_pass = Biquad::high_pass();
+    let squared: Vec<f32> = samples
+        .iter()
+        .map(|&s| {
+            let weighted = high_pass.process(shelf.process(s));
+            weighted * weighted
+        })
+        .collect();
+
+    let window = ((SAMPLE_RATE * WINDOW_MS / 1000.0) as usize).min(squared.len().max(1));
+    let hop = (SAMPLE_RATE * HOP_MS / 1000.0) as usize;
+
+    let mut loudest = 0.0f32;
+    let mut start = 0;
+    while start + window <= squared.len() {
+        let mean = squared[start..start + window].iter().sum::<f32>() / window as f32;
+        loudest = loudest.max(mean);
+        start += hop;
+    }
+
+    if loudest > 0.0 {
+        -0.691 + 10.0 * loudest.log10()
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// Scales a sound towards the target loudness without letting its peak pass the ceiling.
+pub fn match_loudness(samples: &mut [f32]) {
+    let loudness = measure_loudness(samples);
+    if !loudness.is_finite() {
+        return;
+    }
+
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let gain = 10.0f32
+        .powf((TARGET_LOUDNESS_LUFS - loudness) / 20.0)
+        .min(PEAK_CEILING / peak);
+
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+/// Soft limiter for the output stage: transparent below the threshold and
+/// bending smoothly towards, but never past, full scale.
+pub fn limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= LIMITER_THRESHOLD {
+        return sample;
+    }
+
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let over = (magnitude - LIMITER_THRESHOLD) / headroom;
+    sample.signum() * (LIMITER_THRESHOLD + headroom * over.tanh())
+}
@@ -0,0 +1,172 @@
+use rodio::Source;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::Duration;
+
+use crate::utilities::loudness::limit;
+
+pub const SAMPLE_RATE: u32 = 44100;
+const MAX_VOICES: usize = 16;
+const MAX_PENDING: usize = 64;
+
+// New voices are picked up every this many output samples (about 1.5 ms)
+const POLL_INTERVAL: u32 = 64;
+
+struct Voice {
+    samples: Arc<[f32]>,
+    gain: f32,
+    position: usize,
+    // Samples of silence before the sound starts
+    delay: usize,
+    // The handle's generation when it was queued; voices from before a cancel never start
+    generation: u32,
+}
+
+/// Cheap, cloneable trigger for the mixer. Playing a sound only bumps the
+/// `Arc` refcount and writes into a preallocated queue, so the timing thread
+/// never allocates or copies sample data.
+#[derive(Clone)]
+pub struct MixerHandle {
+    sender: SyncSender<Voice>,
+    generation: Arc<AtomicU32>,
+}
+
+impl MixerHandle {
+    pub fn play(&self, samples: &Arc<[f32]>, gain: f32) {
+        self.play_in(samples, gain, Duration::ZERO);
+    }
+
+    // Starts the sound `delay` after the mixer picks it up, for clicks scheduled ahead
+    pub fn play_in(&self, samples: &Arc<[f32]>, gain: f32, delay: Duration) {
+        // A full queue drops the click rather than stalling the caller
+        let _ = self.sender.try_send(Voice {
+            samples: Arc::clone(samples),
+            gain,
+            position: 0,
+            delay: (delay.as_secs_f64() * SAMPLE_RATE as f64).round() as usize,
+            generation: self.generation.load(Ordering::Relaxed),
+        });
+    }
+
+    // Drops every sound queued with a delay that has not started yet; sounds
+    // already playing ring out
+    pub fn cancel_pending(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Endless output source that sums every active voice, applies its gain and
+/// runs the result through the limiter. Added to the output stream once.
+pub struct Mixer {
+    receiver: Receiver<Voice>,
+    voices: Vec<Option<Voice>>,
+    until_poll: u32,
+    generation: Arc<AtomicU32>,
+}
+
+pub fn mixer() -> (MixerHandle, Mixer) {
+    let (sender, receiver) = mpsc::sync_channel(MAX_PENDING);
+    let generation = Arc::new(AtomicU32::new(0));
+    let mixer = Mixer {
+        receiver,
+        voices: (0..MAX_VOICES).map(|_| None).collect(),
+        until_poll: 0,
+        generation: Arc::clone(&generation),
+    };
+    (MixerHandle { sender, generation }, mixer)
+}
+
+impl Mixer {
+    // Starts a voice on the very next sample, for callers that pull samples themselves
+    pub fn play_now(&mut self, samples: &Arc<[f32]>, gain: f32) {
+        self.start_voice(Voice {
+            samples: Arc::clone(samples),
+            gain,
+            position: 0,
+            delay: 0,
+            generation: self.generation.load(Ordering::Relaxed),
+        });
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.voices.iter().all(Option::is_none)
+    }
+
+    fn start_pending_voices(&mut self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        for slot in self.voices.iter_mut() {
+            if slot.as_ref().is_some_and(|voice| voice.delay > 0 && voice.generation != generation) {
+                *slot = None;
+            }
+        }
+        while let Ok(voice) = self.receiver.try_recv() {
+            if voice.generation == generation {
+                self.start_voice(voice);
+            }
+        }
+    }
+
+    fn start_voice(&mut self, voice: Voice) {
+        if voice.samples.is_empty() {
+            return;
+        }
+
+        // With every slot busy the voice furthest into its sound is cut
+        let slot = self.voices.iter().position(Option::is_none).unwrap_or_else(|| {
+            self.voices
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, voice)| voice.as_ref().map_or(0, |v| v.position))
+                .map_or(0, |(i, _)| i)
+        });
+        self.voices[slot] = Some(voice);
+    }
+}
+
+impl Iterator for Mixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.until_poll == 0 {
+            self.start_pending_voices();
+            self.until_poll = POLL_INTERVAL;
+        }
+        self.until_poll -= 1;
+
+        let mut mix = 0.0;
+        for slot in self.voices.iter_mut() {
+            if let Some(voice) = slot {
+                if voice.delay > 0 {
+                    voice.delay -= 1;
+                    continue;
+                }
+                mix += voice.samples[voice.position] * voice.gain;
+                voice.position += 1;
+                if voice.position >= voice.samples.len() {
+                    *slot = None;
+                }
+            }
+        }
+
+        Some(limit(mix))
+    }
+}
+
+impl Source for Mixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
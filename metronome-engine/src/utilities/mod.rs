@@ -0,0 +1,17 @@
+pub mod sound;
+pub mod sample;
+pub mod loudness;
+pub mod kit;
+pub mod mixer;
+pub mod snapshot;
+pub mod mode;
+pub mod modes;
+pub mod timeline;
+pub mod hooks;
+pub mod sink;
+pub mod config;
+pub mod preset;
+pub mod history;
+pub mod profile;
+#[cfg(feature = "scripting")]
+pub mod script;
@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utilities::timeline::{Pattern, TimelineEvent};
+
+/// A metronome mode. The engine owns the clock, the count-in and the audio;
+/// a mode describes what to play as a pattern, which the engine compiles into a timeline.
+pub trait MetronomeModeBehavior: Send {
+    fn name(&self) -> &'static str;
+    fn icon(&self) -> &'static str;
+
+    fn on_start(&mut self) {}
+
+    fn pattern(&self) -> Pattern;
+
+    // A mode that counts down to its end; the history and hooks count it as completed when it gets there
+    fn is_countdown(&self) -> bool {
+        false
+    }
+
+    // Called ahead of the first click of every bar with the tempo it would play at;
+    // the change applies from that click on. Errors leave the bar as it was.
+    fn on_bar(&mut self, _bar: u32, _bpm: u32) -> Result<Option<BarChange>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    // Called for every event as it plays, so the panel can follow along;
+    // returns true when the panel changed
+    fn on_event(&mut self, _event: &TimelineEvent) -> bool {
+        false
+    }
+
+    // Called on every engine wake-up while running; returns when to be called again
+    fn on_tick(&mut self, _elapsed: Duration) -> Option<Duration> {
+        None
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        Vec::new()
+    }
+
+    fn apply_setting(&mut self, _key: &str, _value: SettingValue) {}
+
+    // What the UI shows about the mode besides its settings
+    fn panel(&self) -> ModePanel;
+}
+
+// How a mode reshapes the coming bar
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BarChange {
+    // Holds until the next change
+    pub bpm: Option<u32>,
+    // Accents by beat of the bar, repeating if shorter; holds until the next change
+    pub accent_pattern: Option<Vec<bool>>,
+    // Silences this bar only
+    pub mute: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub kind: SettingKind,
+    pub value: SettingValue,
+}
+
+#[derive(Clone, Debug)]
+pub enum SettingKind {
+    Number { min: u32, max: u32, suffix: &'static str },
+    Choice(&'static [(u32, &'static str)]),
+    Toggle,
+    // One accent flag per click
+    Pattern,
+    // (BPM, beats) pairs
+    Sections { bpm: (u32, u32), beats: (u32, u32) },
+}
+
+// Saved in the config file as a plain TOML value. Untagged variants are tried
+// in order, so Sections comes first to read an empty list as no sections
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SettingValue {
+    Number(u32),
+    Toggle(bool),
+    Sections(Vec<(u32, u32)>),
+    Pattern(Vec<bool>),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ModePanel {
+    // One-line summary for the status bar
+    pub status: String,
+    // Shown only while running
+    pub live: Vec<String>,
+    pub progress: Option<Progress>,
+    pub notes: Vec<&'static str>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub fraction: f32,
+    pub label: String,
+}
@@ -0,0 +1,607 @@
+use std::time::Duration;
+
+use crate::utilities::mode::{MetronomeModeBehavior, ModePanel, Progress, Setting, SettingKind, SettingValue};
+use crate::utilities::timeline::{Clicks, Length, Pattern, Section, Tempo, TimelineEvent};
+
+// How often the countdown display is refreshed while no beat is due
+const COUNTDOWN_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+pub fn builtin_modes() -> Vec<Box<dyn MetronomeModeBehavior>> {
+    vec![
+        Box::new(StandardMode),
+        Box::new(RandomMode::default()),
+        Box::new(PracticeMode::default()),
+        Box::new(PolyrhythmMode::default()),
+        Box::new(RitardandoMode::default()),
+        Box::new(SubdivisionMode::default()),
+        Box::new(CountdownMode::default()),
+    ]
+}
+
+fn format_time(seconds: f32) -> String {
+    format!("{}:{:02}", (seconds / 60.0) as u32, (seconds % 60.0) as u32)
+}
+
+pub struct StandardMode;
+
+impl MetronomeModeBehavior for StandardMode {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🎵"
+    }
+
+    fn pattern(&self) -> Pattern {
+        Pattern::endless("Standard", Tempo::User, Clicks::Beats)
+    }
+
+    fn panel(&self) -> ModePanel {
+        ModePanel {
+            status: "Standard Mode".to_string(),
+            ..ModePanel::default()
+        }
+    }
+}
+
+pub struct RandomMode {
+    count: u32,
+    remaining_ticks: u32,
+}
+
+impl Default for RandomMode {
+    fn default() -> Self {
+        Self {
+            count: 100,
+            remaining_ticks: 100,
+        }
+    }
+}
+
+impl MetronomeModeBehavior for RandomMode {
+    fn name(&self) -> &'static str {
+        "Random"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🎲"
+    }
+
+    fn on_start(&mut self) {
+        self.remaining_ticks = self.count;
+    }
+
+    // The first stretch keeps the user's tempo, every later one picks its own
+    fn pattern(&self) -> Pattern {
+        Pattern {
+            sections: vec![
+                Section::new("Start", Length::Beats(self.count), Tempo::User),
+                Section::new("Random", Length::Beats(self.count), Tempo::Random { min: 60, max: 200 }),
+            ],
+            loop_from: Some(1),
+            ..Pattern::endless("Random", Tempo::User, Clicks::Beats)
+        }
+    }
+
+    fn on_event(&mut self, _event: &TimelineEvent) -> bool {
+        self.remaining_ticks = self.remaining_ticks.saturating_sub(1);
+        if self.remaining_ticks == 0 {
+            self.remaining_ticks = self.count;
+        }
+        true
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        vec![Setting {
+            key: "count",
+            label: "Change every",
+            kind: SettingKind::Number { min: 10, max: 500, suffix: " beats" },
+            value: SettingValue::Number(self.count),
+        }]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        if let ("count", SettingValue::Number(count)) = (key, value) {
+            self.count = count;
+            self.remaining_ticks = count;
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        let fraction = if self.count > 0 {
+            (self.count - self.remaining_ticks) as f32 / self.count as f32
+        } else {
+            0.0
+        };
+        ModePanel {
+            status: format!("Random Mode - Next change in {} beats", self.remaining_ticks),
+            progress: Some(Progress {
+                fraction,
+                label: format!("Next change in: {} beats", self.remaining_ticks),
+            }),
+            notes: vec!["🎯 BPM will randomly change between 60-200"],
+            ..ModePanel::default()
+        }
+    }
+}
+
+pub struct PracticeMode {
+    sections: Vec<(u32, u32)>, // (BPM, beats)
+    current_section: u32,
+    section_remaining: u32,
+}
+
+impl Default for PracticeMode {
+    fn default() -> Self {
+        Self {
+            sections: vec![(60, 32), (120, 32), (180, 32)],
+            current_section: 0,
+            section_remaining: 0,
+        }
+    }
+}
+
+impl MetronomeModeBehavior for PracticeMode {
+    fn name(&self) -> &'static str {
+        "Practice"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🎯"
+    }
+
+    fn on_start(&mut self) {
+        self.current_section = 0;
+        self.section_remaining = 0;
+    }
+
+    fn pattern(&self) -> Pattern {
+        // Without sections there is nothing to finish, so it plays on at the user's tempo
+        if self.sections.is_empty() {
+            return Pattern::endless("Practice", Tempo::User, Clicks::Beats);
+        }
+        let sections = self
+            .sections
+            .iter()
+            .enumerate()
+            .map(|(i, &(bpm, beats))| {
+                Section::new(&format!("Section {}", i + 1), Length::Beats(beats), Tempo::Fixed(bpm))
+            })
+            .collect();
+        Pattern {
+            sections,
+            loop_from: Some(0),
+            ..Pattern::endless("Practice", Tempo::User, Clicks::Beats)
+        }
+    }
+
+    fn on_event(&mut self, _event: &TimelineEvent) -> bool {
+        if self.sections.is_empty() {
+            return false;
+        }
+        if self.section_remaining == 0 {
+            let current_section = self.current_section as usize % self.sections.len();
+            self.section_remaining = self.sections[current_section].1;
+            self.current_section = ((current_section + 1) % self.sections.len()) as u32;
+        }
+        self.section_remaining = self.section_remaining.saturating_sub(1);
+        true
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        vec![Setting {
+            key: "sections",
+            label: "Practice sections (BPM, Beats)",
+            kind: SettingKind::Sections { bpm: (30, 300), beats: (4, 128) },
+            value: SettingValue::Sections(self.sections.clone()),
+        }]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        if let ("sections", SettingValue::Sections(sections)) = (key, value) {
+            self.sections = sections;
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        if self.sections.is_empty() {
+            return ModePanel {
+                status: "Practice Mode - No sections, playing at your tempo".to_string(),
+                ..ModePanel::default()
+            };
+        }
+        ModePanel {
+            status: format!(
+                "Practice Mode - Section {} - {} beats remaining",
+                self.current_section + 1,
+                self.section_remaining
+            ),
+            live: vec![format!(
+                "Current: Section {} - {} beats remaining",
+                self.current_section + 1,
+                self.section_remaining
+            )],
+            ..ModePanel::default()
+        }
+    }
+}
+
+pub struct PolyrhythmMode {
+    primary: u32,
+    secondary: u32,
+    accent_primary: bool,
+    accent_secondary: bool,
+}
+
+impl Default for PolyrhythmMode {
+    fn default() -> Self {
+        Self {
+            primary: 4,
+            secondary: 3,
+            accent_primary: true,
+            accent_secondary: true,
+        }
+    }
+}
+
+impl MetronomeModeBehavior for PolyrhythmMode {
+    fn name(&self) -> &'static str {
+        "Polyrhythm"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🔄"
+    }
+
+    fn pattern(&self) -> Pattern {
+        Pattern {
+            beats_per_bar: Some(self.primary.max(1)),
+            ..Pattern::endless(
+                "Polyrhythm",
+                Tempo::User,
+                Clicks::Polyrhythm {
+                    primary: self.primary,
+                    secondary: self.secondary,
+                    accent_primary: self.accent_primary,
+                    accent_secondary: self.accent_secondary,
+                },
+            )
+        }
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        let rhythm = SettingKind::Number { min: 2, max: 16, suffix: "" };
+        vec![
+            Setting {
+                key: "primary",
+                label: "Primary rhythm",
+                kind: rhythm.clone(),
+                value: SettingValue::Number(self.primary),
+            },
+            Setting {
+                key: "accent_primary",
+                label: "Accent primary",
+                kind: SettingKind::Toggle,
+                value: SettingValue::Toggle(self.accent_primary),
+            },
+            Setting {
+                key: "secondary",
+                label: "Secondary rhythm",
+                kind: rhythm,
+                value: SettingValue::Number(self.secondary),
+            },
+            Setting {
+                key: "accent_secondary",
+                label: "Accent secondary",
+                kind: SettingKind::Toggle,
+                value: SettingValue::Toggle(self.accent_secondary),
+            },
+        ]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        match (key, value) {
+            ("primary", SettingValue::Number(primary)) => self.primary = primary,
+            ("secondary", SettingValue::Number(secondary)) => self.secondary = secondary,
+            ("accent_primary", SettingValue::Toggle(accent)) => self.accent_primary = accent,
+            ("accent_secondary", SettingValue::Toggle(accent)) => self.accent_secondary = accent,
+            _ => {},
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        ModePanel {
+            status: format!("Polyrhythm Mode - {}:{}", self.primary, self.secondary),
+            notes: vec!["💡 Creates overlapping rhythmic patterns"],
+            ..ModePanel::default()
+        }
+    }
+}
+
+pub struct RitardandoMode {
+    start_bpm: u32,
+    target_bpm: u32,
+    duration: u32,
+    remaining: u32,
+}
+
+impl Default for RitardandoMode {
+    fn default() -> Self {
+        Self {
+            start_bpm: 120,
+            target_bpm: 180,
+            duration: 64,
+            remaining: 0,
+        }
+    }
+}
+
+impl MetronomeModeBehavior for RitardandoMode {
+    fn name(&self) -> &'static str {
+        "Ritardando"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🐌"
+    }
+
+    fn on_start(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    fn pattern(&self) -> Pattern {
+        Pattern {
+            sections: vec![Section::new(
+                "Ritardando",
+                Length::Beats(self.duration),
+                Tempo::Ramp { from: self.start_bpm, to: self.target_bpm },
+            )],
+            loop_from: Some(0),
+            ..Pattern::endless("Ritardando", Tempo::User, Clicks::Beats)
+        }
+    }
+
+    fn on_event(&mut self, _event: &TimelineEvent) -> bool {
+        if self.remaining == 0 {
+            self.remaining = self.duration;
+        }
+        self.remaining = self.remaining.saturating_sub(1);
+        true
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        vec![
+            Setting {
+                key: "start_bpm",
+                label: "Start BPM",
+                kind: SettingKind::Number { min: 60, max: 300, suffix: "" },
+                value: SettingValue::Number(self.start_bpm),
+            },
+            Setting {
+                key: "target_bpm",
+                label: "Target BPM",
+                kind: SettingKind::Number { min: 30, max: 250, suffix: "" },
+                value: SettingValue::Number(self.target_bpm),
+            },
+            Setting {
+                key: "duration",
+                label: "Duration",
+                kind: SettingKind::Number { min: 1, max: 256, suffix: " beats" },
+                value: SettingValue::Number(self.duration),
+            },
+        ]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        match (key, value) {
+            ("start_bpm", SettingValue::Number(bpm)) => self.start_bpm = bpm,
+            ("target_bpm", SettingValue::Number(bpm)) => self.target_bpm = bpm,
+            ("duration", SettingValue::Number(duration)) => self.duration = duration.max(1),
+            _ => {},
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        ModePanel {
+            status: format!("Ritardando - {} beats to {}BPM", self.remaining, self.target_bpm),
+            live: vec![format!("Slowing down... {} beats remaining", self.remaining)],
+            ..ModePanel::default()
+        }
+    }
+}
+
+const SUBDIVISIONS: [(u32, &str); 4] = [(1, "Quarter"), (2, "Eighth"), (3, "Triplet"), (4, "Sixteenth")];
+
+// The number of subdivisions is the length of the accent pattern, so the two always agree
+pub struct SubdivisionMode {
+    accent_pattern: Vec<bool>,
+}
+
+impl Default for SubdivisionMode {
+    fn default() -> Self {
+        Self {
+            accent_pattern: vec![true],
+        }
+    }
+}
+
+impl SubdivisionMode {
+    fn subdivisions(&self) -> u32 {
+        self.accent_pattern.len() as u32
+    }
+}
+
+impl MetronomeModeBehavior for SubdivisionMode {
+    fn name(&self) -> &'static str {
+        "Subdivision"
+    }
+
+    fn icon(&self) -> &'static str {
+        "🎼"
+    }
+
+    fn pattern(&self) -> Pattern {
+        Pattern::endless("Subdivision", Tempo::User, Clicks::Subdivided(self.accent_pattern.clone()))
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        vec![
+            Setting {
+                key: "subdivisions",
+                label: "Subdivision",
+                kind: SettingKind::Choice(&SUBDIVISIONS),
+                value: SettingValue::Number(self.subdivisions()),
+            },
+            Setting {
+                key: "accent_pattern",
+                label: "Accent Pattern",
+                kind: SettingKind::Pattern,
+                value: SettingValue::Pattern(self.accent_pattern.clone()),
+            },
+        ]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        match (key, value) {
+            // A new grid starts with only the first click accented
+            ("subdivisions", SettingValue::Number(subdivisions))
+                if is_subdivision(subdivisions) && subdivisions != self.subdivisions() =>
+            {
+                self.accent_pattern = vec![false; subdivisions as usize];
+                self.accent_pattern[0] = true;
+            },
+            ("accent_pattern", SettingValue::Pattern(pattern)) if is_subdivision(pattern.len() as u32) => {
+                self.accent_pattern = pattern;
+            },
+            _ => {},
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        let sub_name = match self.subdivisions() {
+            1 => "Quarter notes",
+            2 => "Eighth notes",
+            3 => "Triplets",
+            4 => "Sixteenth notes",
+            _ => "Custom",
+        };
+        ModePanel {
+            status: format!("Subdivision Mode - {}", sub_name),
+            notes: vec!["💡 Click beats to toggle accents"],
+            ..ModePanel::default()
+        }
+    }
+}
+
+fn is_subdivision(subdivisions: u32) -> bool {
+    SUBDIVISIONS.iter().any(|&(option, _)| option == subdivisions)
+}
+
+pub struct CountdownMode {
+    duration_seconds: u32,
+    remaining_seconds: f32,
+    enable_random_bpm: bool,
+}
+
+impl Default for CountdownMode {
+    fn default() -> Self {
+        Self {
+            duration_seconds: 60,
+            remaining_seconds: 60.0,
+            enable_random_bpm: false,
+        }
+    }
+}
+
+impl MetronomeModeBehavior for CountdownMode {
+    fn name(&self) -> &'static str {
+        "Countdown"
+    }
+
+    fn icon(&self) -> &'static str {
+        "⏱️"
+    }
+
+    fn is_countdown(&self) -> bool {
+        true
+    }
+
+    fn on_start(&mut self) {
+        self.remaining_seconds = self.duration_seconds as f32;
+    }
+
+    fn pattern(&self) -> Pattern {
+        let mut sections = vec![Section::new("Countdown", Length::Forever, Tempo::User)];
+        if self.enable_random_bpm {
+            // Keeps the user's tempo for five seconds, then changes every 3-8 seconds
+            sections = vec![
+                Section::new("Countdown", Length::Seconds { min: 5.0, max: 5.0 }, Tempo::User),
+                Section::new("Random", Length::Seconds { min: 3.0, max: 8.0 }, Tempo::Random { min: 80, max: 180 }),
+            ];
+        }
+        Pattern {
+            sections,
+            loop_from: self.enable_random_bpm.then_some(1),
+            duration: Some(self.duration_seconds as f64),
+            accent_every: Some(10.0),
+            ..Pattern::endless("Countdown", Tempo::User, Clicks::Beats)
+        }
+    }
+
+    fn on_tick(&mut self, elapsed: Duration) -> Option<Duration> {
+        self.remaining_seconds = (self.duration_seconds as f32 - elapsed.as_secs_f32()).max(0.0);
+        Some(COUNTDOWN_REFRESH_INTERVAL)
+    }
+
+    fn settings(&self) -> Vec<Setting> {
+        vec![
+            Setting {
+                key: "duration_seconds",
+                label: "Duration",
+                kind: SettingKind::Number { min: 30, max: 1800, suffix: " s" },
+                value: SettingValue::Number(self.duration_seconds),
+            },
+            Setting {
+                key: "enable_random_bpm",
+                label: "🎲 Randomize BPM during countdown",
+                kind: SettingKind::Toggle,
+                value: SettingValue::Toggle(self.enable_random_bpm),
+            },
+        ]
+    }
+
+    fn apply_setting(&mut self, key: &str, value: SettingValue) {
+        match (key, value) {
+            ("duration_seconds", SettingValue::Number(seconds)) => {
+                self.duration_seconds = seconds;
+                self.remaining_seconds = seconds as f32;
+            },
+            ("enable_random_bpm", SettingValue::Toggle(enabled)) => self.enable_random_bpm = enabled,
+            _ => {},
+        }
+    }
+
+    fn panel(&self) -> ModePanel {
+        let fraction = if self.duration_seconds > 0 {
+            1.0 - self.remaining_seconds / self.duration_seconds as f32
+        } else {
+            0.0
+        };
+        let mut notes = Vec::new();
+        if self.enable_random_bpm {
+            notes.push("💡 BPM will randomly change every 3-8 seconds");
+        }
+        notes.push("🎉 A celebration sound will play when countdown completes!");
+
+        ModePanel {
+            status: format!("Countdown Mode - {} remaining", format_time(self.remaining_seconds)),
+            progress: Some(Progress {
+                fraction,
+                label: format_time(self.remaining_seconds),
+            }),
+            notes,
+            ..ModePanel::default()
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::utilities::config::{Config, read_config, save_config};
+use crate::utilities::profile::profile_dir;
+
+/// A named copy of everything the config restores except the window and keys:
+/// tempo, sound, volume, kit, meter, mode and every mode's settings.
+/// Each preset is a config file of its own, so it can be handed around as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub config: Config,
+}
+
+impl Preset {
+    // Takes over everything but the window and keys; settings of modes the preset
+    // does not mention are kept from before
+    pub fn apply_to(&self, config: &mut Config) {
+        let mut applied = Config {
+            modes: std::mem::take(&mut config.modes),
+            window: config.window,
+            keys: std::mem::take(&mut config.keys),
+            ..self.config.clone()
+        };
+        for (mode, settings) in &self.config.modes {
+            for (key, value) in settings {
+                applied.set_mode_setting(mode, key, value.clone());
+            }
+        }
+        *config = applied;
+    }
+}
+
+pub fn presets_dir() -> Option<PathBuf> {
+    profile_dir().map(|dir| dir.join("presets"))
+}
+
+/// Loads every `*.toml` preset in `dir`, sorted by name. Presets that fail to
+/// load are reported and skipped. A missing directory is not an error.
+pub fn load_presets(dir: &Path) -> (Vec<Preset>, Vec<String>) {
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Vec::new(), Vec::new()),
+        Err(e) => return (Vec::new(), vec![format!("{}: {}", dir.display(), e)]),
+    };
+    paths.sort();
+
+    let mut presets = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match read_preset(&path) {
+            Ok(preset) => presets.push(preset),
+            Err(message) => errors.push(message),
+        }
+    }
+    (presets, errors)
+}
+
+// Named after the file, without its extension
+pub fn read_preset(path: &Path) -> Result<Preset, String> {
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let config = read_config(path)?;
+    Ok(Preset { name, config })
+}
+
+// Saves over a preset with the same name. Names stay inside `dir` and out of
+// sight of hidden files, so any name without a separator or a leading dot will do.
+pub fn save_preset(dir: &Path, name: &str, config: &Config) -> Result<Preset, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err("enter a name without '/' or '\\' that does not start with '.'".to_string());
+    }
+    let preset = Preset {
+        name: name.to_string(),
+        config: Config { window: None, keys: BTreeMap::new(), ..config.clone() },
+    };
+    save_config(&dir.join(format!("{}.toml", name)), &preset.config)?;
+    Ok(preset)
+}
+
+pub fn delete_preset(dir: &Path, name: &str) -> Result<(), String> {
+    let path = dir.join(format!("{}.toml", name));
+    std::fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Copies a preset file from elsewhere into `dir`, upgrading it on the way
+pub fn import_preset(dir: &Path, path: &Path) -> Result<Preset, String> {
+    let preset = read_preset(path)?;
+    save_preset(dir, &preset.name, &preset.config)
+}
+
+pub fn export_preset(preset: &Preset, path: &Path) -> Result<(), String> {
+    save_config(path, &preset.config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty directory of its own for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("metronome-presets-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn importing_an_unreadable_file_writes_nothing() {
+        let dir = temp_dir("unreadable");
+        let path = dir.join("broken.toml");
+        std::fs::write(&path, "bpm = [not toml").unwrap();
+        let presets = dir.join("presets");
+
+        assert!(import_preset(&presets, &path).is_err());
+        let files: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(files, [path]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn importing_a_missing_file_is_an_error() {
+        let dir = temp_dir("missing");
+        assert!(import_preset(&dir.join("presets"), &dir.join("missing.toml")).is_err());
+        assert!(!dir.join("presets").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn names_may_hold_dots_but_not_paths() {
+        let dir = temp_dir("names");
+        let config = Config { bpm: 96, ..Config::default() };
+
+        let preset = save_preset(&dir, " Etude v1.2 ", &config).unwrap();
+        assert_eq!(preset.name, "Etude v1.2");
+        let (presets, errors) = load_presets(&dir);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(presets, [preset]);
+
+        for name in ["", "a/b", "a\\b", ".hidden", "..", "../up"] {
+            assert!(save_preset(&dir, name, &config).is_err(), "{:?} was accepted", name);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+// Uses the metronome folder itself, so files from before profiles stay where they were
+pub const DEFAULT_PROFILE: &str = "default";
+
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Switches the config, presets and history paths to the named profile's
+/// folder, creating it for a new profile. Sounds, kits, script modes, hooks
+/// and sinks belong to the machine and are shared by every profile.
+pub fn select_profile(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return Err(format!("'{}' is not a profile name; use one without '/', '\\' or '.'", name));
+    }
+    if name != DEFAULT_PROFILE
+        && let Some(dir) = profiles_dir().map(|dir| dir.join(name))
+    {
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    *ACTIVE_PROFILE.write().unwrap() = Some(name.to_string()).filter(|name| name != DEFAULT_PROFILE);
+    Ok(())
+}
+
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE.read().unwrap().clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+// Where the active profile keeps its config, presets and history
+pub fn profile_dir() -> Option<PathBuf> {
+    let base = dirs::config_dir()?.join("metronome");
+    match ACTIVE_PROFILE.read().unwrap().as_deref() {
+        Some(name) => Some(base.join("profiles").join(name)),
+        None => Some(base),
+    }
+}
+
+fn profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("metronome").join("profiles"))
+}
+
+// The default profile first, then the others by name
+pub fn list_profiles() -> Vec<String> {
+    let mut names: Vec<String> = profiles_dir()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != DEFAULT_PROFILE)
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+// `--profile NAME` or `--profile=NAME`
+pub fn profile_from_args(args: &[String]) -> Option<String> {
+    args.iter().enumerate().find_map(|(i, arg)| match arg.strip_prefix("--profile") {
+        Some("") => args.get(i + 1).cloned(),
+        Some(value) => value.strip_prefix('=').map(str::to_string),
+        None => None,
+    })
+}
@@ -0,0 +1,216 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+const SAMPLE_RATE: u32 = 44100;
+const SUPPORTED_EXTENSIONS: [&str; 4] = ["wav", "flac", "ogg", "oga"];
+
+// Anything longer than this is a recording, not a click
+const MAX_SOURCE_SECONDS: u32 = 10;
+const MAX_CLICK_MS: u32 = 1000;
+const FADE_OUT_MS: u32 = 5;
+
+const SILENCE_THRESHOLD: f32 = 0.003; // about -50 dBFS
+const TARGET_PEAK: f32 = 0.5;
+
+pub struct CustomSound {
+    pub name: String,
+    pub samples: Vec<f32>,
+}
+
+pub fn custom_sounds_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("METRONOME_SOUNDS_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    dirs::config_dir().map(|dir| dir.join("metronome").join("sounds"))
+}
+
+/// Loads every supported audio file in `dir`, sorted by file name.
+/// Files that fail to decode are reported in the second list instead of aborting.
+pub fn load_custom_sounds(dir: &Path) -> (Vec<CustomSound>, Vec<String>) {
+    let mut sounds = Vec::new();
+    let mut errors = Vec::new();
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (sounds, errors);
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_supported(path))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        match load_sample(&path) {
+            Ok(samples) => sounds.push(CustomSound { name, samples }),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    (sounds, errors)
+}
+
+pub fn load_sample(path: &Path) -> Result<Vec<f32>, Box<dyn Error>> {
+    let (mono, source_rate) = decode_mono(path)?;
+
+    let mut wave = resample(&mono, source_rate, SAMPLE_RATE);
+    trim_silence(&mut wave);
+    if wave.is_empty() {
+        return Err("file contains only silence".into());
+    }
+
+    let max_samples = (SAMPLE_RATE * MAX_CLICK_MS / 1000) as usize;
+    if wave.len() > max_samples {
+        wave.truncate(max_samples);
+        apply_fade_out(&mut wave);
+    }
+
+    normalize(&mut wave);
+    Ok(wave)
+}
+
+fn is_supported(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+            .unwrap_or(false)
+}
+
+fn decode_mono(path: &Path) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("no audio track found")?;
+    let track_id = track.id;
+    let source_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or("unknown sample rate")?;
+
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let max_frames = (source_rate * MAX_SOURCE_SECONDS) as usize;
+    let mut mono = Vec::new();
+
+    while mono.len() < max_frames {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet is skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        // Downmix by averaging all channels of each frame
+        for frame in buffer.samples().chunks(channels) {
+            mono.push(frame.iter().sum::<f32>() / channels as f32);
+        }
+    }
+
+    mono.truncate(max_frames);
+    Ok((mono, source_rate))
+}
+
+fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let output_len = (samples.len() as f64 / ratio) as usize;
+
+    let mut wave: Vec<f32> = Vec::with_capacity(output_len);
+    for i in 0..output_len {
+        let position = i as f64 * ratio;
+        let index = position as usize;
+        let fraction = (position - index as f64) as f32;
+
+        let current = samples[index];
+        let next = samples.get(index + 1).copied().unwrap_or(current);
+        wave.push(current + (next - current) * fraction);
+    }
+    wave
+}
+
+fn trim_silence(wave: &mut Vec<f32>) {
+    let Some(start) = wave.iter().position(|s| s.abs() > SILENCE_THRESHOLD) else {
+        wave.clear();
+        return;
+    };
+    let end = wave
+        .iter()
+        .rposition(|s| s.abs() > SILENCE_THRESHOLD)
+        .unwrap_or(start);
+
+    wave.truncate(end + 1);
+    wave.drain(..start);
+}
+
+fn apply_fade_out(wave: &mut [f32]) {
+    let fade_samples = ((SAMPLE_RATE * FADE_OUT_MS / 1000) as usize).min(wave.len());
+    let fade_start = wave.len() - fade_samples;
+
+    for (i, sample) in wave[fade_start..].iter_mut().enumerate() {
+        *sample *= 1.0 - i as f32 / fade_samples as f32;
+    }
+}
+
+fn normalize(wave: &mut [f32]) {
+    let peak = wave.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 0.0 {
+        let gain = TARGET_PEAK / peak;
+        for sample in wave.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
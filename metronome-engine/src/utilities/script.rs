@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, Map, Scope};
+
+use crate::engine::{MAX_BPM, MIN_BPM};
+use crate::utilities::mode::{BarChange, MetronomeModeBehavior, ModePanel};
+use crate::utilities::timeline::{Clicks, Pattern, Tempo, TimelineEvent};
+
+// Limits for every call into a script, so a runaway loop cannot stall the clock
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_COLLECTION_SIZE: usize = 1024;
+
+pub fn scripts_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("metronome").join("modes"))
+}
+
+/// Loads every `*.rhai` file in `dir` as a mode, sorted by name. Scripts that
+/// fail to compile are reported and skipped. A missing directory is not an error.
+pub fn load_script_modes(dir: &Path) -> (Vec<Box<dyn MetronomeModeBehavior>>, Vec<String>) {
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "rhai"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Vec::new(), Vec::new()),
+        Err(e) => return (Vec::new(), vec![format!("{}: {}", dir.display(), e)]),
+    };
+    paths.sort();
+
+    let mut modes: Vec<Box<dyn MetronomeModeBehavior>> = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match ScriptMode::from_file(&path) {
+            Ok(mode) => modes.push(Box::new(mode)),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    (modes, errors)
+}
+
+/// A practice mode written in Rhai. The script defines `on_bar(bar)`, called
+/// before every bar, and may define `on_start()`. Both see `this`, a map kept
+/// between calls with `this.bpm` set to the current tempo. `on_bar` returns
+/// nothing to keep the bar as it is, or a map with any of `bpm`,
+/// `accent_pattern` (one bool per beat) and `mute`.
+pub struct ScriptMode {
+    name: &'static str,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+    has_on_start: bool,
+    bar: u32,
+    bpm: u32,
+    error: Option<String>,
+}
+
+impl ScriptMode {
+    pub fn new(name: &str, source: &str) -> Result<Self, Box<dyn Error>> {
+        let engine = sandboxed_engine();
+        let ast = engine.compile(source)?;
+        if !ast.iter_functions().any(|function| function.name == "on_bar" && function.params.len() == 1) {
+            return Err("the script does not define on_bar(bar)".into());
+        }
+        let has_on_start = ast.iter_functions().any(|function| function.name == "on_start" && function.params.is_empty());
+
+        // Top-level statements run once, so scripts can declare constants
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+
+        Ok(Self {
+            // Modes are named for the whole run, and scripts are only loaded at startup
+            name: Box::leak(name.to_string().into_boxed_str()),
+            engine,
+            ast,
+            scope,
+            state: Map::new().into(),
+            has_on_start,
+            bar: 0,
+            bpm: 0,
+            error: None,
+        })
+    }
+
+    // Named after the file, without its extension
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let source = std::fs::read_to_string(path)?;
+        let name = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        Self::new(&name, &source)
+    }
+
+    fn call(&mut self, function: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, Box<dyn Error>> {
+        if let Some(state) = self.state.write_lock::<Map>().as_deref_mut() {
+            state.insert("bpm".into(), Dynamic::from_int(self.bpm as rhai::INT));
+        }
+        let options = CallFnOptions::new().eval_ast(false).rewind_scope(true).bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options(options, &mut self.scope, &self.ast, function, args);
+        result.map_err(|e| e.to_string().into())
+    }
+}
+
+impl MetronomeModeBehavior for ScriptMode {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn icon(&self) -> &'static str {
+        "📜"
+    }
+
+    fn on_start(&mut self) {
+        self.state = Map::new().into();
+        self.bar = 0;
+        self.error = None;
+        if self.has_on_start {
+            // on_start has no way to report back; its errors show on the panel
+            if let Err(e) = self.call("on_start", ()) {
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn pattern(&self) -> Pattern {
+        Pattern::endless(self.name, Tempo::User, Clicks::Beats)
+    }
+
+    fn on_bar(&mut self, bar: u32, bpm: u32) -> Result<Option<BarChange>, Box<dyn Error>> {
+        self.bar = bar;
+        self.bpm = bpm;
+        let result = self.call("on_bar", (bar as rhai::INT,)).and_then(bar_change);
+        self.error = result.as_ref().err().map(|e| e.to_string());
+        result
+    }
+
+    // The panel shows the bar, tempo and on_bar's error, so it changes with a new bar or tempo
+    fn on_event(&mut self, event: &TimelineEvent) -> bool {
+        let changed = event.bpm != self.bpm || (event.beat == 0 && event.click == 0);
+        self.bpm = event.bpm;
+        changed
+    }
+
+    fn panel(&self) -> ModePanel {
+        let mut live = vec![format!("Bar {} at {} BPM", self.bar + 1, self.bpm)];
+        if let Some(error) = &self.error {
+            live.push(format!("⚠️ {}", error));
+        }
+        ModePanel {
+            status: format!("Script Mode - {}", self.name),
+            live,
+            notes: vec!["📜 Loaded from the modes folder in your config directory"],
+            ..ModePanel::default()
+        }
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH)
+        .set_max_string_size(MAX_COLLECTION_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .on_print(|_| {})
+        .on_debug(|_, _, _| {});
+    engine
+}
+
+// Reads what on_bar returned: nothing, or a map of changes
+fn bar_change(value: Dynamic) -> Result<Option<BarChange>, Box<dyn Error>> {
+    if value.is_unit() {
+        return Ok(None);
+    }
+    let map = value.try_cast::<Map>().ok_or("on_bar must return a map or nothing")?;
+
+    let mut change = BarChange::default();
+    for (key, value) in map {
+        match key.as_str() {
+            "bpm" => {
+                let bpm = match value.as_int() {
+                    Ok(bpm) => bpm as f64,
+                    Err(_) => value.as_float().map_err(|_| "bpm must be a number")?,
+                };
+                change.bpm = Some(bpm.round().clamp(MIN_BPM as f64, MAX_BPM as f64) as u32);
+            },
+            "accent_pattern" => {
+                let accents = value.try_cast::<Array>().ok_or("accent_pattern must be an array")?;
+                let accents = accents
+                    .into_iter()
+                    .map(|accent| accent.as_bool().map_err(|_| "accent_pattern must hold booleans"))
+                    .collect::<Result<Vec<bool>, _>>()?;
+                change.accent_pattern = Some(accents);
+            },
+            "mute" => change.mute = value.as_bool().map_err(|_| "mute must be a boolean")?,
+            other => return Err(format!("on_bar returned an unknown key `{}`", other).into()),
+        }
+    }
+    Ok(Some(change))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn returned_bpm(script: &str) -> Option<u32> {
+        let value = sandboxed_engine().eval::<Dynamic>(script).unwrap();
+        bar_change(value).unwrap().unwrap().bpm
+    }
+
+    #[test]
+    fn scripted_tempos_stay_in_the_apps_range() {
+        assert_eq!(returned_bpm("#{ bpm: 96 }"), Some(96));
+        assert_eq!(returned_bpm("#{ bpm: 120.6 }"), Some(121));
+        assert_eq!(returned_bpm("#{ bpm: 1000 }"), Some(MAX_BPM));
+        assert_eq!(returned_bpm("#{ bpm: -5 }"), Some(MIN_BPM));
+    }
+}
@@ -0,0 +1,375 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::utilities::kit::VoiceRole;
+use crate::utilities::mixer::MixerHandle;
+use crate::utilities::timeline::TimelineEvent;
+
+// General MIDI percussion channel, counting from 1
+const MIDI_DRUM_CHANNEL: u8 = 10;
+
+// A click handed to a sink ahead of time
+#[derive(Clone, Debug)]
+pub struct ScheduledBeat {
+    pub event: TimelineEvent,
+    // When the sink should output it: the click's due time minus the sink's latency
+    pub at: Instant,
+    // The engine's pick of sound and volume; muted clicks have none and a velocity of 0
+    pub samples: Option<Arc<[f32]>>,
+    pub volume: f32,
+}
+
+/// Somewhere clicks go: the audio output, a MIDI port, a log file. The engine
+/// hands every sink each click as soon as the slowest sink needs it, so
+/// `schedule` must not block; sinks that do I/O hand it to their own thread.
+pub trait BeatSink: Send {
+    fn name(&self) -> &str;
+
+    // How long the sink's output takes to be heard or seen; its beats are scheduled that much earlier
+    fn latency(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn schedule(&mut self, beat: ScheduledBeat);
+
+    // Called when the metronome stops
+    fn stop(&mut self) {}
+}
+
+/// Plays clicks through the engine's mixer.
+pub struct AudioSink {
+    mixer: MixerHandle,
+    latency: Duration,
+}
+
+impl AudioSink {
+    pub fn new(mixer: MixerHandle, latency: Duration) -> Self {
+        Self { mixer, latency }
+    }
+}
+
+impl BeatSink for AudioSink {
+    fn name(&self) -> &str {
+        "audio"
+    }
+
+    fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    fn schedule(&mut self, beat: ScheduledBeat) {
+        if let Some(samples) = &beat.samples {
+            self.mixer.play_in(samples, beat.volume, beat.at.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    // Clicks scheduled ahead would otherwise still sound after stopping
+    fn stop(&mut self) {
+        self.mixer.cancel_pending();
+    }
+}
+
+// What a threaded sink does with each beat once it is due
+trait Output: Send + 'static {
+    fn beat(&mut self, beat: &ScheduledBeat) -> io::Result<()>;
+
+    fn stop(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Runs an `Output` on its own thread, waking at each beat's `at`.
+// The first error is reported and turns the sink off.
+struct ThreadedSink {
+    name: String,
+    latency: Duration,
+    // None asks the output to stop
+    sender: Sender<Option<ScheduledBeat>>,
+}
+
+impl ThreadedSink {
+    fn spawn(name: String, latency: Duration, mut output: impl Output) -> Self {
+        let (sender, receiver) = mpsc::channel::<Option<ScheduledBeat>>();
+        let thread_name = name.clone();
+        std::thread::spawn(move || {
+            for message in receiver {
+                let result = match message {
+                    Some(beat) => {
+                        std::thread::sleep(beat.at.saturating_duration_since(Instant::now()));
+                        output.beat(&beat)
+                    },
+                    None => output.stop(),
+                };
+                if let Err(e) = result {
+                    eprintln!("Beat sink {} stopped: {}", thread_name, e);
+                    return;
+                }
+            }
+        });
+        Self { name, latency, sender }
+    }
+}
+
+impl BeatSink for ThreadedSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    fn schedule(&mut self, beat: ScheduledBeat) {
+        let _ = self.sender.send(Some(beat));
+    }
+
+    fn stop(&mut self) {
+        let _ = self.sender.send(None);
+    }
+}
+
+// Raw MIDI bytes, e.g. to /dev/snd/midiC1D0 or a FIFO read by a bridge
+struct MidiOutput {
+    device: File,
+    channel: u8,
+    // Note to release before the next one
+    sounding: Option<u8>,
+}
+
+impl MidiOutput {
+    fn release(&mut self) -> io::Result<()> {
+        if let Some(note) = self.sounding.take() {
+            self.device.write_all(&[0x80 | self.channel, note, 0])?;
+        }
+        Ok(())
+    }
+}
+
+impl Output for MidiOutput {
+    fn beat(&mut self, beat: &ScheduledBeat) -> io::Result<()> {
+        self.release()?;
+        if beat.event.velocity <= 0.0 {
+            return self.device.flush();
+        }
+        let note = midi_note(beat.event.voice);
+        let velocity = (beat.event.velocity * 84.0).round().clamp(1.0, 127.0) as u8;
+        self.device.write_all(&[0x90 | self.channel, note, velocity])?;
+        self.sounding = Some(note);
+        self.device.flush()
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.release()?;
+        self.device.flush()
+    }
+}
+
+// General MIDI percussion notes, woodblocks for the bar and a hi-hat between beats
+fn midi_note(role: VoiceRole) -> u8 {
+    match role {
+        VoiceRole::Downbeat | VoiceRole::Accent => 76,
+        VoiceRole::Beat => 77,
+        VoiceRole::Subdivision => 42,
+        VoiceRole::PolyVoice => 37,
+        VoiceRole::CountIn => 75,
+    }
+}
+
+// One line per click, flushed as it goes so the log survives a crash
+struct CsvOutput {
+    writer: BufWriter<File>,
+}
+
+impl Output for CsvOutput {
+    fn beat(&mut self, beat: &ScheduledBeat) -> io::Result<()> {
+        let event = &beat.event;
+        writeln!(
+            self.writer,
+            "{:.3},{:.3},{},{},{},{},{},{:.2},{:?}",
+            wall_clock(beat.at),
+            event.time,
+            event.bar + 1,
+            event.beat + 1,
+            event.click,
+            csv_field(&event.section),
+            event.bpm,
+            event.velocity,
+            event.voice,
+        )?;
+        self.writer.flush()
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Unix time in seconds of an instant close to now
+fn wall_clock(at: Instant) -> f64 {
+    let now = Instant::now();
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    match at.checked_duration_since(now) {
+        Some(ahead) => since_epoch + ahead.as_secs_f64(),
+        None => since_epoch - now.duration_since(at).as_secs_f64(),
+    }
+}
+
+// An OSC message per click over UDP: /metronome/beat bar beat click bpm velocity
+struct OscOutput {
+    socket: UdpSocket,
+    address: SocketAddr,
+}
+
+impl Output for OscOutput {
+    fn beat(&mut self, beat: &ScheduledBeat) -> io::Result<()> {
+        let event = &beat.event;
+        let mut packet = Vec::with_capacity(64);
+        osc_string(&mut packet, "/metronome/beat");
+        osc_string(&mut packet, ",iiiif");
+        for value in [event.bar as i32, event.beat as i32, event.click as i32, event.bpm as i32] {
+            packet.extend_from_slice(&value.to_be_bytes());
+        }
+        packet.extend_from_slice(&event.velocity.to_be_bytes());
+        self.socket.send_to(&packet, self.address).map(|_| ())
+    }
+}
+
+// OSC strings are null terminated and padded to four bytes
+fn osc_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(text.as_bytes());
+    packet.extend(std::iter::repeat_n(0, 4 - text.len() % 4));
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkOutput {
+    Midi {
+        device: PathBuf,
+        // 1-16
+        #[serde(default = "default_midi_channel")]
+        channel: u8,
+    },
+    Csv { path: PathBuf },
+    // host:port; broadcast addresses work too
+    Osc { address: String },
+}
+
+fn default_midi_channel() -> u8 {
+    MIDI_DRUM_CHANNEL
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub output: SinkOutput,
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl SinkConfig {
+    /// Parses a command-line sink: `midi:DEVICE`, `csv:PATH` or `osc:HOST:PORT`,
+    /// optionally followed by `@MS` of latency, e.g. `osc:127.0.0.1:9000@20`.
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let (spec, latency_ms) = match spec.rsplit_once('@') {
+            Some((spec, latency)) => {
+                let latency = latency.trim_end_matches("ms").parse().map_err(|_| format!("bad latency in '{}'", spec))?;
+                (spec, latency)
+            },
+            None => (spec, 0),
+        };
+        let (kind, target) = spec.split_once(':').ok_or_else(|| format!("expected kind:target, got '{}'", spec))?;
+        let output = match kind {
+            "midi" => SinkOutput::Midi { device: target.into(), channel: MIDI_DRUM_CHANNEL },
+            "csv" => SinkOutput::Csv { path: target.into() },
+            "osc" => SinkOutput::Osc { address: target.to_string() },
+            _ => return Err(format!("unknown sink '{}', expected midi, csv or osc", kind)),
+        };
+        Ok(Self { output, latency_ms, enabled: true })
+    }
+
+    pub fn open(&self) -> Result<Box<dyn BeatSink>, String> {
+        let latency = Duration::from_millis(self.latency_ms);
+        let sink = match &self.output {
+            SinkOutput::Midi { device, channel } => {
+                let file = OpenOptions::new().write(true).open(device).map_err(|e| format!("{}: {}", device.display(), e))?;
+                let output = MidiOutput { device: file, channel: channel.clamp(&1, &16) - 1, sounding: None };
+                ThreadedSink::spawn(format!("midi {}", device.display()), latency, output)
+            },
+            SinkOutput::Csv { path } => {
+                let new_file = !path.exists();
+                let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                let mut writer = BufWriter::new(file);
+                if new_file {
+                    writeln!(writer, "timestamp,time,bar,beat,click,section,bpm,velocity,voice").map_err(|e| e.to_string())?;
+                }
+                ThreadedSink::spawn(format!("csv {}", path.display()), latency, CsvOutput { writer })
+            },
+            SinkOutput::Osc { address } => {
+                let target = address
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addresses| addresses.next())
+                    .ok_or_else(|| format!("cannot resolve '{}'", address))?;
+                let bind: SocketAddr = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+                let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+                socket.set_broadcast(true).map_err(|e| e.to_string())?;
+                ThreadedSink::spawn(format!("osc {}", address), latency, OscOutput { socket, address: target })
+            },
+        };
+        Ok(Box::new(sink))
+    }
+}
+
+// What plays the clicks: the audio output with its latency, plus any other sinks
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SinkSettings {
+    #[serde(default)]
+    pub audio: AudioSettings,
+    #[serde(default, rename = "sink")]
+    pub sinks: Vec<SinkConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AudioSettings {
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { enabled: true, latency_ms: 0 }
+    }
+}
+
+pub fn sinks_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("metronome").join("sinks.toml"))
+}
+
+/// Reads an `[audio]` table and `[[sink]]` tables. A missing file means audio only.
+pub fn load_sinks(path: &Path) -> Result<SinkSettings, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SinkSettings::default()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
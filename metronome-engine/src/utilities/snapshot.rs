@@ -0,0 +1,35 @@
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+/// Writer side of a lock-free snapshot. The owner publishes whole immutable
+/// values and readers load the latest one without ever blocking the writer.
+pub struct SnapshotPublisher<T> {
+    shared: Arc<ArcSwap<T>>,
+    // The previously published snapshot, reused once no reader holds it
+    spare: Option<Arc<T>>,
+}
+
+impl<T: Clone> SnapshotPublisher<T> {
+    pub fn new(initial: T) -> (Self, Arc<ArcSwap<T>>) {
+        let shared = Arc::new(ArcSwap::from_pointee(initial));
+        let publisher = Self {
+            shared: Arc::clone(&shared),
+            spare: None,
+        };
+        (publisher, shared)
+    }
+
+    pub fn publish(&mut self, value: &T) {
+        let next = match self.spare.take() {
+            Some(mut spare) => match Arc::get_mut(&mut spare) {
+                Some(slot) => {
+                    *slot = value.clone();
+                    spare
+                }
+                None => Arc::new(value.clone()),
+            },
+            None => Arc::new(value.clone()),
+        };
+        self.spare = Some(self.shared.swap(next));
+    }
+}
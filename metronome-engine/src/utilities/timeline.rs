@@ -0,0 +1,680 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::utilities::kit::VoiceRole;
+use crate::utilities::mode::BarChange;
+
+pub const BEATS_PER_BAR: u32 = 4;
+
+// Accented clicks play this much louder than plain ones
+pub const ACCENT_VELOCITY: f32 = 1.5;
+
+/// Describes what a mode plays. A pattern is a list of sections that the
+/// timeline walks through in order, optionally looping back to one of them.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub sections: Vec<Section>,
+    // Section to continue with after the last one; None ends the timeline
+    pub loop_from: Option<usize>,
+    // None follows the metronome's meter
+    pub beats_per_bar: Option<u32>,
+    pub clicks: Clicks,
+    // Ends the timeline this many seconds after the start
+    pub duration: Option<f64>,
+    // Accents the first click after every multiple of this many seconds
+    pub accent_every: Option<f64>,
+}
+
+impl Pattern {
+    // A single endless section
+    pub fn endless(label: &str, tempo: Tempo, clicks: Clicks) -> Self {
+        Self {
+            sections: vec![Section::new(label, Length::Forever, tempo)],
+            loop_from: None,
+            beats_per_bar: None,
+            clicks,
+            duration: None,
+            accent_every: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub label: Arc<str>,
+    pub length: Length,
+    pub tempo: Tempo,
+}
+
+impl Section {
+    pub fn new(label: &str, length: Length, tempo: Tempo) -> Self {
+        Self {
+            label: label.into(),
+            length,
+            tempo,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Length {
+    Beats(u32),
+    // Picked at random between min and max each time the section starts
+    Seconds { min: f64, max: f64 },
+    Forever,
+}
+
+#[derive(Clone, Debug)]
+pub enum Tempo {
+    // Follows the tempo set by the user, including changes while playing
+    User,
+    Fixed(u32),
+    // Moves linearly from one tempo to the other over a section measured in beats
+    Ramp { from: u32, to: u32 },
+    // Picked at random each time the section starts
+    Random { min: u32, max: u32 },
+}
+
+// What is played within each beat
+#[derive(Clone, Debug)]
+pub enum Clicks {
+    Beats,
+    // One click per entry, with the flag marking accents
+    Subdivided(Vec<bool>),
+    // Every beat is checked against both rhythms; the bar is one primary cycle
+    Polyrhythm { primary: u32, secondary: u32, accent_primary: bool, accent_secondary: bool },
+}
+
+#[derive(Clone, Debug)]
+pub struct TimelineEvent {
+    // Seconds since the start of the timeline
+    pub time: f64,
+    pub voice: VoiceRole,
+    pub velocity: f32,
+    pub bar: u32,
+    pub beat: u32,
+    // Click within the beat, 0 on the beat itself
+    pub click: u32,
+    pub section: Arc<str>,
+    pub bpm: u32,
+}
+
+impl TimelineEvent {
+    pub fn is_accent(&self) -> bool {
+        self.velocity > 1.0
+    }
+}
+
+enum SectionEnd {
+    Beats(u32),
+    Time(f64),
+    Never,
+}
+
+/// The events of a pattern in playing order, generated one at a time so
+/// endless patterns cost nothing up front.
+pub struct Timeline {
+    pattern: Pattern,
+    user_bpm: u32,
+    section: usize,
+    // Sections entered so far, counting loops
+    sections_entered: u32,
+    section_tempo: Option<u32>,
+    section_end: SectionEnd,
+    section_beat: u32,
+    beat: u32,
+    click: u32,
+    // Time of the next click
+    time: f64,
+    last_time: f64,
+    // Beats between the last click and the next one
+    step: f64,
+    step_follows_user: bool,
+    ended: bool,
+    // Set by the mode's bar changes
+    tempo_override: Option<u32>,
+    beat_accents: Option<Vec<bool>>,
+    muted: bool,
+    // Whether `take_upcoming_bar` already handed out the bar starting at the next click
+    bar_taken: bool,
+}
+
+impl Timeline {
+    pub fn new(pattern: Pattern, user_bpm: u32) -> Self {
+        let ended = pattern.sections.is_empty();
+        let mut timeline = Self {
+            pattern,
+            user_bpm,
+            section: 0,
+            sections_entered: 0,
+            section_tempo: None,
+            section_end: SectionEnd::Never,
+            section_beat: 0,
+            beat: 0,
+            click: 0,
+            time: 0.0,
+            last_time: 0.0,
+            step: 1.0,
+            step_follows_user: false,
+            ended,
+            tempo_override: None,
+            beat_accents: None,
+            muted: false,
+            bar_taken: false,
+        };
+        if !ended {
+            timeline.enter_section(0);
+        }
+        timeline
+    }
+
+    // When the next event is due, or the timeline ends, in seconds since the start
+    pub fn next_time(&self) -> f64 {
+        match self.pattern.duration {
+            Some(duration) => self.time.min(duration),
+            None => self.time,
+        }
+    }
+
+    pub fn next_due(&self) -> Duration {
+        Duration::from_secs_f64(self.next_time().max(0.0))
+    }
+
+    // Spacing of the upcoming click in beats
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    // Re-times the pending click when it follows the user's tempo
+    // The user's tempo also replaces one a mode set with `apply_bar`, until the mode sets another
+    pub fn set_user_tempo(&mut self, bpm: u32) {
+        self.user_bpm = bpm;
+        if self.tempo_override.take().is_some() && !self.ended {
+            self.step_follows_user = matches!(self.pattern.sections[self.section].tempo, Tempo::User);
+        }
+        if self.step_follows_user {
+            self.time = self.last_time + self.step * 60.0 / bpm.max(1) as f64;
+        }
+    }
+
+    // Grows every time a section starts, so callers can spot changes across `next`
+    pub fn sections_entered(&self) -> u32 {
+        self.sections_entered
+    }
+
+    // Whether the current section picked its tempo at random
+    pub fn random_tempo(&self) -> bool {
+        self.tempo_override.is_none() && matches!(self.pattern.sections[self.section].tempo, Tempo::Random { .. })
+    }
+
+    // The bar whose first click comes next and the tempo it would play at, if a bar starts there
+    pub fn upcoming_bar(&self) -> Option<(u32, u32)> {
+        let beats_per_bar = self.beats_per_bar();
+        if self.ended || self.click != 0 || !self.beat.is_multiple_of(beats_per_bar) {
+            return None;
+        }
+        Some((self.beat / beats_per_bar, self.current_bpm()))
+    }
+
+    // Like `upcoming_bar`, but hands each bar out once, so it can be asked for early and often
+    pub fn take_upcoming_bar(&mut self) -> Option<(u32, u32)> {
+        if self.bar_taken {
+            return None;
+        }
+        let upcoming = self.upcoming_bar();
+        self.bar_taken = upcoming.is_some();
+        upcoming
+    }
+
+    // Applies a mode's change to the upcoming bar, see `MetronomeModeBehavior::on_bar`
+    pub fn apply_bar(&mut self, change: &BarChange) {
+        if let Some(bpm) = change.bpm {
+            self.tempo_override = Some(bpm.max(1));
+        }
+        if let Some(accents) = &change.accent_pattern {
+            self.beat_accents = Some(accents.clone()).filter(|accents| !accents.is_empty());
+        }
+        self.muted = change.mute;
+    }
+
+    // Skips the events before `time`, as if the pattern had played from the start
+    pub fn seek(&mut self, time: f64) {
+        while self.time < time && self.next().is_some() {}
+    }
+
+    // Picks up where `previous` left off after the pattern changed while playing:
+    // the same bar and beat, with the next click due when `previous` had it due.
+    // Sections are walked through at this pattern's lengths. A beat whose
+    // subdivision changed goes on from the next beat, and a beat past the end
+    // of a shorter bar starts the next bar.
+    pub fn continue_from(&mut self, previous: &Timeline) {
+        let mut beat = previous.beat;
+        let mut click = previous.click;
+        let mut time = previous.time;
+        let mut step = previous.step;
+        let previous_clicks = previous.clicks_per_beat();
+        if click != 0 && previous_clicks != self.clicks_per_beat() {
+            let rest = (previous_clicks - click) as f64 / previous_clicks as f64;
+            time += rest * 60.0 / previous.current_bpm() as f64;
+            step += rest;
+            beat += 1;
+            click = 0;
+        }
+
+        let (previous_bar, beats_per_bar) = (previous.beats_per_bar(), self.beats_per_bar());
+        let (mut bar, mut bar_beat) = (beat / previous_bar, beat % previous_bar);
+        if bar_beat >= beats_per_bar {
+            bar += 1;
+            bar_beat = 0;
+        }
+        let target = bar * beats_per_bar + bar_beat;
+
+        // Walking through the earlier beats must not run into the pattern's time limit
+        let duration = self.pattern.duration.take();
+        while self.beat < target && self.next().is_some() {}
+        self.pattern.duration = duration;
+        if self.ended {
+            return;
+        }
+        self.beat = target;
+        self.click = click;
+        self.time = time;
+        self.last_time = previous.last_time;
+        self.step = step;
+        self.step_follows_user = matches!(self.pattern.sections[self.section].tempo, Tempo::User);
+    }
+
+    fn enter_section(&mut self, index: usize) {
+        let mut rng = rand::thread_rng();
+        let section = &self.pattern.sections[index];
+        self.section = index;
+        self.sections_entered += 1;
+        self.section_beat = 0;
+        self.section_tempo = match section.tempo {
+            Tempo::Random { min, max } => Some(rng.gen_range(min.min(max)..=max)),
+            _ => None,
+        };
+        self.section_end = match section.length {
+            Length::Beats(beats) => SectionEnd::Beats(beats.max(1)),
+            Length::Seconds { min, max } => {
+                let seconds = if max > min { rng.gen_range(min..=max) } else { min };
+                SectionEnd::Time(self.time + seconds)
+            },
+            Length::Forever => SectionEnd::Never,
+        };
+    }
+
+    fn section_over(&self) -> bool {
+        match self.section_end {
+            SectionEnd::Beats(beats) => self.section_beat >= beats,
+            SectionEnd::Time(end) => self.time >= end,
+            SectionEnd::Never => false,
+        }
+    }
+
+    fn current_bpm(&self) -> u32 {
+        if let Some(bpm) = self.tempo_override {
+            return bpm;
+        }
+        let section = &self.pattern.sections[self.section];
+        let bpm = match section.tempo {
+            Tempo::User => self.user_bpm,
+            Tempo::Fixed(bpm) => bpm,
+            Tempo::Ramp { from, to } => match self.section_end {
+                SectionEnd::Beats(beats) => {
+                    let progress = self.section_beat as f32 / beats as f32;
+                    (from as f32 - (from as f32 - to as f32) * progress) as u32
+                },
+                _ => from,
+            },
+            Tempo::Random { .. } => self.section_tempo.unwrap_or(self.user_bpm),
+        };
+        bpm.max(1)
+    }
+
+    fn clicks_per_beat(&self) -> u32 {
+        match &self.pattern.clicks {
+            Clicks::Subdivided(accents) => (accents.len() as u32).max(1),
+            _ => 1,
+        }
+    }
+
+    fn beats_per_bar(&self) -> u32 {
+        self.pattern.beats_per_bar.unwrap_or(BEATS_PER_BAR).max(1)
+    }
+
+    fn voice(&self, timed_accent: bool) -> (VoiceRole, bool) {
+        click_voice(&self.pattern.clicks, self.beats_per_bar(), self.beat, self.click, timed_accent)
+    }
+}
+
+impl Iterator for Timeline {
+    type Item = TimelineEvent;
+
+    fn next(&mut self) -> Option<TimelineEvent> {
+        if self.ended {
+            return None;
+        }
+        if self.pattern.duration.is_some_and(|duration| self.time >= duration) {
+            self.ended = true;
+            return None;
+        }
+        // Sections only change on a beat
+        if self.click == 0 && self.section_over() {
+            let next = self.section + 1;
+            let next = if next < self.pattern.sections.len() {
+                Some(next)
+            } else {
+                self.pattern.loop_from.filter(|&index| index < self.pattern.sections.len())
+            };
+            match next {
+                Some(index) => self.enter_section(index),
+                None => {
+                    self.ended = true;
+                    return None;
+                },
+            }
+        }
+
+        let bpm = self.current_bpm();
+        let timed_accent = match self.pattern.accent_every {
+            Some(every) if every > 0.0 => {
+                self.beat == 0 && self.click == 0
+                    || (self.time / every).floor() > (self.last_time / every).floor()
+            },
+            _ => false,
+        };
+        let beats_per_bar = self.beats_per_bar();
+        let bar_accent = self.click == 0
+            && self.beat_accents.as_ref().is_some_and(|accents| {
+                accents[(self.beat % beats_per_bar) as usize % accents.len()]
+            });
+        let (voice, accent) = self.voice(timed_accent || bar_accent);
+
+        let event = TimelineEvent {
+            time: self.time,
+            voice,
+            velocity: match (self.muted, accent) {
+                (true, _) => 0.0,
+                (false, true) => ACCENT_VELOCITY,
+                (false, false) => 1.0,
+            },
+            bar: self.beat / beats_per_bar,
+            beat: self.beat % beats_per_bar,
+            click: self.click,
+            section: Arc::clone(&self.pattern.sections[self.section].label),
+            bpm,
+        };
+
+        let clicks_per_beat = self.clicks_per_beat();
+        self.last_time = self.time;
+        self.step = 1.0 / clicks_per_beat as f64;
+        self.step_follows_user =
+            self.tempo_override.is_none() && matches!(self.pattern.sections[self.section].tempo, Tempo::User);
+        self.time += self.step * 60.0 / bpm as f64;
+        self.click += 1;
+        self.bar_taken = false;
+        if self.click >= clicks_per_beat {
+            self.click = 0;
+            self.beat += 1;
+            self.section_beat += 1;
+            // Muting lasts one bar
+            if self.beat.is_multiple_of(beats_per_bar) {
+                self.muted = false;
+            }
+        }
+
+        Some(event)
+    }
+}
+
+// Voice and accent of a click, with `beat` counted from the start and `click` within the beat
+pub fn click_voice(clicks: &Clicks, beats_per_bar: u32, beat: u32, click: u32, timed_accent: bool) -> (VoiceRole, bool) {
+    let bar_position = beat % beats_per_bar.max(1);
+    match clicks {
+        Clicks::Beats => (beat_role(bar_position, timed_accent), timed_accent),
+        Clicks::Subdivided(accents) => {
+            let accent = timed_accent || accents.get(click as usize).copied().unwrap_or(false);
+            let role = if click == 0 {
+                beat_role(bar_position, accent)
+            } else if accent {
+                VoiceRole::Accent
+            } else {
+                VoiceRole::Subdivision
+            };
+            (role, accent)
+        },
+        Clicks::Polyrhythm { primary, secondary, accent_primary, accent_secondary } => {
+            let primary_hit = *primary > 0 && beat.is_multiple_of(*primary);
+            let secondary_hit = *secondary > 0 && beat.is_multiple_of(*secondary);
+            let accent = timed_accent || (primary_hit && *accent_primary);
+            let role = if primary_hit && secondary_hit {
+                VoiceRole::Downbeat
+            } else if secondary_hit && *accent_secondary {
+                VoiceRole::PolyVoice
+            } else if accent {
+                VoiceRole::Accent
+            } else {
+                VoiceRole::Beat
+            };
+            (role, accent)
+        },
+    }
+}
+
+pub fn beat_role(bar_position: u32, is_accent: bool) -> VoiceRole {
+    if bar_position == 0 {
+        VoiceRole::Downbeat
+    } else if is_accent {
+        VoiceRole::Accent
+    } else {
+        VoiceRole::Beat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(clicks: Clicks, beats_per_bar: u32) -> Pattern {
+        Pattern { beats_per_bar: Some(beats_per_bar), ..Pattern::endless("Test", Tempo::User, clicks) }
+    }
+
+    fn sections(sections: Vec<Section>) -> Pattern {
+        Pattern { sections, ..pattern(Clicks::Beats, 4) }
+    }
+
+    fn play(timeline: &mut Timeline, events: usize) -> Vec<TimelineEvent> {
+        timeline.take(events).collect()
+    }
+
+    // Bar, beat and click of an event
+    fn position(event: &TimelineEvent) -> (u32, u32, u32) {
+        (event.bar, event.beat, event.click)
+    }
+
+    #[test]
+    fn each_bar_is_handed_out_once_before_its_first_click() {
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        assert_eq!(timeline.take_upcoming_bar(), Some((0, 120)));
+        assert_eq!(timeline.take_upcoming_bar(), None);
+        play(&mut timeline, 1);
+        assert_eq!(timeline.take_upcoming_bar(), None);
+        play(&mut timeline, 3);
+        assert_eq!(timeline.upcoming_bar(), Some((1, 120)));
+        assert_eq!(timeline.take_upcoming_bar(), Some((1, 120)));
+        assert_eq!(timeline.take_upcoming_bar(), None);
+    }
+
+    #[test]
+    fn a_bar_change_starts_with_the_bar_it_was_asked_for() {
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        play(&mut timeline, 4);
+        timeline.take_upcoming_bar();
+        timeline.apply_bar(&BarChange { bpm: Some(60), accent_pattern: Some(vec![false, true]), mute: false });
+
+        let bar = play(&mut timeline, 4);
+        // The downbeat was already due at the old tempo; the clicks after it follow the new one
+        assert_eq!(bar.iter().map(|event| event.time).collect::<Vec<_>>(), [2.0, 3.0, 4.0, 5.0]);
+        assert!(bar.iter().all(|event| event.bpm == 60));
+        assert_eq!(bar.iter().map(|event| event.velocity).collect::<Vec<_>>(), [1.0, ACCENT_VELOCITY, 1.0, ACCENT_VELOCITY]);
+
+        timeline.take_upcoming_bar();
+        timeline.apply_bar(&BarChange { mute: true, ..BarChange::default() });
+        assert!(play(&mut timeline, 4).iter().all(|event| event.velocity == 0.0));
+        // Muting lasts one bar, the tempo holds
+        let next = play(&mut timeline, 1);
+        assert_eq!((next[0].velocity, next[0].bpm), (1.0, 60));
+    }
+
+    #[test]
+    fn seek_lands_on_the_first_click_at_or_after_the_time() {
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        timeline.seek(5.2);
+        let event = timeline.next().unwrap();
+        assert_eq!((position(&event), event.time), ((2, 3, 0), 5.5));
+
+        let mut timeline = Timeline::new(pattern(Clicks::Subdivided(vec![true, false]), 4), 120);
+        timeline.seek(1.1);
+        let event = timeline.next().unwrap();
+        assert_eq!((position(&event), event.time), ((0, 2, 1), 1.25));
+    }
+
+    #[test]
+    fn user_tempo_retimes_the_pending_click_mid_bar() {
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        play(&mut timeline, 2);
+        timeline.set_user_tempo(60);
+        let rest = play(&mut timeline, 2);
+        assert_eq!(rest.iter().map(|event| (position(event), event.time, event.bpm)).collect::<Vec<_>>(), [
+            ((0, 2, 0), 1.5, 60),
+            ((0, 3, 0), 2.5, 60)
+        ]);
+    }
+
+    #[test]
+    fn user_tempo_replaces_a_bar_change_but_not_a_fixed_tempo() {
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        timeline.take_upcoming_bar();
+        timeline.apply_bar(&BarChange { bpm: Some(200), ..BarChange::default() });
+        play(&mut timeline, 1);
+        timeline.set_user_tempo(90);
+        assert_eq!(play(&mut timeline, 1)[0].bpm, 90);
+
+        let mut timeline = Timeline::new(sections(vec![Section::new("Fixed", Length::Forever, Tempo::Fixed(100))]), 120);
+        play(&mut timeline, 1);
+        timeline.set_user_tempo(60);
+        let event = timeline.next().unwrap();
+        assert_eq!((event.time, event.bpm), (0.6, 100));
+    }
+
+    #[test]
+    fn seconds_sections_end_on_the_first_beat_past_their_length() {
+        let mut timeline = Timeline::new(
+            sections(vec![
+                Section::new("Warm-up", Length::Seconds { min: 2.5, max: 2.5 }, Tempo::Fixed(60)),
+                Section::new("Fast", Length::Beats(2), Tempo::Fixed(120)),
+            ]),
+            120,
+        );
+        let events: Vec<(f64, String)> = timeline.by_ref().map(|event| (event.time, event.section.to_string())).collect();
+        assert_eq!(events, [
+            (0.0, "Warm-up".to_string()),
+            (1.0, "Warm-up".to_string()),
+            (2.0, "Warm-up".to_string()),
+            (3.0, "Fast".to_string()),
+            (3.5, "Fast".to_string()),
+        ]);
+        assert_eq!(timeline.sections_entered(), 2);
+    }
+
+    #[test]
+    fn sections_loop_back_and_durations_cut_them_short() {
+        let mut looping = sections(vec![
+            Section::new("Intro", Length::Beats(1), Tempo::User),
+            Section::new("Loop", Length::Beats(1), Tempo::User),
+        ]);
+        looping.loop_from = Some(1);
+        let labels: Vec<String> = Timeline::new(looping, 120).take(3).map(|event| event.section.to_string()).collect();
+        assert_eq!(labels, ["Intro", "Loop", "Loop"]);
+
+        let timed = Pattern { duration: Some(1.2), ..pattern(Clicks::Beats, 4) };
+        let times: Vec<f64> = Timeline::new(timed, 120).map(|event| event.time).collect();
+        assert_eq!(times, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn subdivided_clicks_follow_their_accents() {
+        let clicks = Clicks::Subdivided(vec![true, false, true, false]);
+        assert_eq!(click_voice(&clicks, 4, 0, 0, false), (VoiceRole::Downbeat, true));
+        assert_eq!(click_voice(&clicks, 4, 1, 0, false), (VoiceRole::Accent, true));
+        assert_eq!(click_voice(&clicks, 4, 1, 1, false), (VoiceRole::Subdivision, false));
+        assert_eq!(click_voice(&clicks, 4, 1, 2, false), (VoiceRole::Accent, true));
+        assert_eq!(click_voice(&clicks, 4, 5, 3, true), (VoiceRole::Accent, true));
+
+        let plain = Clicks::Subdivided(vec![false, false]);
+        assert_eq!(click_voice(&plain, 4, 4, 0, false), (VoiceRole::Downbeat, false));
+        assert_eq!(click_voice(&plain, 4, 2, 0, false), (VoiceRole::Beat, false));
+    }
+
+    #[test]
+    fn continuing_after_a_tempo_change_keeps_the_bar() {
+        let mut previous = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        play(&mut previous, 6);
+        previous.set_user_tempo(60);
+
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 4), 60);
+        timeline.continue_from(&previous);
+        let events = play(&mut timeline, 3);
+        assert_eq!(events.iter().map(|event| (position(event), event.time)).collect::<Vec<_>>(), [
+            ((1, 2, 0), 3.5),
+            ((1, 3, 0), 4.5),
+            ((2, 0, 0), 5.5)
+        ]);
+    }
+
+    #[test]
+    fn continuing_in_a_shorter_bar_starts_the_next_one() {
+        let mut previous = Timeline::new(pattern(Clicks::Beats, 4), 120);
+        play(&mut previous, 3);
+
+        let mut timeline = Timeline::new(pattern(Clicks::Beats, 3), 120);
+        timeline.continue_from(&previous);
+        assert_eq!(timeline.upcoming_bar(), Some((1, 120)));
+        let event = timeline.next().unwrap();
+        assert_eq!((position(&event), event.time), ((1, 0, 0), 1.5));
+    }
+
+    #[test]
+    fn continuing_with_other_subdivisions_waits_for_the_next_beat() {
+        let mut previous = Timeline::new(pattern(Clicks::Subdivided(vec![true, false]), 4), 120);
+        play(&mut previous, 3);
+
+        let mut timeline = Timeline::new(pattern(Clicks::Subdivided(vec![true, false, false]), 4), 120);
+        timeline.continue_from(&previous);
+        let events = play(&mut timeline, 2);
+        assert_eq!(events.iter().map(|event| (position(event), event.time)).collect::<Vec<_>>(), [
+            ((0, 2, 0), 1.0),
+            ((0, 2, 1), 1.0 + 0.5 / 3.0)
+        ]);
+    }
+
+    #[test]
+    fn continuing_walks_through_the_sections() {
+        let practice = || {
+            sections(vec![
+                Section::new("Slow", Length::Beats(4), Tempo::Fixed(60)),
+                Section::new("Fast", Length::Beats(4), Tempo::Fixed(120)),
+            ])
+        };
+        let mut previous = Timeline::new(practice(), 100);
+        play(&mut previous, 5);
+
+        let mut timeline = Timeline::new(practice(), 100);
+        timeline.continue_from(&previous);
+        let event = timeline.next().unwrap();
+        assert_eq!((position(&event), &*event.section, event.time), ((1, 1, 0), "Fast", 4.5));
+    }
+}
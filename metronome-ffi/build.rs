@@ -0,0 +1,16 @@
+use std::env;
+use std::path::PathBuf;
+
+// Regenerates include/metronome.h whenever the exported API changes
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    match cbindgen::generate(&crate_dir) {
+        Ok(bindings) => {
+            bindings.write_to_file(crate_dir.join("include/metronome.h"));
+        },
+        Err(error) => println!("cargo:warning=Failed to generate metronome.h: {}", error),
+    }
+}
@@ -0,0 +1,126 @@
+#ifndef METRONOME_H
+#define METRONOME_H
+
+/* Generated by cbindgen from src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Sample rate of rendered buffers and built-in sounds.
+#define METRONOME_SAMPLE_RATE 44100
+
+// A live metronome playing through the default audio output.
+typedef struct Metronome Metronome;
+
+// Renders clicks into buffers without an audio device.
+typedef struct MetronomeRenderer MetronomeRenderer;
+
+// Settings for a new metronome or renderer; start from metronome_default_config().
+typedef struct MetronomeConfig {
+  // 30-300
+  uint32_t bpm;
+  uint32_t beats_per_bar;
+  // 0-100
+  uint32_t volume;
+  // 0 beep, 1 kick, 2 click, 3 cowbell, 4 hi-hat, 5 woodblock, 6 triangle, 7 square
+  uint32_t sound;
+  // Index of the built-in mode, 0 is the standard metronome
+  uint32_t mode;
+  bool count_in;
+} MetronomeConfig;
+
+// One click, as passed to a MetronomeBeatCallback.
+typedef struct MetronomeBeat {
+  // Seconds since the first click
+  double time;
+  // Frame within the buffer being rendered; always 0 for live playback
+  size_t frame;
+  uint32_t bar;
+  uint32_t beat;
+  // Click within the beat, 0 on the beat itself
+  uint32_t click;
+  uint32_t bpm;
+  float velocity;
+  bool accent;
+} MetronomeBeat;
+
+// Called for every click. Live metronomes call it from the engine's timing
+// thread, so it must return quickly and must not free the metronome.
+typedef void (*MetronomeBeatCallback)(const struct MetronomeBeat *beat, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct MetronomeConfig metronome_default_config(void);
+
+// The last error on this thread as a string owned by the library, or NULL.
+const char *metronome_last_error(void);
+
+// Opens the default audio output and returns a stopped metronome, or NULL
+// with metronome_last_error() set. Free it with metronome_free().
+struct Metronome *metronome_new(const struct MetronomeConfig *config);
+
+// Stops the metronome and closes its audio output.
+void metronome_free(struct Metronome *metronome);
+
+void metronome_start(const struct Metronome *metronome);
+
+void metronome_stop(const struct Metronome *metronome);
+
+// Held to 30-300 BPM.
+void metronome_set_bpm(const struct Metronome *metronome, uint32_t bpm);
+
+// 0-100; louder is held to 100.
+void metronome_set_volume(const struct Metronome *metronome, uint32_t volume);
+
+void metronome_set_sound(const struct Metronome *metronome, uint32_t sound);
+
+void metronome_set_meter(const struct Metronome *metronome, uint32_t beats_per_bar);
+
+void metronome_set_mode(const struct Metronome *metronome, uint32_t mode);
+
+void metronome_set_count_in(const struct Metronome *metronome, bool enabled);
+
+// Replaces the beat callback; NULL removes it.
+void metronome_set_beat_callback(const struct Metronome *metronome,
+                                 MetronomeBeatCallback callback,
+                                 void *user_data);
+
+// Returns a renderer for the configured mode. Count-in is not rendered.
+// Free it with metronome_renderer_free().
+struct MetronomeRenderer *metronome_renderer_new(const struct MetronomeConfig *config);
+
+void metronome_renderer_free(struct MetronomeRenderer *renderer);
+
+// Held to 30-300 BPM.
+void metronome_renderer_set_bpm(struct MetronomeRenderer *renderer, uint32_t bpm);
+
+// Renders the next `frames` mono samples into `output` and calls `callback`
+// for every click in them. Returns the number of frames rendered, which is
+// less than `frames` once the mode has ended; the rest of `output` is zeroed.
+//
+// # Safety
+// `output` must point to at least `frames` writable floats.
+size_t metronome_renderer_render(struct MetronomeRenderer *renderer,
+                                 float *output,
+                                 size_t frames,
+                                 MetronomeBeatCallback callback,
+                                 void *user_data);
+
+// Copies up to `capacity` samples of a built-in sound into `output` and
+// returns its full length, or 0 for an unknown sound. Passing NULL only
+// returns the length. Sound 8 is the celebration fanfare.
+//
+// # Safety
+// `output` must be NULL or point to at least `capacity` writable floats.
+size_t metronome_sound_samples(uint32_t sound, float *output, size_t capacity);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* METRONOME_H */
@@ -0,0 +1,380 @@
+//! C interface to the metronome engine. The header in include/metronome.h is
+//! generated from this file by cbindgen on every build.
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char, c_void};
+use std::sync::{Arc, Mutex};
+
+use metronome_engine::utilities::mixer::SAMPLE_RATE;
+use metronome_engine::utilities::timeline::TimelineEvent;
+use metronome_engine::{MetronomeBuilder, MetronomeCommand, MetronomeEvent, Renderer, Sound, builtin_sound};
+
+/// Sample rate of rendered buffers and built-in sounds.
+pub const METRONOME_SAMPLE_RATE: u32 = 44100;
+
+// Spelled out above so cbindgen can write it into the header
+const _: () = assert!(METRONOME_SAMPLE_RATE == SAMPLE_RATE);
+
+/// Settings for a new metronome or renderer; start from metronome_default_config().
+#[repr(C)]
+pub struct MetronomeConfig {
+    /// 30-300
+    pub bpm: u32,
+    pub beats_per_bar: u32,
+    /// 0-100
+    pub volume: u32,
+    /// 0 beep, 1 kick, 2 click, 3 cowbell, 4 hi-hat, 5 woodblock, 6 triangle, 7 square
+    pub sound: u32,
+    /// Index of the built-in mode, 0 is the standard metronome
+    pub mode: u32,
+    pub count_in: bool,
+}
+
+/// One click, as passed to a MetronomeBeatCallback.
+#[repr(C)]
+pub struct MetronomeBeat {
+    /// Seconds since the first click
+    pub time: f64,
+    /// Frame within the buffer being rendered; always 0 for live playback
+    pub frame: usize,
+    pub bar: u32,
+    pub beat: u32,
+    /// Click within the beat, 0 on the beat itself
+    pub click: u32,
+    pub bpm: u32,
+    pub velocity: f32,
+    pub accent: bool,
+}
+
+impl MetronomeBeat {
+    fn new(event: &TimelineEvent, frame: usize) -> Self {
+        Self {
+            time: event.time,
+            frame,
+            bar: event.bar,
+            beat: event.beat,
+            click: event.click,
+            bpm: event.bpm,
+            velocity: event.velocity,
+            accent: event.is_accent(),
+        }
+    }
+}
+
+/// Called for every click. Live metronomes call it from the engine's timing
+/// thread, so it must return quickly and must not free the metronome.
+pub type MetronomeBeatCallback = Option<extern "C" fn(beat: &MetronomeBeat, user_data: *mut c_void)>;
+
+// The user data pointer belongs to the caller, who promises it can be used from the engine thread
+#[derive(Clone, Copy)]
+struct BeatCallback {
+    callback: extern "C" fn(&MetronomeBeat, *mut c_void),
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for BeatCallback {}
+
+/// A live metronome playing through the default audio output.
+pub struct Metronome {
+    inner: metronome_engine::Metronome,
+    beat_callback: Arc<Mutex<Option<BeatCallback>>>,
+}
+
+/// Renders clicks into buffers without an audio device.
+pub struct MetronomeRenderer {
+    inner: Renderer,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+fn builder(config: &MetronomeConfig) -> MetronomeBuilder {
+    metronome_engine::Metronome::builder()
+        .bpm(config.bpm)
+        .meter(config.beats_per_bar)
+        .volume(config.volume)
+        .sound(Sound::from_key(config.sound).unwrap_or(Sound::Beep))
+        .count_in(config.count_in)
+        .select_mode(config.mode as usize)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_default_config() -> MetronomeConfig {
+    MetronomeConfig {
+        bpm: 120,
+        beats_per_bar: 4,
+        volume: 80,
+        sound: 0,
+        mode: 0,
+        count_in: false,
+    }
+}
+
+/// The last error on this thread as a string owned by the library, or NULL.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Opens the default audio output and returns a stopped metronome, or NULL
+/// with metronome_last_error() set. Free it with metronome_free().
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_new(config: Option<&MetronomeConfig>) -> Option<Box<Metronome>> {
+    let default_config = metronome_default_config();
+    let inner = match builder(config.unwrap_or(&default_config)).build() {
+        Ok(inner) => inner,
+        Err(error) => {
+            set_last_error(&error.to_string());
+            return None;
+        },
+    };
+
+    let beat_callback: Arc<Mutex<Option<BeatCallback>>> = Arc::default();
+    let engine_callback = beat_callback.clone();
+    inner.on_event(move |event| {
+        // Copied out so the callback runs unlocked and may replace itself
+        if let MetronomeEvent::Beat { event, .. } = event
+            && let Some(callback) = engine_callback.lock().ok().and_then(|callback| *callback)
+        {
+            (callback.callback)(&MetronomeBeat::new(event, 0), callback.user_data);
+        }
+        true
+    });
+
+    Some(Box::new(Metronome { inner, beat_callback }))
+}
+
+/// Stops the metronome and closes its audio output.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_free(metronome: Option<Box<Metronome>>) {
+    drop(metronome);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_start(metronome: Option<&Metronome>) {
+    if let Some(metronome) = metronome {
+        metronome.inner.start();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_stop(metronome: Option<&Metronome>) {
+    if let Some(metronome) = metronome {
+        metronome.inner.stop();
+    }
+}
+
+fn send(metronome: Option<&Metronome>, command: MetronomeCommand) {
+    if let Some(metronome) = metronome {
+        metronome.inner.send(command);
+    }
+}
+
+/// Held to 30-300 BPM.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_bpm(metronome: Option<&Metronome>, bpm: u32) {
+    send(metronome, MetronomeCommand::ChangeBpm(bpm));
+}
+
+/// 0-100; louder is held to 100.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_volume(metronome: Option<&Metronome>, volume: u32) {
+    send(metronome, MetronomeCommand::ChangeVolume(volume));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_sound(metronome: Option<&Metronome>, sound: u32) {
+    send(metronome, MetronomeCommand::ChangeSoundType(sound));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_meter(metronome: Option<&Metronome>, beats_per_bar: u32) {
+    send(metronome, MetronomeCommand::ChangeMeter(beats_per_bar));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_mode(metronome: Option<&Metronome>, mode: u32) {
+    send(metronome, MetronomeCommand::ChangeMode(mode as usize));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_count_in(metronome: Option<&Metronome>, enabled: bool) {
+    send(metronome, MetronomeCommand::SetCountIn(enabled));
+}
+
+/// Replaces the beat callback; NULL removes it.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_set_beat_callback(
+    metronome: Option<&Metronome>,
+    callback: MetronomeBeatCallback,
+    user_data: *mut c_void,
+) {
+    let Some(metronome) = metronome else { return };
+    if let Ok(mut beat_callback) = metronome.beat_callback.lock() {
+        *beat_callback = callback.map(|callback| BeatCallback { callback, user_data });
+    }
+}
+
+/// Returns a renderer for the configured mode. Count-in is not rendered.
+/// Free it with metronome_renderer_free().
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_renderer_new(config: Option<&MetronomeConfig>) -> Box<MetronomeRenderer> {
+    let default_config = metronome_default_config();
+    let inner = builder(config.unwrap_or(&default_config)).renderer();
+    Box::new(MetronomeRenderer { inner })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_renderer_free(renderer: Option<Box<MetronomeRenderer>>) {
+    drop(renderer);
+}
+
+/// Held to 30-300 BPM.
+#[unsafe(no_mangle)]
+pub extern "C" fn metronome_renderer_set_bpm(renderer: Option<&mut MetronomeRenderer>, bpm: u32) {
+    if let Some(renderer) = renderer {
+        renderer.inner.set_tempo(bpm);
+    }
+}
+
+/// Renders the next `frames` mono samples into `output` and calls `callback`
+/// for every click in them. Returns the number of frames rendered, which is
+/// less than `frames` once the mode has ended; the rest of `output` is zeroed.
+///
+/// # Safety
+/// `output` must point to at least `frames` writable floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metronome_renderer_render(
+    renderer: Option<&mut MetronomeRenderer>,
+    output: *mut f32,
+    frames: usize,
+    callback: MetronomeBeatCallback,
+    user_data: *mut c_void,
+) -> usize {
+    let Some(renderer) = renderer else { return 0 };
+    if output.is_null() {
+        return 0;
+    }
+    let output = unsafe { std::slice::from_raw_parts_mut(output, frames) };
+
+    renderer.inner.render(output, |event, frame| {
+        if let Some(callback) = callback {
+            callback(&MetronomeBeat::new(event, frame), user_data);
+        }
+    })
+}
+
+/// Copies up to `capacity` samples of a built-in sound into `output` and
+/// returns its full length, or 0 for an unknown sound. Passing NULL only
+/// returns the length. Sound 8 is the celebration fanfare.
+///
+/// # Safety
+/// `output` must be NULL or point to at least `capacity` writable floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metronome_sound_samples(sound: u32, output: *mut f32, capacity: usize) -> usize {
+    let Some(samples) = builtin_sound(sound) else { return 0 };
+    if !output.is_null() {
+        let output = unsafe { std::slice::from_raw_parts_mut(output, capacity) };
+        let copied = capacity.min(samples.len());
+        output[..copied].copy_from_slice(&samples[..copied]);
+    }
+    samples.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Countdown, which ends after its 60 seconds
+    const COUNTDOWN_MODE: u32 = 6;
+
+    extern "C" fn count_beats(_beat: &MetronomeBeat, user_data: *mut c_void) {
+        unsafe { *(user_data as *mut u32) += 1 };
+    }
+
+    fn render(renderer: &mut MetronomeRenderer, output: &mut [f32], beats: &mut u32) -> usize {
+        let user_data = beats as *mut u32 as *mut c_void;
+        unsafe { metronome_renderer_render(Some(renderer), output.as_mut_ptr(), output.len(), Some(count_beats), user_data) }
+    }
+
+    #[test]
+    fn a_null_config_renders_the_default_settings() {
+        let mut renderer = metronome_renderer_new(None);
+        let mut output = vec![0.0; 2 * SAMPLE_RATE as usize];
+        let mut beats = 0;
+        assert_eq!(render(&mut renderer, &mut output, &mut beats), output.len());
+        // Two seconds at 120 BPM
+        assert_eq!(beats, 4);
+        metronome_renderer_free(Some(renderer));
+    }
+
+    #[test]
+    fn a_null_config_opens_the_default_settings() {
+        // Without an audio device this fails, and says why
+        match metronome_new(None) {
+            Some(metronome) => {
+                assert_eq!(metronome.inner.snapshot().bpm, 120);
+                metronome_free(Some(metronome));
+            },
+            None => assert!(!metronome_last_error().is_null()),
+        }
+    }
+
+    #[test]
+    fn null_handles_are_ignored() {
+        let mut output = [0.0; 16];
+        let rendered = unsafe { metronome_renderer_render(None, output.as_mut_ptr(), output.len(), None, std::ptr::null_mut()) };
+        assert_eq!(rendered, 0);
+        metronome_renderer_set_bpm(None, 90);
+        metronome_set_bpm(None, 90);
+        metronome_free(None);
+    }
+
+    #[test]
+    fn rendering_past_the_end_zeroes_the_rest() {
+        let config = MetronomeConfig { mode: COUNTDOWN_MODE, ..metronome_default_config() };
+        let mut renderer = metronome_renderer_new(Some(&config));
+        let mut output = vec![0.0; 10 * SAMPLE_RATE as usize];
+        let mut beats = 0;
+        let mut total = 0;
+        loop {
+            output.fill(1.0);
+            let rendered = render(&mut renderer, &mut output, &mut beats);
+            total += rendered;
+            if rendered < output.len() {
+                assert!(output[rendered..].iter().all(|&sample| sample == 0.0));
+                break;
+            }
+        }
+        assert!(total >= 60 * SAMPLE_RATE as usize, "ended after {} frames", total);
+        assert_eq!(beats, 120);
+
+        // An ended renderer stays ended
+        output.fill(1.0);
+        assert_eq!(render(&mut renderer, &mut output, &mut beats), 0);
+        assert!(output.iter().all(|&sample| sample == 0.0));
+        assert_eq!(beats, 120);
+    }
+
+    #[test]
+    fn sound_samples_copy_at_most_the_capacity() {
+        let kick = builtin_sound(Sound::Kick.key()).unwrap();
+        let length = unsafe { metronome_sound_samples(Sound::Kick.key(), std::ptr::null_mut(), 0) };
+        assert_eq!(length, kick.len());
+
+        let mut output = vec![9.0; 20];
+        let copied = unsafe { metronome_sound_samples(Sound::Kick.key(), output.as_mut_ptr(), 16) };
+        assert_eq!(copied, kick.len());
+        assert_eq!(output[..16], kick[..16]);
+        assert!(output[16..].iter().all(|&sample| sample == 9.0));
+
+        // Custom keys have no built-in samples
+        assert_eq!(unsafe { metronome_sound_samples(Sound::Custom(0).key(), output.as_mut_ptr(), 16) }, 0);
+    }
+}
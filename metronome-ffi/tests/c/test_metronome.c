@@ -0,0 +1,132 @@
+// Exercises the C interface: built-in sounds, offline rendering with beat
+// callbacks, and a live metronome when an audio output is available.
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+#include "metronome.h"
+
+#define CHECK(condition)                                                   \
+    do {                                                                   \
+        if (!(condition)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #condition);                                           \
+            exit(1);                                                       \
+        }                                                                  \
+    } while (0)
+
+#define MAX_BEATS 64
+
+typedef struct {
+    size_t count;
+    size_t offset;
+    size_t frames[MAX_BEATS];
+    MetronomeBeat beats[MAX_BEATS];
+} BeatLog;
+
+static void log_beat(const MetronomeBeat *beat, void *user_data) {
+    BeatLog *log = user_data;
+    if (log->count < MAX_BEATS) {
+        log->frames[log->count] = log->offset + beat->frame;
+        log->beats[log->count] = *beat;
+    }
+    log->count++;
+}
+
+static void test_sounds(void) {
+    for (uint32_t sound = 0; sound <= 8; sound++) {
+        size_t length = metronome_sound_samples(sound, NULL, 0);
+        CHECK(length > 0);
+
+        float *samples = malloc(length * sizeof(float));
+        CHECK(metronome_sound_samples(sound, samples, length) == length);
+        float peak = 0.0f;
+        for (size_t i = 0; i < length; i++) {
+            peak = fmaxf(peak, fabsf(samples[i]));
+        }
+        CHECK(peak > 0.0f);
+        free(samples);
+    }
+    CHECK(metronome_sound_samples(1000, NULL, 0) == 0);
+}
+
+static void test_render(void) {
+    MetronomeConfig config = metronome_default_config();
+    config.bpm = 120;
+    config.beats_per_bar = 3;
+    MetronomeRenderer *renderer = metronome_renderer_new(&config);
+
+    // Two seconds in odd-sized blocks, as an audio callback would ask for them
+    BeatLog log = {0};
+    float block[1000];
+    size_t total = 2 * METRONOME_SAMPLE_RATE;
+    float peak = 0.0f;
+    while (log.offset < total) {
+        size_t frames = total - log.offset < 1000 ? total - log.offset : 1000;
+        CHECK(metronome_renderer_render(renderer, block, frames, log_beat, &log) == frames);
+        for (size_t i = 0; i < frames; i++) {
+            peak = fmaxf(peak, fabsf(block[i]));
+        }
+        log.offset += frames;
+    }
+    CHECK(peak > 0.0f);
+
+    // Quarter notes at 120 BPM fall every 22050 frames, with the downbeat on frame 0
+    CHECK(log.count == 4);
+    for (size_t i = 0; i < log.count; i++) {
+        CHECK(log.frames[i] == i * METRONOME_SAMPLE_RATE / 2);
+        CHECK(log.beats[i].beat == i % 3);
+        CHECK(log.beats[i].bar == i / 3);
+        CHECK(log.beats[i].bpm == 120);
+    }
+
+    metronome_renderer_free(renderer);
+}
+
+static void test_modes(void) {
+    // Every built-in mode clicks within its first two seconds
+    for (uint32_t mode = 0; mode < 7; mode++) {
+        MetronomeConfig config = metronome_default_config();
+        config.mode = mode;
+        MetronomeRenderer *renderer = metronome_renderer_new(&config);
+
+        BeatLog log = {0};
+        size_t frames = 2 * METRONOME_SAMPLE_RATE;
+        float *buffer = malloc(frames * sizeof(float));
+        CHECK(metronome_renderer_render(renderer, buffer, frames, log_beat, &log) == frames);
+        CHECK(log.count > 0);
+        CHECK(log.frames[0] == 0);
+
+        free(buffer);
+        metronome_renderer_free(renderer);
+    }
+}
+
+static void test_live(void) {
+    Metronome *metronome = metronome_new(NULL);
+    if (metronome == NULL) {
+        printf("skipping live test: %s\n", metronome_last_error());
+        return;
+    }
+
+    BeatLog log = {0};
+    metronome_set_beat_callback(metronome, log_beat, &log);
+    metronome_set_bpm(metronome, 240);
+    metronome_start(metronome);
+    usleep(1200 * 1000);
+    metronome_stop(metronome);
+    metronome_set_beat_callback(metronome, NULL, NULL);
+    metronome_free(metronome);
+
+    CHECK(log.count >= 2);
+}
+
+int main(void) {
+    test_sounds();
+    test_render();
+    test_modes();
+    test_live();
+    printf("all tests passed\n");
+    return 0;
+}